@@ -1,4 +1,11 @@
+import * as THREE from "three";
 import { Renderer } from "./renderer";
+import {
+	compressGlbWithDraco,
+	exportGlb,
+	GltfExportOptions,
+} from "./gltf_export";
+import { export3mf, exportPly } from "./model_export";
 
 export class SchematicExporter {
 	constructor(private renderer: Renderer) {}
@@ -7,4 +14,23 @@ export class SchematicExporter {
 		const obj = this.renderer.exportUsdz();
 		return obj;
 	}
+
+	getSchematicMeshes() {
+		return this.renderer.scene.children.filter(
+			(child): child is THREE.Mesh => child instanceof THREE.Mesh
+		);
+	}
+
+	async exportGlb(options: GltfExportOptions = {}) {
+		const glb = await exportGlb(this.getSchematicMeshes());
+		return options.draco ? compressGlbWithDraco(glb, options) : glb;
+	}
+
+	exportPly() {
+		return exportPly(this.getSchematicMeshes());
+	}
+
+	async export3mf(options: { blockSize?: number } = {}) {
+		return export3mf(this.getSchematicMeshes(), options);
+	}
 }
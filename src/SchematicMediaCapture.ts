@@ -1,5 +1,6 @@
 import * as THREE from "three";
 import { Renderer } from "./renderer";
+import { logger } from "./logger";
 
 export class SchematicMediaCapture {
 	renderer: Renderer;
@@ -23,7 +24,7 @@ export class SchematicMediaCapture {
 		const elevation = Math.asin(
 			(this.renderer.camera.position.y - centerPosition.y) / distance
 		);
-		console.log("centerPosition", centerPosition);
+		logger.debug("centerPosition", centerPosition);
 		return { centerPosition, distance, elevation };
 	}
 
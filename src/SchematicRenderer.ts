@@ -1,14 +1,96 @@
 import * as THREE from "three";
-import { TagMap } from "@enginehub/nbt-ts";
 import { Renderer } from "./renderer";
 import { ResourceLoader } from "./resource_loader";
 import { WorldMeshBuilder } from "./world_mesh_builder";
-import { parseNbtFromBase64 } from "./utils";
-import { loadSchematic } from "@enginehub/schematicjs";
+import { loadSchematicFromBase64 } from "./schematic_loader";
 import { SchematicRendererGUI } from "./SchematicRendererGUI";
 import { SchematicRendererCore } from "./SchematicRendererCore";
 import { SchematicMediaCapture } from "./SchematicMediaCapture";
 import { SchematicExporter } from "./SchematicExporter";
+import type { GltfExportOptions } from "./gltf_export";
+import { exportQb, exportVox } from "./voxel_export";
+import {
+	createTextureColorMapping,
+	getPaletteFaceColors,
+} from "./texture_colors";
+import { logger, LogLevel, LogSink } from "./logger";
+import { BenchmarkMode, runMeshBuildingBenchmark } from "./benchmark";
+import { createMeshSnapshot, diffMeshSnapshots } from "./mesh_snapshot";
+import { measureSchematic } from "./measurement";
+import {
+	createAxisSortedIndices,
+	getMeshIndexBuffer,
+	IndexMode,
+} from "./index_formats";
+import { auditMaterialUsage, pruneUnusedMaterials } from "./material_audit";
+import {
+	exportPaletteBlob,
+	getPaletteSummary,
+	getSchematicPalette,
+	importPaletteBlob,
+} from "./palette";
+import type {
+	BlockAnimation,
+	CategoryInferenceOverrides,
+	CoordinateFrame,
+	HighlightPredicate,
+	InstanceBufferOptions,
+	IsometricSpriteOptions,
+	InventoryPreviewOptions,
+	UploadSegmentOptions,
+	ContactShadowOptions,
+	DebugMeshOptions,
+	ExclusionBox,
+	ElevationOptions,
+	CutCapOptions,
+	CutVolume,
+	HeatmapOptions,
+	FixtureKind,
+	FixtureOptions,
+	GroupProvenance,
+	LightOverlayOptions,
+	MeshSnapshot,
+	NetworkChunkOptions,
+	TextureAnimation,
+	BlockRotation,
+	TurntableOptions,
+	VoxelColorMapping,
+	WaterVolume,
+} from "./types";
+import { buildHighlightMesh } from "./highlight";
+import { buildLightOverlay } from "./block_light";
+import { buildContactShadow } from "./contact_shadow";
+import { buildDebugMeshes } from "./debug_meshes";
+import { buildCutCaps } from "./exclusion";
+import {
+	buildBlockAnimations,
+	setBlockAnimationProgress,
+} from "./block_animation";
+import { createInstanceBuffers } from "./instance_output";
+import { extractWaterVolumes } from "./water_volumes";
+import { buildInventoryPreview } from "./inventory_preview";
+import { INVISIBLE_BLOCKS } from "./utils";
+import {
+	renderIsometricSprite,
+	renderPaletteSpriteSheet,
+} from "./isometric_sprites";
+import {
+	computeTurntableCameras,
+	renderTurntableFrames,
+} from "./turntable";
+import { loadNetworkChunk } from "./network_chunk";
+import { renderElevations } from "./elevation_export";
+import { createFixtureSchematic } from "./test_fixtures";
+import { createBatchedMesh, createBatchedOutput } from "./batched_output";
+import {
+	describeVertexLayout,
+	packInterleavedVertices,
+} from "./vertex_layout";
+import { createUploadSegments } from "./upload_segments";
+import { analyzeVertexFormats } from "./vertex_format_report";
+import { schematicToWorld, worldToSchematic } from "./coordinates";
+import { inferPaletteCategory } from "./category_inference";
+import { getQuadBlockRange } from "./quad_provenance";
 
 export class SchematicRenderer {
 	canvas: HTMLCanvasElement;
@@ -18,6 +100,7 @@ export class SchematicRenderer {
 	loadedSchematic: any;
 	resourceLoader: any;
 	materialMap: Map<string, THREE.Material> = new Map();
+	waterVolumes?: WaterVolume[];
 	worldMeshBuilder: WorldMeshBuilder | undefined;
 	jarUrl: string | string[] | undefined;
 
@@ -30,15 +113,23 @@ export class SchematicRenderer {
 		this.canvas = canvas;
 		this.schematicData = schematicData;
 		this.options = options;
+		if (this.options?.logLevel) {
+			logger.setLevel(this.options.logLevel);
+		}
+		if (this.options?.logSink) {
+			logger.setSink(this.options.logSink);
+		}
 		this.renderer = new Renderer(canvas, options);
 		this.resourceLoader = new ResourceLoader(
 			this.options?.resourcePackBlobs,
-			this.materialMap
+			this.materialMap,
+			this.options
 		);
 		this.worldMeshBuilder = new WorldMeshBuilder(
 			this.resourceLoader,
 			this.materialMap,
-			this.renderer
+			this.renderer,
+			this.options
 		);
 		this.schematicRendererCore = new SchematicRendererCore(
 			this.renderer,
@@ -52,10 +143,10 @@ export class SchematicRenderer {
 	}
 
 	async initialize() {
-		let parsedNbt: TagMap;
-		parsedNbt = parseNbtFromBase64(this.schematicData);
-
-		this.loadedSchematic = loadSchematic(parsedNbt);
+		this.loadedSchematic = loadSchematicFromBase64(
+			this.schematicData,
+			this.options
+		);
 		this.materialMap = new Map();
 		this.renderer.schematic = this.loadedSchematic;
 
@@ -70,13 +161,77 @@ export class SchematicRenderer {
 
 	async updateSchematic(schematicData: string) {
 		this.schematicData = schematicData;
-		await this.schematicRendererCore.updateSchematic(schematicData);
+		this.waterVolumes = undefined;
+		await this.schematicRendererCore.updateSchematic(
+			schematicData,
+			this.options
+		);
+	}
+
+	async buildFixture(kind: FixtureKind, options: FixtureOptions = {}) {
+		const schematic = createFixtureSchematic(kind, options);
+		this.loadedSchematic = schematic;
+		this.renderer.schematic = schematic;
+		await this.schematicRendererCore.clearSchematic();
+		await this.schematicRendererCore.render(schematic);
+		return schematic;
+	}
+
+	// replaces the current schematic with a chunk received from a live server
+	async buildFromNetworkChunk(bytes: Uint8Array, options: NetworkChunkOptions) {
+		const schematic = loadNetworkChunk(bytes, options);
+		this.loadedSchematic = schematic;
+		this.renderer.schematic = schematic;
+		await this.schematicRendererCore.clearSchematic();
+		await this.schematicRendererCore.render(schematic);
+		return schematic;
 	}
 
 	async exportUsdz() {
 		return this.schematicExporter.exportUsdz();
 	}
 
+	async exportGlb(options: GltfExportOptions = {}) {
+		return this.schematicExporter.exportGlb(options);
+	}
+
+	exportPly() {
+		return this.schematicExporter.exportPly();
+	}
+
+	async export3mf(options: { blockSize?: number } = {}) {
+		return this.schematicExporter.export3mf(options);
+	}
+
+	// without a mapping the colors come from the palette's textures
+	exportVox(mapping?: VoxelColorMapping) {
+		return exportVox(
+			this.loadedSchematic,
+			mapping ?? this.getTextureColorMapping()
+		);
+	}
+
+	exportQb(mapping?: VoxelColorMapping) {
+		return exportQb(
+			this.loadedSchematic,
+			mapping ?? this.getTextureColorMapping()
+		);
+	}
+
+	getTextureColors() {
+		if (!this.worldMeshBuilder) {
+			return {};
+		}
+		return getPaletteFaceColors(
+			this.worldMeshBuilder.blockMeshBuilder,
+			this.resourceLoader.textureColors
+		);
+	}
+
+	getTextureColorMapping() {
+		return createTextureColorMapping(this.getTextureColors());
+	}
+
 	async downloadScreenshot(resolutionX: number, resolutionY: number) {
 		return this.schematicMediaCapture.downloadScreenshot(
 			resolutionX,
@@ -107,4 +262,775 @@ export class SchematicRenderer {
 	updateZoom(value: number) {
 		this.renderer.updateZoom(value);
 	}
+
+	setLogLevel(level: LogLevel) {
+		logger.setLevel(level);
+	}
+
+	setLogSink(sink: LogSink | null) {
+		logger.setSink(sink);
+	}
+
+	async benchmark(iterations: number = 5, mode: BenchmarkMode = "default") {
+		if (!this.worldMeshBuilder) {
+			throw new Error("World mesh builder is not initialized");
+		}
+		return runMeshBuildingBenchmark(this.worldMeshBuilder, iterations, mode);
+	}
+
+	// rebuilds only the chunks that changed since the last load
+	async reloadSchematic(schematicData: string) {
+		if (!this.worldMeshBuilder) {
+			return this.updateSchematic(schematicData);
+		}
+		this.schematicData = schematicData;
+		this.waterVolumes = undefined;
+		this.loadedSchematic = loadSchematicFromBase64(schematicData, this.options);
+		this.renderer.schematic = this.loadedSchematic;
+		this.resourceLoader.setSchematic(this.loadedSchematic);
+		return this.worldMeshBuilder.reloadSchematic(this.loadedSchematic);
+	}
+
+	async setExclusionBoxes(boxes: ExclusionBox[]) {
+		return this.worldMeshBuilder?.setExclusionBoxes(boxes);
+	}
+
+	// an empty list restores the whole schematic, caps are drawn unless options.caps is false
+	async setCutVolumes(
+		volumes: CutVolume[],
+		options: CutCapOptions & { caps?: boolean } = {}
+	) {
+		if (!this.worldMeshBuilder) {
+			return undefined;
+		}
+		const meshes = await this.worldMeshBuilder.setCutVolumes(volumes);
+		this.clearCutCaps();
+		if (volumes.length > 0 && (options.caps ?? true)) {
+			this.renderer.scene.add(
+				buildCutCaps(
+					this.worldMeshBuilder.sourceSchematic,
+					volumes,
+					this.worldMeshBuilder.getMeshOffset(),
+					options
+				)
+			);
+		}
+		return meshes;
+	}
+
+	// occupancy of cells outside the schematic, e.g. from the neighbouring chunk of a
+	// streamed world, so ao and sun shadows line up across the seam
+	async setNeighborOccupancy(
+		neighborOccupancy: ((x: number, y: number, z: number) => boolean) | null
+	) {
+		return this.worldMeshBuilder?.setNeighborOccupancy(neighborOccupancy) ?? [];
+	}
+
+	async setHeatmap(heatmap: HeatmapOptions | null) {
+		return this.worldMeshBuilder?.setHeatmap(heatmap);
+	}
+
+	clearCutCaps() {
+		const caps = this.renderer.scene.getObjectByName("CutCaps");
+		if (caps instanceof THREE.Mesh) {
+			this.renderer.scene.remove(caps);
+			caps.geometry.dispose();
+			(caps.material as THREE.Material).dispose();
+		}
+	}
+
+	async rebuildChunk(chunkKey: bigint) {
+		return this.worldMeshBuilder?.rebuildChunk(chunkKey);
+	}
+
+	async buildAsync(frameBudget: number = 8, token?: unknown) {
+		return this.worldMeshBuilder?.getSchematicMeshesAsync(
+			this.worldMeshBuilder.chunkDimensions,
+			frameBudget,
+			token
+		);
+	}
+
+	async buildChunkAsync(chunkKey: bigint, token?: unknown) {
+		return this.worldMeshBuilder?.buildChunkAsync(chunkKey, token);
+	}
+
+	getBuildToken(chunkKey: bigint) {
+		return this.worldMeshBuilder?.getBuildToken(chunkKey);
+	}
+
+	async rebuildCategory(chunkKey: bigint, category: string) {
+		return this.worldMeshBuilder?.rebuildCategory(chunkKey, category);
+	}
+
+	enqueuePrebuild(chunkKeys: bigint[]) {
+		this.worldMeshBuilder?.enqueuePrebuild(chunkKeys);
+	}
+
+	async processPrebuildQueue(timeBudget: number) {
+		return this.worldMeshBuilder?.processPrebuildQueue(timeBudget) ?? 0;
+	}
+
+	compact(
+		options: { dropBlockMeshes?: boolean; dropResourceCaches?: boolean } = {}
+	) {
+		return this.worldMeshBuilder?.compact(options) ?? 0;
+	}
+
+	getDirtyChunks() {
+		return this.worldMeshBuilder?.getDirtyChunks() ?? [];
+	}
+
+	getDirtyAttributes() {
+		return this.worldMeshBuilder?.getDirtyAttributes() ?? [];
+	}
+
+	getMeshVersion(chunkKey: bigint) {
+		return this.worldMeshBuilder?.getMeshVersion(chunkKey) ?? 0;
+	}
+
+	getMaterialUsage() {
+		return auditMaterialUsage(
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [],
+			this.resourceLoader.materialMap
+		);
+	}
+
+	pruneUnusedMaterials() {
+		return pruneUnusedMaterials(
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [],
+			this.resourceLoader.materialMap
+		);
+	}
+
+	// blocks drawn as fallback cubes because no blockstate variant matched them
+	getUnknownBlocks() {
+		return [
+			...(this.worldMeshBuilder?.blockMeshBuilder.unknownBlocks.values() ?? []),
+		];
+	}
+
+	getGeometryDedupStats() {
+		return this.worldMeshBuilder?.blockMeshBuilder.geometryPool.getStats();
+	}
+
+	// what each palette entry would be tagged with under options.inferCategories
+	getInferredCategories(overrides?: CategoryInferenceOverrides) {
+		if (!this.loadedSchematic) {
+			return [];
+		}
+		const inferCategories = this.options.inferCategories;
+		return getSchematicPalette(this.loadedSchematic).map((block) => ({
+			block,
+			category: inferPaletteCategory(
+				block,
+				overrides ??
+					(inferCategories && inferCategories !== true ? inferCategories : {})
+			),
+		}));
+	}
+
+	// indices refer to getSchematicPalette, all entries are prepared when omitted
+	async preparePalette(indices?: number[], rotations: BlockRotation[] = []) {
+		if (!this.worldMeshBuilder) {
+			return 0;
+		}
+		const palette = getSchematicPalette(this.worldMeshBuilder.schematic);
+		const blocks = indices
+			? indices.map((index) => palette[index]).filter(Boolean)
+			: palette;
+		return this.worldMeshBuilder.blockMeshBuilder.prepareBlocks(
+			blocks,
+			rotations
+		);
+	}
+
+	// the non blocking variant of preparePalette, builds started meanwhile wait for it
+	bakePalette(
+		indices?: number[],
+		options: { frameBudget?: number; rotations?: BlockRotation[] } = {}
+	) {
+		if (!this.worldMeshBuilder) {
+			return undefined;
+		}
+		const palette = getSchematicPalette(this.worldMeshBuilder.schematic);
+		const blocks = indices
+			? indices.map((index) => palette[index]).filter(Boolean)
+			: palette;
+		return this.worldMeshBuilder.bakePalette(blocks, options);
+	}
+
+	getSchematicPalette() {
+		if (!this.worldMeshBuilder) {
+			return [];
+		}
+		return getSchematicPalette(this.worldMeshBuilder.schematic);
+	}
+
+	getPaletteSummary() {
+		if (!this.worldMeshBuilder) {
+			return [];
+		}
+		return getPaletteSummary(this.worldMeshBuilder.blockMeshBuilder);
+	}
+
+	exportPaletteBlob() {
+		if (!this.worldMeshBuilder) {
+			throw new Error("World mesh builder is not initialized");
+		}
+		return exportPaletteBlob(this.worldMeshBuilder.blockMeshBuilder);
+	}
+
+	// call after the resource packs are loaded so imported materials can be recreated
+	async importPaletteBlob(blob: Blob) {
+		if (!this.worldMeshBuilder) {
+			throw new Error("World mesh builder is not initialized");
+		}
+		return importPaletteBlob(this.worldMeshBuilder.blockMeshBuilder, blob);
+	}
+
+	// animation parameters per material group, keyed like the mesh names
+	async getMaterialAnimations() {
+		const animations: { [materialId: string]: TextureAnimation } = {};
+		if (!this.worldMeshBuilder) {
+			return animations;
+		}
+		const { materialDescriptors } = this.worldMeshBuilder.blockMeshBuilder;
+		const meshes: THREE.Mesh[] = this.worldMeshBuilder.worldMeshes.flat();
+		for (const materialId of new Set(meshes.map((mesh) => mesh.name))) {
+			const descriptor = materialDescriptors.get(materialId);
+			if (!descriptor) {
+				continue;
+			}
+			const animation = await this.resourceLoader.getTextureAnimation(
+				descriptor.texture
+			);
+			if (animation) {
+				animations[materialId] = animation;
+			}
+		}
+		return animations;
+	}
+
+	getChunkVisibilityGraph() {
+		return this.worldMeshBuilder?.getChunkVisibilityGraph() ?? new Map();
+	}
+
+	getPaletteKeys(paletteIndices?: number[]) {
+		if (!paletteIndices) {
+			return undefined;
+		}
+		const palette = this.getPaletteSummary();
+		return new Set(
+			paletteIndices
+				.map((index) => palette[index]?.key)
+				.filter((key): key is string => key !== undefined)
+		);
+	}
+
+	buildHighlightFiltered(predicate: HighlightPredicate) {
+		if (!this.worldMeshBuilder || !this.loadedSchematic) {
+			return undefined;
+		}
+		this.clearHighlight();
+		const mesh = buildHighlightMesh(
+			this.loadedSchematic,
+			predicate,
+			this.worldMeshBuilder.getMeshOffset(),
+			this.getPaletteKeys(predicate.paletteIndices)
+		);
+		this.renderer.scene.add(mesh);
+		return mesh;
+	}
+
+	clearHighlight() {
+		const highlight = this.renderer.scene.getObjectByName("Highlight");
+		if (highlight instanceof THREE.InstancedMesh) {
+			this.renderer.scene.remove(highlight);
+			highlight.geometry.dispose();
+			(highlight.material as THREE.Material).dispose();
+		}
+	}
+
+	// pass null to leave x-ray mode
+	async setXrayFilter(predicate: HighlightPredicate | null) {
+		if (!this.worldMeshBuilder) {
+			return;
+		}
+		await this.worldMeshBuilder.setXrayFilter(
+			predicate
+				? {
+						predicate,
+						paletteKeys: this.getPaletteKeys(predicate.paletteIndices),
+				  }
+				: null
+		);
+	}
+
+	buildLightOverlay(options: LightOverlayOptions = {}) {
+		if (!this.worldMeshBuilder || !this.loadedSchematic) {
+			return undefined;
+		}
+		this.clearLightOverlay();
+		const mesh = buildLightOverlay(
+			this.loadedSchematic,
+			this.worldMeshBuilder.getMeshOffset(),
+			options
+		);
+		this.renderer.scene.add(mesh);
+		return mesh;
+	}
+
+	clearLightOverlay() {
+		const overlay = this.renderer.scene.getObjectByName("LightOverlay");
+		if (overlay instanceof THREE.Mesh) {
+			this.renderer.scene.remove(overlay);
+			overlay.geometry.dispose();
+			(overlay.material as THREE.Material).dispose();
+		}
+	}
+
+	// occupancy points, per face culling overlays and dirty chunk boxes for tracking down
+	// missing faces, the occlusion overlay has a quad per block face so keep it to small builds
+	buildDebugMeshes(options: DebugMeshOptions = {}) {
+		if (!this.worldMeshBuilder) {
+			return undefined;
+		}
+		this.clearDebugMeshes();
+		const { blockMeshBuilder } = this.worldMeshBuilder;
+		const group = buildDebugMeshes(
+			this.worldMeshBuilder.schematic,
+			this.worldMeshBuilder.getMeshOffset(),
+			(block, pos) => blockMeshBuilder.getOccludedFacesForBlock(block, pos),
+			this.worldMeshBuilder.getDirtyChunkBoxes(),
+			options
+		);
+		this.renderer.scene.add(group);
+		return group;
+	}
+
+	clearDebugMeshes() {
+		const debugMeshes = this.renderer.scene.getObjectByName("DebugMeshes");
+		if (!debugMeshes) {
+			return;
+		}
+		this.renderer.scene.remove(debugMeshes);
+		debugMeshes.traverse((object: any) => {
+			object.geometry?.dispose();
+			object.material?.dispose();
+		});
+	}
+
+	// the animated blocks stay in the chunk meshes, hide them there with setCutVolumes
+	// using their cells if the host doesn't want them drawn twice
+	async buildBlockAnimations(animations: BlockAnimation[]) {
+		if (!this.worldMeshBuilder || !this.loadedSchematic) {
+			return undefined;
+		}
+		this.clearBlockAnimations();
+		const { blockMeshBuilder } = this.worldMeshBuilder;
+		const group = await buildBlockAnimations(
+			this.loadedSchematic,
+			this.worldMeshBuilder.getMeshOffset(),
+			animations,
+			(block, pos) => blockMeshBuilder.getBlockMeshFromCache(block, pos),
+			(components) =>
+				this.resourceLoader.createMeshesFromBlocks(components, {
+					chunkMeshCreation: {
+						materialRetrieval: 0,
+						arrayCreation: 0,
+						slicing: 0,
+						positionTranslation: 0,
+						arrayAllocation: 0,
+						indexCalculation: 0,
+						geometryCreation: 0,
+					},
+				})
+		);
+		this.renderer.scene.add(group);
+		return group;
+	}
+
+	setBlockAnimationProgress(t: number) {
+		const group = this.renderer.scene.getObjectByName("BlockAnimations");
+		if (group) {
+			setBlockAnimationProgress(group, t);
+		}
+	}
+
+	// materials come from the shared material map, only the geometry is ours
+	clearBlockAnimations() {
+		const group = this.renderer.scene.getObjectByName("BlockAnimations");
+		if (!group) {
+			return;
+		}
+		this.renderer.scene.remove(group);
+		group.traverse((object: any) => object.geometry?.dispose());
+	}
+
+	buildContactShadow(options: ContactShadowOptions = {}) {
+		if (!this.worldMeshBuilder || !this.loadedSchematic) {
+			return undefined;
+		}
+		this.clearContactShadow();
+		const mesh = buildContactShadow(
+			this.loadedSchematic,
+			this.worldMeshBuilder.getMeshOffset(),
+			options
+		);
+		this.renderer.scene.add(mesh);
+		return mesh;
+	}
+
+	clearContactShadow() {
+		const shadow = this.renderer.scene.getObjectByName("ContactShadow");
+		if (shadow instanceof THREE.Mesh) {
+			this.renderer.scene.remove(shadow);
+			shadow.geometry.dispose();
+			shadow.material.map?.dispose();
+			shadow.material.dispose();
+		}
+	}
+
+	async buildInventoryPreview(options: InventoryPreviewOptions = {}) {
+		if (!this.worldMeshBuilder || !this.loadedSchematic) {
+			return undefined;
+		}
+		this.clearInventoryPreview();
+		const group = await buildInventoryPreview(
+			this.loadedSchematic,
+			this.worldMeshBuilder.getMeshOffset(),
+			(itemId) => this.resourceLoader.getItemTexture(itemId),
+			options
+		);
+		this.renderer.scene.add(group);
+		return group;
+	}
+
+	clearInventoryPreview() {
+		const preview = this.renderer.scene.getObjectByName("InventoryPreview");
+		if (!preview) {
+			return;
+		}
+		this.renderer.scene.remove(preview);
+		const disposables = new Set<{ dispose(): void }>();
+		preview.traverse((object) => {
+			if (object instanceof THREE.Mesh || object instanceof THREE.Sprite) {
+				disposables.add(object.geometry);
+				disposables.add(object.material as THREE.Material);
+			}
+		});
+		disposables.forEach((disposable) => disposable.dispose());
+	}
+
+	getTurntableCameras(
+		angles: number | number[],
+		options: TurntableOptions = {}
+	) {
+		const box = new THREE.Box3();
+		for (const mesh of this.worldMeshBuilder?.worldMeshes.flat() ?? []) {
+			box.expandByObject(mesh);
+		}
+		return computeTurntableCameras(box, angles, options);
+	}
+
+	renderTurntableFrames(
+		angles: number | number[],
+		options: TurntableOptions = {}
+	) {
+		const cameras = this.getTurntableCameras(angles, options);
+		const frames = renderTurntableFrames(
+			this.renderer.renderer,
+			this.renderer.scene,
+			cameras,
+			options
+		);
+		return cameras.map((camera, i) => ({ camera, frame: frames[i] }));
+	}
+
+	// blueprint style orthographic views of the build, one image pair per side
+	renderElevations(options: ElevationOptions = {}) {
+		const box = new THREE.Box3();
+		for (const mesh of this.worldMeshBuilder?.worldMeshes.flat() ?? []) {
+			box.expandByObject(mesh);
+		}
+		if (box.isEmpty()) {
+			return [];
+		}
+		return renderElevations(
+			this.renderer.renderer,
+			this.renderer.scene,
+			box,
+			options
+		);
+	}
+
+	renderPaletteSpriteSheet(options: IsometricSpriteOptions = {}) {
+		if (!this.worldMeshBuilder) {
+			return undefined;
+		}
+		return renderPaletteSpriteSheet(
+			this.worldMeshBuilder.blockMeshBuilder.blockMeshCache,
+			this.resourceLoader.materialMap,
+			options
+		);
+	}
+
+	// meant for small schematics, every block is drawn without culling hidden faces
+	async renderIsometricSprite(options: IsometricSpriteOptions = {}) {
+		if (!this.worldMeshBuilder || !this.loadedSchematic) {
+			return undefined;
+		}
+		const schematic = this.loadedSchematic;
+		const blockMeshBuilder = this.worldMeshBuilder.blockMeshBuilder;
+		const components: [any, number[]][] = [];
+		for (const pos of schematic) {
+			const block = schematic.getBlock(pos);
+			if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+				continue;
+			}
+			const blockComponents = await blockMeshBuilder.getBlockMeshFromCache(
+				block,
+				pos
+			);
+			for (const component of Object.values(blockComponents ?? {})) {
+				components.push([component, [pos.x, pos.y, pos.z]]);
+			}
+		}
+		return renderIsometricSprite(
+			components,
+			{ x: schematic.width, y: schematic.height, z: schematic.length },
+			this.resourceLoader.materialMap,
+			options
+		);
+	}
+
+	async buildVisible(frustum?: THREE.Frustum | THREE.Plane[]) {
+		if (!this.worldMeshBuilder) {
+			return [];
+		}
+		const camera = this.renderer.camera;
+		camera.updateMatrixWorld();
+		const cameraFrustum =
+			frustum ??
+			new THREE.Frustum().setFromProjectionMatrix(
+				new THREE.Matrix4().multiplyMatrices(
+					camera.projectionMatrix,
+					camera.matrixWorldInverse
+				)
+			);
+		return this.worldMeshBuilder.buildVisible(cameraFrustum, camera.position);
+	}
+
+	updateCategoryVisibility() {
+		this.worldMeshBuilder?.updateCategoryVisibility(
+			this.renderer.camera.position
+		);
+	}
+
+	getInstanceBuffers(options: InstanceBufferOptions = {}) {
+		if (!this.worldMeshBuilder || !this.loadedSchematic) {
+			return [];
+		}
+		return createInstanceBuffers(
+			this.loadedSchematic,
+			this.worldMeshBuilder.getMeshOffset(),
+			{ blockRotation: this.options.blockRotation, ...options }
+		);
+	}
+
+	// needs options.debugProvenance before the build
+	getGroupProvenance() {
+		const meshes: THREE.Mesh[] =
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [];
+		return meshes
+			.filter((mesh) => mesh.userData.provenance)
+			.map((mesh) => ({
+				chunkKey: mesh.userData.chunkKey as bigint,
+				materialId: mesh.name,
+				provenance: mesh.userData.provenance as GroupProvenance,
+			}));
+	}
+
+	getWaterVolumes() {
+		if (!this.worldMeshBuilder || !this.loadedSchematic) {
+			return [];
+		}
+		this.waterVolumes = extractWaterVolumes(
+			this.loadedSchematic,
+			this.worldMeshBuilder.getMeshOffset()
+		);
+		return this.waterVolumes;
+	}
+
+	// the volume the camera is inside of, for underwater fog and color grading
+	getCameraWaterVolume() {
+		const position = this.renderer.camera.position;
+		return (this.waterVolumes ?? this.getWaterVolumes()).find(
+			(volume) =>
+				volume.boundingBox.containsPoint(position) &&
+				volume.containsPoint(position)
+		);
+	}
+
+	getGroupBoundingBoxes() {
+		const meshes: THREE.Mesh[] =
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [];
+		return meshes.map((mesh) => ({
+			chunkKey: mesh.userData.chunkKey as bigint,
+			materialId: mesh.name,
+			boundingBox: mesh.userData.boundingBox as THREE.Box3 | undefined,
+		}));
+	}
+
+	// pass the result to the helpers in ./coordinates, scale is whatever the host applied
+	// to the schematic's parent
+	getCoordinateFrame(scale = 1): CoordinateFrame | undefined {
+		if (!this.worldMeshBuilder?.schematic) {
+			return undefined;
+		}
+		return {
+			offset: this.worldMeshBuilder.getMeshOffset(),
+			chunkDimensions: { ...this.worldMeshBuilder.chunkDimensions },
+			chunkScales: new Map(this.worldMeshBuilder.chunkScales),
+			scale,
+		};
+	}
+
+	// needs options.quadProvenance before the build, works on greedy merged meshes too
+	getIntersectedBlocks(intersection: THREE.Intersection) {
+		if (!(intersection.object instanceof THREE.Mesh) || !intersection.face) {
+			return undefined;
+		}
+		return getQuadBlockRange(intersection.object, intersection.face.a);
+	}
+
+	worldToSchematic(world: THREE.Vector3, normal?: THREE.Vector3, scale = 1) {
+		const frame = this.getCoordinateFrame(scale);
+		return frame && worldToSchematic(frame, world, normal);
+	}
+
+	schematicToWorld(cell: THREE.Vector3, center = false, scale = 1) {
+		const frame = this.getCoordinateFrame(scale);
+		return frame && schematicToWorld(frame, cell, center);
+	}
+
+	// set when options.autoChunking picked the chunk regions of the last build
+	getChunkPartition() {
+		return this.worldMeshBuilder?.chunkPartition;
+	}
+
+	// the meshes are returned rather than added, the host decides when to swap them in
+	async buildChunkLod(chunkKey: bigint, lodLevel: number) {
+		return this.worldMeshBuilder?.buildChunkLod(chunkKey, lodLevel) ?? [];
+	}
+
+	// call after swapping a chunk's meshes so skirts are only kept where detail differs
+	async setChunkLodLevel(chunkKey: bigint, lodLevel: number) {
+		return this.worldMeshBuilder?.setChunkLodLevel(chunkKey, lodLevel) ?? [];
+	}
+
+	getIndexBuffers(mode: IndexMode) {
+		const meshes: THREE.Mesh[] =
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [];
+		return meshes.map((mesh) => ({
+			materialId: mesh.name,
+			...getMeshIndexBuffer(mesh, mode),
+		}));
+	}
+
+	// reorders every mesh's triangles by layer along the axis, the ranges in userData map
+	// each layer to its slice of the index buffer for draw range sweeps. with greedy
+	// meshing a merged wall shows up whole at its bottom layer
+	sortIndicesByAxis(axis: "x" | "y" | "z") {
+		const meshes: THREE.Mesh[] =
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [];
+		return meshes.map((mesh) => {
+			const { indices, ranges } = createAxisSortedIndices(mesh, axis);
+			mesh.geometry.setIndex(new THREE.BufferAttribute(indices, 1));
+			mesh.userData.axisRanges = { axis, ranges };
+			return {
+				materialId: mesh.name,
+				chunkKey: mesh.userData.chunkKey,
+				ranges,
+			};
+		});
+	}
+
+	// layouts follow webgpu's GPUVertexBufferLayout, with interleave the matching vertex data
+	// is packed alongside so it can be uploaded as one buffer per mesh
+	getVertexLayouts(
+		options: { padAttributes?: boolean; interleave?: boolean } = {}
+	) {
+		const meshes: THREE.Mesh[] =
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [];
+		return meshes.map((mesh) => {
+			const layout = describeVertexLayout(mesh.geometry, options);
+			return {
+				materialId: mesh.name,
+				chunkKey: mesh.userData.chunkKey,
+				layout,
+				vertices: options.interleave
+					? packInterleavedVertices(mesh.geometry, layout)
+					: undefined,
+			};
+		});
+	}
+
+	// which compact vertex formats would reproduce the current build without loss
+	getVertexFormatReport() {
+		return analyzeVertexFormats(
+			this.worldMeshBuilder?.worldMeshes.flat() ?? []
+		);
+	}
+
+	// segment ranges let the host spread large uploads over several frames
+	getUploadSegments(options: UploadSegmentOptions = {}) {
+		const meshes: THREE.Mesh[] =
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [];
+		return meshes.map((mesh) => ({
+			materialId: mesh.name,
+			chunkKey: mesh.userData.chunkKey,
+			segments: createUploadSegments(mesh.geometry, options),
+		}));
+	}
+
+	getBatchedOutput() {
+		return createBatchedOutput(this.worldMeshBuilder?.worldMeshes.flat() ?? []);
+	}
+
+	// one BatchedMesh per material, the chunk meshes are left in the scene for the host
+	createBatchedMeshes() {
+		return this.getBatchedOutput().map((group) =>
+			createBatchedMesh(
+				group,
+				this.resourceLoader.materialMap.get(group.materialId) ??
+					new THREE.MeshBasicMaterial()
+			)
+		);
+	}
+
+	measure(blockSize: number = 1) {
+		return measureSchematic(this.loadedSchematic, blockSize);
+	}
+
+	getMeshSnapshot(options: { regionSize?: number } = {}) {
+		return createMeshSnapshot(
+			this.worldMeshBuilder?.worldMeshes.flat() ?? [],
+			options
+		);
+	}
+
+	// snapshots are plain json, so either side can come from an earlier run
+	diffMeshSnapshots(a: MeshSnapshot, b: MeshSnapshot) {
+		return diffMeshSnapshots(a, b);
+	}
+
+	getMergeReport() {
+		return this.worldMeshBuilder?.mergeReport;
+	}
+
+	getGeometryDiagnostics() {
+		return this.worldMeshBuilder?.blockMeshBuilder.geometryDiagnostics;
+	}
 }
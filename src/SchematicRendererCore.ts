@@ -1,10 +1,9 @@
 import * as THREE from "three";
-import { loadSchematic } from "@enginehub/schematicjs";
 import { Renderer } from "./renderer";
 import { ResourceLoader } from "./resource_loader";
 import { WorldMeshBuilder } from "./world_mesh_builder";
-import { parseNbtFromBase64 } from "./utils";
-import { TagMap } from "@enginehub/nbt-ts";
+import { loadSchematicFromBase64 } from "./schematic_loader";
+import { logger } from "./logger";
 
 export class SchematicRendererCore {
 	constructor(
@@ -35,17 +34,15 @@ export class SchematicRendererCore {
 		this.renderer.animate();
 		const startPerformance = performance.now();
 		await this.worldMeshBuilder.getSchematicMeshes();
-		console.log(
+		logger.info(
 			"Schematic rendered in",
 			performance.now() - startPerformance,
 			"ms"
 		);
 	}
 
-	async updateSchematic(schematicData: string) {
-		let parsedSchematic: TagMap;
-		parsedSchematic = parseNbtFromBase64(schematicData);
-		const newSchemMesh = loadSchematic(parsedSchematic);
+	async updateSchematic(schematicData: string, options: any = {}) {
+		const newSchemMesh = loadSchematicFromBase64(schematicData, options);
 		await this.clearSchematic();
 		await this.render(newSchemMesh);
 	}
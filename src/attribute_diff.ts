@@ -0,0 +1,59 @@
+import * as THREE from "three";
+
+// a change to any of these means the faces moved and the geometry has to be replaced
+const SHAPE_ATTRIBUTES = ["position", "normal", "uv"];
+
+function arraysEqual(a: ArrayLike<number>, b: ArrayLike<number>) {
+	if (a.length !== b.length || a.constructor !== b.constructor) {
+		return false;
+	}
+	for (let i = 0; i < a.length; i++) {
+		if (a[i] !== b[i]) {
+			return false;
+		}
+	}
+	return true;
+}
+
+// the names of the attributes that differ, undefined when the shape itself changed
+export function diffGeometryAttributes(
+	previous: THREE.BufferGeometry,
+	next: THREE.BufferGeometry
+) {
+	const names = Object.keys(next.attributes);
+	if (
+		names.length !== Object.keys(previous.attributes).length ||
+		!!previous.index !== !!next.index ||
+		(next.index && !arraysEqual(previous.index!.array, next.index.array))
+	) {
+		return undefined;
+	}
+	const changed: string[] = [];
+	for (const name of names) {
+		const before = previous.getAttribute(name);
+		const after = next.getAttribute(name);
+		if (!before || before.itemSize !== after.itemSize) {
+			return undefined;
+		}
+		if (arraysEqual(before.array, after.array)) {
+			continue;
+		}
+		if (SHAPE_ATTRIBUTES.includes(name)) {
+			return undefined;
+		}
+		changed.push(name);
+	}
+	return changed;
+}
+
+export function copyGeometryAttributes(
+	previous: THREE.BufferGeometry,
+	next: THREE.BufferGeometry,
+	names: string[]
+) {
+	for (const name of names) {
+		const attribute = previous.getAttribute(name) as THREE.BufferAttribute;
+		(attribute.array as THREE.TypedArray).set(next.getAttribute(name).array);
+		attribute.needsUpdate = true;
+	}
+}
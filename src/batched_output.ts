@@ -0,0 +1,110 @@
+import * as THREE from "three";
+import type { BatchedGeometryRange, BatchedMaterialGroup } from "./types";
+
+// packs every chunk mesh of a material into one vertex and index pool, the ranges line up
+// with what THREE.BatchedMesh.addGeometry reserves so the host can draw it as a multi draw
+export function createBatchedOutput(meshes: THREE.Mesh[]) {
+	const byMaterial = new Map<string, THREE.Mesh[]>();
+	for (const mesh of meshes) {
+		if (!byMaterial.has(mesh.name)) {
+			byMaterial.set(mesh.name, []);
+		}
+		byMaterial.get(mesh.name)!.push(mesh);
+	}
+	const groups: BatchedMaterialGroup[] = [];
+	for (const [materialId, group] of byMaterial) {
+		const attributeNames = Object.keys(group[0].geometry.attributes).filter(
+			(name) => group.every((mesh) => mesh.geometry.getAttribute(name))
+		);
+		let vertexTotal = 0;
+		let indexTotal = 0;
+		for (const mesh of group) {
+			vertexTotal += mesh.geometry.getAttribute("position").count;
+			indexTotal += mesh.geometry.getIndex()?.count ?? 0;
+		}
+		const attributes: BatchedMaterialGroup["attributes"] = {};
+		for (const name of attributeNames) {
+			const itemSize = group[0].geometry.getAttribute(name).itemSize;
+			attributes[name] = {
+				array: new Float32Array(vertexTotal * itemSize),
+				itemSize,
+			};
+		}
+		const indices = new Uint32Array(indexTotal);
+		const ranges: BatchedGeometryRange[] = [];
+		let vertexStart = 0;
+		let indexStart = 0;
+		for (const mesh of group) {
+			const geometry = mesh.geometry;
+			const vertexCount = geometry.getAttribute("position").count;
+			for (const name of attributeNames) {
+				const { array, itemSize } = attributes[name];
+				array.set(
+					geometry.getAttribute(name).array as ArrayLike<number>,
+					vertexStart * itemSize
+				);
+			}
+			const index = geometry.getIndex();
+			const indexCount = index?.count ?? 0;
+			if (index) {
+				indices.set(index.array as ArrayLike<number>, indexStart);
+			}
+			mesh.updateMatrix();
+			ranges.push({
+				chunkKey: mesh.userData.chunkKey,
+				vertexStart,
+				vertexCount,
+				indexStart,
+				indexCount,
+				matrix: mesh.matrix.toArray(),
+			});
+			vertexStart += vertexCount;
+			indexStart += indexCount;
+		}
+		groups.push({ materialId, attributes, indices, ranges });
+	}
+	return groups;
+}
+
+export function createBatchedMesh(
+	group: BatchedMaterialGroup,
+	material: THREE.Material
+) {
+	const vertexCount = group.attributes.position.array.length / 3;
+	const batchedMesh = new THREE.BatchedMesh(
+		group.ranges.length,
+		vertexCount,
+		group.indices.length,
+		material
+	);
+	const matrix = new THREE.Matrix4();
+	for (const range of group.ranges) {
+		const geometry = new THREE.BufferGeometry();
+		for (const name in group.attributes) {
+			const { array, itemSize } = group.attributes[name];
+			geometry.setAttribute(
+				name,
+				new THREE.BufferAttribute(
+					array.subarray(
+						range.vertexStart * itemSize,
+						(range.vertexStart + range.vertexCount) * itemSize
+					),
+					itemSize
+				)
+			);
+		}
+		geometry.setIndex(
+			new THREE.BufferAttribute(
+				group.indices.subarray(
+					range.indexStart,
+					range.indexStart + range.indexCount
+				),
+				1
+			)
+		);
+		const id = batchedMesh.addGeometry(geometry);
+		batchedMesh.setMatrixAt(id, matrix.fromArray(range.matrix));
+	}
+	batchedMesh.name = group.materialId;
+	return batchedMesh;
+}
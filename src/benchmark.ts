@@ -0,0 +1,85 @@
+import type { WorldMeshBuilder } from "./world_mesh_builder";
+import type { BenchmarkResult, PhaseTimings } from "./types";
+
+export type BenchmarkMode = "default" | "greedy";
+
+const PHASES = [
+	"occlusion",
+	"blockMeshRetrieval",
+	"blockMeshCreation",
+	"chunkMeshCreation",
+	"total",
+] as const;
+
+function summarize(samples: number[]): PhaseTimings {
+	const sorted = samples.slice().sort((a, b) => a - b);
+	const middle = Math.floor(sorted.length / 2);
+	const median =
+		sorted.length % 2 === 0
+			? (sorted[middle - 1] + sorted[middle]) / 2
+			: sorted[middle];
+	return { min: sorted[0], median, max: sorted[sorted.length - 1] };
+}
+
+async function buildOnce(worldMeshBuilder: WorldMeshBuilder) {
+	const timings: { [phase: string]: number } = {};
+	for (const phase of PHASES) {
+		timings[phase] = 0;
+	}
+	const { offset } = worldMeshBuilder.initializeMeshCreation();
+	const chunks = worldMeshBuilder.splitSchemaIntoChunks();
+	const start = performance.now();
+	for (const chunk of chunks.values()) {
+		const meshes = await worldMeshBuilder.getChunkMesh(
+			chunk.positions,
+			offset ?? { x: 0, y: 0, z: 0 }
+		);
+		const chunkTimes = worldMeshBuilder.lastChunkTimes;
+		timings.occlusion += chunkTimes.occlusion;
+		timings.blockMeshRetrieval += chunkTimes.blockMeshRetrieval;
+		timings.blockMeshCreation += chunkTimes.blockMeshCreation;
+		timings.chunkMeshCreation += chunkTimes.chunkMeshCreation.total_time;
+		for (const mesh of meshes) {
+			mesh.geometry.dispose();
+		}
+	}
+	timings.total = performance.now() - start;
+	return timings;
+}
+
+// the first build only warms the block mesh and material caches and is not measured
+export async function runMeshBuildingBenchmark(
+	worldMeshBuilder: WorldMeshBuilder,
+	iterations: number = 5,
+	mode: BenchmarkMode = "default"
+): Promise<BenchmarkResult> {
+	if (!worldMeshBuilder.schematic) {
+		throw new Error("No schematic loaded to benchmark");
+	}
+	const previousGreedy = worldMeshBuilder.options.greedyMeshing;
+	const previousReport = worldMeshBuilder.mergeReport;
+	worldMeshBuilder.options.greedyMeshing = mode === "greedy";
+
+	const samples: { [phase: string]: number[] } = {};
+	for (const phase of PHASES) {
+		samples[phase] = [];
+	}
+	try {
+		await buildOnce(worldMeshBuilder);
+		for (let i = 0; i < iterations; i++) {
+			const timings = await buildOnce(worldMeshBuilder);
+			for (const phase of PHASES) {
+				samples[phase].push(timings[phase]);
+			}
+		}
+	} finally {
+		worldMeshBuilder.options.greedyMeshing = previousGreedy;
+		worldMeshBuilder.mergeReport = previousReport;
+	}
+
+	const phases: { [phase: string]: PhaseTimings } = {};
+	for (const phase of PHASES) {
+		phases[phase] = summarize(samples[phase]);
+	}
+	return { mode, iterations, phases };
+}
@@ -0,0 +1,85 @@
+import * as THREE from "three";
+import type { BlockAnimation, BlockAnimationTransform } from "./types";
+
+const IDENTITY: BlockAnimationTransform = {};
+
+function getTransform(transform: BlockAnimationTransform) {
+	const rotation = (transform.rotation ?? [0, 0, 0]).map(
+		THREE.MathUtils.degToRad
+	);
+	return {
+		offset: new THREE.Vector3(...(transform.offset ?? [0, 0, 0])),
+		rotation: new THREE.Quaternion().setFromEuler(
+			new THREE.Euler(rotation[0], rotation[1], rotation[2])
+		),
+		scale: new THREE.Vector3(...(transform.scale ?? [1, 1, 1])),
+	};
+}
+
+// each animated block gets its own group placed at the pivot, with the block geometry
+// shifted so the pivot sits at the group origin, that way rotations swing doors around
+// their hinge instead of the block center
+export async function buildBlockAnimations(
+	schematic: any,
+	offset: { x: number; y: number; z: number },
+	animations: BlockAnimation[],
+	getBlockComponents: (block: any, pos: THREE.Vector3) => Promise<any>,
+	createMeshes: (components: {
+		[materialId: string]: [any, number[]][];
+	}) => THREE.Mesh[]
+) {
+	const group = new THREE.Group();
+	group.name = "BlockAnimations";
+	for (const animation of animations) {
+		const [x, y, z] = animation.position;
+		const pos = new THREE.Vector3(x, y, z);
+		const block = animation.block ?? schematic.getBlock(pos);
+		if (!block) {
+			continue;
+		}
+		const blockComponents = await getBlockComponents(block, pos);
+		const pivot = animation.pivot ?? [0.5, 0.5, 0.5];
+		const components: { [materialId: string]: [any, number[]][] } = {};
+		for (const component of Object.values(blockComponents ?? {}) as any[]) {
+			(components[component.materialId] ??= []).push([
+				component,
+				[-pivot[0], -pivot[1], -pivot[2]],
+			]);
+		}
+		const animated = new THREE.Group();
+		animated.name = `BlockAnimation:${x},${y},${z}`;
+		animated.add(...createMeshes(components));
+		animated.userData.animation = {
+			base: new THREE.Vector3(
+				x + offset.x + pivot[0],
+				y + offset.y + pivot[1],
+				z + offset.z + pivot[2]
+			),
+			start: getTransform(animation.start ?? IDENTITY),
+			end: getTransform(animation.end),
+		};
+		animated.userData.blockPosition = animation.position;
+		group.add(animated);
+		applyBlockAnimation(animated, 0);
+	}
+	return group;
+}
+
+export function applyBlockAnimation(animated: THREE.Object3D, t: number) {
+	const animation = animated.userData.animation;
+	if (!animation) {
+		return;
+	}
+	const { base, start, end } = animation;
+	animated.position.copy(base).add(start.offset.clone().lerp(end.offset, t));
+	animated.quaternion.slerpQuaternions(start.rotation, end.rotation, t);
+	animated.scale.copy(start.scale).lerp(end.scale, t);
+}
+
+// t runs from 0 at the start transforms to 1 at the end ones, hosts drive it per frame
+export function setBlockAnimationProgress(group: THREE.Object3D, t: number) {
+	const progress = THREE.MathUtils.clamp(t, 0, 1);
+	for (const animated of group.children) {
+		applyBlockAnimation(animated, progress);
+	}
+}
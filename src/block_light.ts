@@ -0,0 +1,350 @@
+import * as THREE from "three";
+import { isOpaqueBlock, INVISIBLE_BLOCKS } from "./utils";
+import type { LightOverlayOptions } from "./types";
+
+const LIGHT_EMISSION: { [type: string]: number } = {
+	beacon: 15,
+	campfire: 15,
+	conduit: 15,
+	end_gateway: 15,
+	end_portal: 15,
+	fire: 15,
+	glowstone: 15,
+	jack_o_lantern: 15,
+	lantern: 15,
+	lava: 15,
+	ochre_froglight: 15,
+	pearlescent_froglight: 15,
+	verdant_froglight: 15,
+	sea_lantern: 15,
+	shroomlight: 15,
+	end_rod: 14,
+	torch: 14,
+	wall_torch: 14,
+	nether_portal: 11,
+	crying_obsidian: 10,
+	soul_campfire: 10,
+	soul_fire: 10,
+	soul_lantern: 10,
+	soul_torch: 10,
+	soul_wall_torch: 10,
+	enchanting_table: 7,
+	glow_lichen: 7,
+	redstone_torch: 7,
+	redstone_wall_torch: 7,
+	amethyst_cluster: 5,
+	magma_block: 3,
+	brewing_stand: 1,
+	brown_mushroom: 1,
+	dragon_egg: 1,
+	end_portal_frame: 1,
+};
+
+// blocks whose light depends on their lit state
+const LIT_EMISSION: { [type: string]: number } = {
+	redstone_lamp: 15,
+	furnace: 13,
+	blast_furnace: 13,
+	smoker: 13,
+	redstone_ore: 9,
+	deepslate_redstone_ore: 9,
+};
+
+export function getLightEmission(block: any) {
+	if (!block) {
+		return 0;
+	}
+	if (block.properties?.lit === "false") {
+		return 0;
+	}
+	if (LIT_EMISSION[block.type] !== undefined) {
+		return block.properties?.lit === "true" ? LIT_EMISSION[block.type] : 0;
+	}
+	if (block.type.endsWith("candle") && block.properties?.lit === "true") {
+		return 3 * Number(block.properties.candles ?? 1);
+	}
+	return LIGHT_EMISSION[block.type] ?? 0;
+}
+
+const LIGHT_OFFSETS = [
+	[1, 0, 0],
+	[-1, 0, 0],
+	[0, 1, 0],
+	[0, -1, 0],
+	[0, 0, 1],
+	[0, 0, -1],
+];
+// bit i of a light mask blocks the face towards LIGHT_OFFSETS[i], i ^ 1 is the opposite face
+const FACE_BITS = ["east", "west", "up", "down", "south", "north"];
+const ALL_FACES = 0x3f;
+
+// where the light visibly comes from inside the cell, the rest emit from the center
+export function getLightEmitterOffset(block: any) {
+	const type = block.type;
+	const facing = FACE_BITS.indexOf(block.properties?.facing);
+	if (type.endsWith("wall_torch") && facing !== -1) {
+		// the torch points along facing, so its flame stays near the wall behind it
+		const [dx, , dz] = LIGHT_OFFSETS[facing];
+		return [0.5 - dx * 0.2, 0.85, 0.5 - dz * 0.2];
+	}
+	if (type.endsWith("torch")) {
+		return [0.5, 0.7, 0.5];
+	}
+	if (type.endsWith("lantern") && type !== "sea_lantern") {
+		return [0.5, block.properties?.hanging === "true" ? 0.45 : 0.3, 0.5];
+	}
+	if (type.endsWith("campfire") || type.endsWith("candle")) {
+		return [0.5, 0.4, 0.5];
+	}
+	return [0.5, 0.5, 0.5];
+}
+
+// the faces of a partial block that are completely covered, light can't pass those but
+// still gets through the open sides of a slab or stair
+export function getLightBlockingFaces(block: any) {
+	if (!block) {
+		return 0;
+	}
+	if (isOpaqueBlock(block)) {
+		return ALL_FACES;
+	}
+	const properties = block.properties ?? {};
+	if (block.type.endsWith("_slab")) {
+		if (properties.type === "double") {
+			return ALL_FACES;
+		}
+		return 1 << FACE_BITS.indexOf(properties.type === "top" ? "up" : "down");
+	}
+	if (block.type.endsWith("_stairs")) {
+		let mask =
+			1 << FACE_BITS.indexOf(properties.half === "top" ? "up" : "down");
+		const facing = FACE_BITS.indexOf(properties.facing);
+		if (facing !== -1 && !properties.shape?.startsWith("outer")) {
+			mask |= 1 << facing;
+		}
+		return mask;
+	}
+	if (block.type === "snow" && properties.layers === "8") {
+		return ALL_FACES;
+	}
+	if (block.type === "farmland" || block.type === "dirt_path") {
+		return 1 << FACE_BITS.indexOf("down");
+	}
+	return 0;
+}
+
+// breadth first flood fill of block light, each step into a non opaque block costs a level
+// with subBlock light also passes partial blocks through their open faces
+export function computeBlockLight(
+	schematic: any,
+	options: {
+		subBlock?: boolean;
+		neighborLight?: (x: number, y: number, z: number) => number;
+	} = {}
+) {
+	const { width, height, length } = schematic;
+	const index = (x: number, y: number, z: number) =>
+		x + z * width + y * width * length;
+	const light = new Uint8Array(width * height * length);
+	const blocking = new Uint8Array(width * height * length);
+	const queue: number[] = [];
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		const i = index(pos.x, pos.y, pos.z);
+		if (options.subBlock) {
+			blocking[i] = getLightBlockingFaces(block);
+		} else {
+			blocking[i] = isOpaqueBlock(block) ? ALL_FACES : 0;
+		}
+		const emission = getLightEmission(block);
+		if (emission > 0) {
+			light[i] = emission;
+			queue.push(pos.x, pos.y, pos.z);
+		}
+	}
+	// light levels of the cells just outside the edge, supplied by whoever holds the
+	// neighbouring schematic, flow in like any other source
+	if (options.neighborLight) {
+		for (const pos of schematic) {
+			const i = index(pos.x, pos.y, pos.z);
+			for (let face = 0; face < LIGHT_OFFSETS.length; face++) {
+				const [dx, dy, dz] = LIGHT_OFFSETS[face];
+				const nx = pos.x + dx;
+				const ny = pos.y + dy;
+				const nz = pos.z + dz;
+				const outside =
+					nx < 0 ||
+					ny < 0 ||
+					nz < 0 ||
+					nx >= width ||
+					ny >= height ||
+					nz >= length;
+				if (!outside || blocking[i] & (1 << face)) {
+					continue;
+				}
+				const level = options.neighborLight(nx, ny, nz) - 1;
+				if (level > light[i]) {
+					light[i] = level;
+					queue.push(pos.x, pos.y, pos.z);
+				}
+			}
+		}
+	}
+	for (let head = 0; head < queue.length; head += 3) {
+		const x = queue[head];
+		const y = queue[head + 1];
+		const z = queue[head + 2];
+		const i = index(x, y, z);
+		const level = light[i];
+		if (level <= 1) {
+			continue;
+		}
+		// full emitters like glowstone still light their surroundings
+		const sourceMask = blocking[i] === ALL_FACES ? 0 : blocking[i];
+		for (let face = 0; face < LIGHT_OFFSETS.length; face++) {
+			if (sourceMask & (1 << face)) {
+				continue;
+			}
+			const [dx, dy, dz] = LIGHT_OFFSETS[face];
+			const nx = x + dx;
+			const ny = y + dy;
+			const nz = z + dz;
+			if (
+				nx < 0 ||
+				ny < 0 ||
+				nz < 0 ||
+				nx >= width ||
+				ny >= height ||
+				nz >= length
+			) {
+				continue;
+			}
+			const ni = index(nx, ny, nz);
+			if (blocking[ni] & (1 << (face ^ 1)) || light[ni] >= level - 1) {
+				continue;
+			}
+			light[ni] = level - 1;
+			queue.push(nx, ny, nz);
+		}
+	}
+	return { light, index };
+}
+
+// emitter positions for hosts that place point lights, offset into world space
+export function getLightSources(
+	schematic: any,
+	offset: { x: number; y: number; z: number }
+) {
+	const sources: { position: number[]; level: number; type: string }[] = [];
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		const level = getLightEmission(block);
+		if (level === 0) {
+			continue;
+		}
+		const [ox, oy, oz] = getLightEmitterOffset(block);
+		sources.push({
+			position: [
+				pos.x + ox + offset.x,
+				pos.y + oy + offset.y,
+				pos.z + oz + offset.z,
+			],
+			level,
+			type: block.type,
+		});
+	}
+	return sources;
+}
+
+function getLightLevelColor(level: number, threshold: number) {
+	if (level < threshold) {
+		return new THREE.Color(1, 0, 0);
+	}
+	// from yellow at the threshold to green at full brightness
+	const t = (level - threshold) / Math.max(1, 15 - threshold);
+	return new THREE.Color(1 - t, 1, 0);
+}
+
+// a surface is an empty cell resting on an opaque block, which is where mobs could spawn
+export function buildLightOverlay(
+	schematic: any,
+	offset: { x: number; y: number; z: number },
+	options: LightOverlayOptions = {}
+) {
+	const threshold = options.spawnThreshold ?? 1;
+	const heightOffset = options.heightOffset ?? 0.02;
+	const { light, index } = computeBlockLight(schematic, {
+		subBlock: options.subBlockLight,
+		neighborLight: options.neighborLight,
+	});
+	const positions: number[] = [];
+	const colors: number[] = [];
+	const indices: number[] = [];
+	const levels: { x: number; y: number; z: number; level: number }[] = [];
+	for (const pos of schematic) {
+		if (pos.y === 0) {
+			continue;
+		}
+		const block = schematic.getBlock(pos);
+		if (block && !INVISIBLE_BLOCKS.has(block.type)) {
+			continue;
+		}
+		const below = schematic.getBlock(
+			new THREE.Vector3(pos.x, pos.y - 1, pos.z)
+		);
+		if (!isOpaqueBlock(below)) {
+			continue;
+		}
+		const level = light[index(pos.x, pos.y, pos.z)];
+		if (level >= threshold && !options.showAllLevels) {
+			continue;
+		}
+		levels.push({ x: pos.x, y: pos.y, z: pos.z, level });
+		const color = getLightLevelColor(level, threshold);
+		const vertexOffset = positions.length / 3;
+		for (const [cx, cz] of [
+			[0, 0],
+			[0, 1],
+			[1, 0],
+			[1, 1],
+		]) {
+			positions.push(
+				pos.x + cx + offset.x,
+				pos.y + heightOffset + offset.y,
+				pos.z + cz + offset.z
+			);
+			colors.push(color.r, color.g, color.b);
+		}
+		indices.push(
+			vertexOffset,
+			vertexOffset + 1,
+			vertexOffset + 2,
+			vertexOffset + 2,
+			vertexOffset + 1,
+			vertexOffset + 3
+		);
+	}
+	const geometry = new THREE.BufferGeometry();
+	geometry.setAttribute(
+		"position",
+		new THREE.BufferAttribute(new Float32Array(positions), 3)
+	);
+	geometry.setAttribute(
+		"color",
+		new THREE.BufferAttribute(new Float32Array(colors), 3)
+	);
+	geometry.setIndex(indices);
+	const material = new THREE.MeshBasicMaterial({
+		vertexColors: true,
+		transparent: true,
+		opacity: 0.5,
+		depthWrite: false,
+	});
+	const mesh = new THREE.Mesh(geometry, material);
+	mesh.name = "LightOverlay";
+	mesh.userData.levels = levels;
+	if (options.subBlockLight) {
+		mesh.userData.sources = getLightSources(schematic, offset);
+	}
+	return mesh;
+}
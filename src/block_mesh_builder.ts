@@ -2,7 +2,15 @@ import * as THREE from "three";
 
 import type { loadSchematic } from "@enginehub/schematicjs";
 
-import type { BlockModel, BlockModelData, Vector } from "./types";
+import type {
+	BlockModel,
+	BlockModelData,
+	Faces,
+	GeometryDiagnostic,
+	BlockRotation,
+	MaterialDescriptor,
+	Vector,
+} from "./types";
 
 import {
 	isExtendedPiston,
@@ -12,12 +20,42 @@ import {
 	normalize,
 	TRANSPARENT_BLOCKS,
 	getDirectionData,
+	getPairedBlockFace,
+	joinPairedElement,
+	getPartialBlockHeight,
 	hashBlockForMap,
 	POSSIBLE_FACES,
 	DEFAULT_UV,
+	SNOWY_BLOCKS,
+	SNOW_COVER_BLOCKS,
+	HORIZONTAL_FACES,
+	RAIL_BLOCKS,
+	getRailShape,
+	isOpaqueBlock,
+	isWallAttachedBlock,
+	emitWarning,
+	faceToFacingVector,
+	createCubeComponents,
+	isFlushFace,
+	isQuarterTurn,
 } from "./utils";
+import { VOX_COLOR_BLOCK } from "./vox_loader";
+import {
+	getFaceForNormal,
+	getFaceNormal,
+	getUvLockTransform,
+	getUvLockUv,
+	quantizeRotation,
+	rotateBlockComponents,
+	rotateFaceUv,
+} from "./block_rotation";
+import { GeometryPool } from "./geometry_dedup";
+import { offsetCoplanarComponents } from "./coplanar_offset";
 
 import { ResourceLoader } from "./resource_loader";
+import { logger } from "./logger";
+
+export const UNKNOWN_BLOCK_MATERIAL_ID = "unknown-block";
 
 export class BlockMeshBuilder {
 	public blockMeshCache: Map<any, any>;
@@ -27,11 +65,22 @@ export class BlockMeshBuilder {
 	schematic: any;
 	renderer: any;
 	faceDataCache: Map<string, any>;
+	options: any;
+	geometryDiagnostics: Map<string, GeometryDiagnostic[]>;
+	neighborStateCache: Map<string, any>;
+	paletteBlocks: Map<string, any>;
+	unknownBlocks: Map<string, any>;
+	materialDescriptors: Map<string, MaterialDescriptor>;
+	rotatedComponentCache: WeakMap<object, Map<string, any>>;
+	geometryPool: GeometryPool;
+	coplanarOffsetCache: WeakMap<object, any>;
+	faceCullingCache: WeakMap<object, Map<string, WeakMap<object, boolean>>>;
 
 	constructor(
 		ressourceLoader: any,
 		materialMap: Map<string, THREE.Material>,
-		renderer: any
+		renderer: any,
+		options: any = {}
 	) {
 		this.blockMeshCache = new Map();
 		this.materialMap = materialMap;
@@ -39,6 +88,16 @@ export class BlockMeshBuilder {
 		this.ressourceLoader = ressourceLoader;
 		this.renderer = renderer;
 		this.faceDataCache = new Map();
+		this.options = options ?? {};
+		this.geometryDiagnostics = new Map();
+		this.neighborStateCache = new Map();
+		this.paletteBlocks = new Map();
+		this.unknownBlocks = new Map();
+		this.materialDescriptors = new Map();
+		this.rotatedComponentCache = new WeakMap();
+		this.geometryPool = new GeometryPool();
+		this.coplanarOffsetCache = new WeakMap();
+		this.faceCullingCache = new WeakMap();
 	}
 
 	public setSchematic(schematic: any) {
@@ -50,11 +109,7 @@ export class BlockMeshBuilder {
 			faceData.texture,
 			model
 		);
-		const rotation = faceData.rotation;
-		const idSuffix = rotation ? `-${rotation}` : "";
-		return `${textureName}-${color?.r ?? 1}-${color?.g ?? 1}-${
-			color?.b ?? 1
-		}${idSuffix}`;
+		return `${textureName}-${color?.r ?? 1}-${color?.g ?? 1}-${color?.b ?? 1}`;
 	}
 
 	public normalizeElementCoords(element: BlockModel["elements"][0]) {
@@ -77,8 +132,11 @@ export class BlockMeshBuilder {
 	) {
 		const subMaterials: { [key: string]: string | null } = {};
 		const uvs: { [key: string]: [number, number, number, number] } = {};
+		// applied per corner in getBlockMesh, rotating the rect itself would mirror it
+		const rotations: { [key: string]: number } = {};
+		const explicitUvs: { [key: string]: boolean } = {};
 		if (!element.faces) {
-			return { subMaterials, uvs };
+			return { subMaterials, uvs, rotations, explicitUvs };
 		}
 		for (const face of POSSIBLE_FACES) {
 			const faceData: any = element.faces[face];
@@ -107,14 +165,21 @@ export class BlockMeshBuilder {
 				materialColor ?? new THREE.Color(1, 1, 1)
 			);
 			if (!this.materialMap.has(materialId)) {
+				const transparent =
+					TRANSPARENT_BLOCKS.has(block.type) ||
+					faceData.texture.includes("overlay");
 				// TODO: check performance, I think there might be redundant calls to getBase 64 Image
 				const material = await this.ressourceLoader.getTextureMaterial(
 					model,
 					faceData,
-					TRANSPARENT_BLOCKS.has(block.type) ||
-						faceData.texture.includes("overlay"),
+					transparent,
 					materialColor
 				);
+				this.materialDescriptors.set(materialId, {
+					texture: textureName,
+					transparent,
+					color: materialColor?.toArray(),
+				});
 
 				this.materialMap.set(
 					materialId,
@@ -132,37 +197,16 @@ export class BlockMeshBuilder {
 			// );
 
 			subMaterials[face] = materialId;
-			const faceRotation = faceData.rotation || 0;
-			uvs[face] = this.rotateUv(
-				(faceData.uv || DEFAULT_UV).map((u: number) => u / 16),
-				faceRotation
-			) as [number, number, number, number];
+			rotations[face] = faceData.rotation || 0;
+			explicitUvs[face] = faceData.uv !== undefined;
+			uvs[face] = (faceData.uv || DEFAULT_UV).map((u: number) => u / 16) as [
+				number,
+				number,
+				number,
+				number
+			];
 		}
-		return { subMaterials, uvs };
-	}
-
-	public rotateUv(uv: [number, number, number, number], rotation: number) {
-		rotation = (rotation * Math.PI) / 180;
-		const center = [0.5, 0.5];
-		const uvCentered = [
-			uv[0] - center[0],
-			uv[1] - center[1],
-			uv[2] - center[0],
-			uv[3] - center[1],
-		];
-		const uvRotated = [
-			uvCentered[0] * Math.cos(rotation) - uvCentered[1] * Math.sin(rotation),
-			uvCentered[0] * Math.sin(rotation) + uvCentered[1] * Math.cos(rotation),
-			uvCentered[2] * Math.cos(rotation) - uvCentered[3] * Math.sin(rotation),
-			uvCentered[2] * Math.sin(rotation) + uvCentered[3] * Math.cos(rotation),
-		];
-		const uvFinal = [
-			uvRotated[0] + center[0],
-			uvRotated[1] + center[1],
-			uvRotated[2] + center[0],
-			uvRotated[3] + center[1],
-		];
-		return uvFinal;
+		return { subMaterials, uvs, rotations, explicitUvs };
 	}
 
 	private popupWindow: Window | null = null;
@@ -177,7 +221,7 @@ export class BlockMeshBuilder {
 		}
 
 		if (!this.popupWindow) {
-			console.error("Failed to open popup window");
+			logger.error("Failed to open popup window");
 			return;
 		}
 
@@ -234,6 +278,45 @@ export class BlockMeshBuilder {
 		allImagesContainer.appendChild(imageContainer);
 	}
 
+	// synthetic blocks of imported voxel models only carry a color
+	public getColorCubeComponents(color: string) {
+		const materialId = `color-${color}`;
+		if (!this.materialMap.has(materialId)) {
+			this.materialMap.set(
+				materialId,
+				new THREE.MeshStandardMaterial({ color: new THREE.Color(color) })
+			);
+		}
+		return createCubeComponents(materialId);
+	}
+
+	// the classic missing texture checker, so unknown blocks leave a visible cube
+	// instead of a hole
+	public getUnknownBlockComponents() {
+		if (!this.materialMap.has(UNKNOWN_BLOCK_MATERIAL_ID)) {
+			const fallback = this.options.unknownBlockFallback;
+			const [first, second] = (
+				fallback === true ? ["#f800f8", "#000000"] : fallback.colors
+			).map((color: string | number) => new THREE.Color(color));
+			const pixels = new Uint8Array(16);
+			[first, second, second, first].forEach((color, i) => {
+				pixels.set(
+					[color.r * 255, color.g * 255, color.b * 255, 255],
+					i * 4
+				);
+			});
+			const texture = new THREE.DataTexture(pixels, 2, 2);
+			texture.magFilter = THREE.NearestFilter;
+			texture.minFilter = THREE.NearestFilter;
+			texture.needsUpdate = true;
+			this.materialMap.set(
+				UNKNOWN_BLOCK_MATERIAL_ID,
+				new THREE.MeshStandardMaterial({ map: texture })
+			);
+		}
+		return createCubeComponents(UNKNOWN_BLOCK_MATERIAL_ID);
+	}
+
 	public async getBlockMesh(
 		block: any,
 		_blockPosition?: any
@@ -255,8 +338,32 @@ export class BlockMeshBuilder {
 				uvs: number[];
 			};
 		} = {};
+		if (block.type === VOX_COLOR_BLOCK) {
+			return this.getColorCubeComponents(block.properties.color);
+		}
 		const faces = ["east", "west", "up", "down", "south", "north"];
 		const { modelOptions } = await this.ressourceLoader.getBlockMeta(block);
+		if (modelOptions.holders.length === 0) {
+			emitWarning(this.options, {
+				type: "unknown_block",
+				message: `No blockstate variant matched ${modelOptions.name}`,
+				block: hashBlockForMap(block),
+			});
+			if (this.options.unknownBlockFallback) {
+				this.unknownBlocks.set(hashBlockForMap(block), block);
+				return this.getUnknownBlockComponents();
+			}
+		}
+
+		const smoothingVertices: {
+			key: string;
+			index: number;
+			position: number[];
+			group?: string;
+		}[] = [];
+		const autoSmoothing = this.options.normalSmoothingAngle > 0;
+
+		const pairedFace = getPairedBlockFace(block);
 
 		let modelIndex = 0;
 		let start = performance.now();
@@ -270,14 +377,35 @@ export class BlockMeshBuilder {
 			};
 
 			const model = await this.ressourceLoader.loadModel(modelHolder.model);
+			// uvlock keeps textures aligned to the world when the variant is rotated,
+			// e.g. stairs and fences whose planks would otherwise turn with the block
+			const uvLocked = Boolean(
+				modelHolder.uvlock && (modelHolder.x || modelHolder.y)
+			);
 
 			const elements = model?.elements;
 			if (!elements) continue;
+			// the side facing the other half, turned back by the variant rotation
+			const modelPairedFace =
+				pairedFace &&
+				faces.find(
+					(face) =>
+						getFaceForNormal(
+							this.applyRotation(
+								getFaceNormal(face),
+								modelHolderRotation,
+								[0, 0, 0]
+							)
+						) === pairedFace
+				);
 			let elementIndex = 0;
 			for (const element of elements) {
 				elementIndex++;
 				if (!element.from || !element.to) continue;
 				this.normalizeElementCoords(element);
+				if (modelPairedFace) {
+					joinPairedElement(element, modelPairedFace);
+				}
 				let faceData;
 				const faceDataCacheKey = `${modelHolder.model}-${modelIndex}-${elementIndex}`;
 				if (this.faceDataCache.has(faceDataCacheKey)) {
@@ -286,6 +414,13 @@ export class BlockMeshBuilder {
 					try {
 						faceData = await this.processFaceData(element, model, block);
 					} catch (e) {
+						emitWarning(this.options, {
+							type: "face_data_error",
+							message: `Failed to process faces of ${modelHolder.model}`,
+							block: hashBlockForMap(block),
+							resource: modelHolder.model,
+							details: e,
+						});
 						continue;
 					}
 					this.faceDataCache.set(faceDataCacheKey, faceData);
@@ -302,20 +437,10 @@ export class BlockMeshBuilder {
 					const materialId = faceData.subMaterials[dir];
 					if (!materialId) continue;
 
-					const uniqueKey = `${materialId}-${dir}`;
-
-					if (!blockComponents[uniqueKey]) {
-						blockComponents[uniqueKey] = {
-							materialId,
-							face: dir,
-							positions: [],
-							normals: [],
-							uvs: [],
-						};
-					}
-
 					const dirData = directionData[dir];
-
+					const corners: { cornerPos: number[]; uv: number[] }[] = [];
+					// before the variant rotation, explicit uvs under uvlock need both
+					const modelPositions: number[][] = [];
 					for (const { pos, uv } of dirData.corners) {
 						if (!from || !size || !pos || !uv) continue;
 						let cornerPos = [
@@ -329,8 +454,81 @@ export class BlockMeshBuilder {
 								elementRotation as any
 							);
 						}
+						modelPositions.push(cornerPos);
 						cornerPos = this.applyRotation(cornerPos, modelHolderRotation);
+						if (this.options.vertexSnapGrid > 0) {
+							cornerPos = this.snapToGrid(
+								cornerPos,
+								this.options.vertexSnapGrid
+							);
+						}
+						corners.push({ cornerPos, uv });
+					}
+					let uvRect = faceData.uvs[dir];
+					const worldFace = uvLocked
+						? getFaceForNormal(
+								this.applyRotation(
+									dirData.normal,
+									modelHolderRotation,
+									[0, 0, 0]
+								)
+						  )
+						: undefined;
+					const explicitUv = Boolean(faceData.explicitUvs?.[dir]);
+					if (worldFace && !explicitUv) {
+						for (const corner of corners) {
+							corner.uv = getUvLockUv(worldFace, corner.cornerPos);
+						}
+						const us = corners.map(({ uv }) => uv[0]);
+						const vs = corners.map(({ uv }) => uv[1]);
+						uvRect = [
+							Math.min(...us),
+							Math.min(...vs),
+							Math.max(...us),
+							Math.max(...vs),
+						];
+					}
+					const uvRotation = faceData.rotations?.[dir] ?? 0;
+					if (uvRotation) {
+						for (const corner of corners) {
+							corner.uv = rotateFaceUv(corner.uv, uvRect, uvRotation);
+						}
+					}
+					// like vanilla an explicit rect is kept and turned with the block
+					if (worldFace && explicitUv) {
+						const transform = getUvLockTransform(
+							modelPositions.map((pos) => getUvLockUv(dir, pos)),
+							corners.map(({ cornerPos }) => getUvLockUv(worldFace, cornerPos))
+						);
+						for (const corner of corners) {
+							corner.uv = transform(corner.uv);
+						}
+					}
+
+					// faces off the block boundary go into their own component that is never
+					// culled, otherwise a neighbor on that side would hide them too
+					const flush =
+						!this.options.exactCulling ||
+						isFlushFace(
+							dir,
+							corners.map(({ cornerPos }) => cornerPos)
+						);
+					const uniqueKey = flush
+						? `${materialId}-${dir}`
+						: `${materialId}-${dir}-inner`;
+
+					if (!blockComponents[uniqueKey]) {
+						blockComponents[uniqueKey] = {
+							materialId,
+							face: dir,
+							positions: [],
+							normals: [],
+							uvs: [],
+							...(flush ? {} : { cullable: false }),
+						};
+					}
 
+					for (const { cornerPos, uv } of corners) {
 						blockComponents[uniqueKey].positions.push(...cornerPos);
 						if (block.type === "redstone_wire") {
 							blockComponents[uniqueKey].uvs.push(uv[0], 1 - uv[1]);
@@ -338,23 +536,98 @@ export class BlockMeshBuilder {
 							blockComponents[uniqueKey].uvs.push(1 - uv[0], 1 - uv[1]);
 						}
 						blockComponents[uniqueKey].normals.push(...dirData.normal);
+						if (element.smoothing_group || autoSmoothing) {
+							smoothingVertices.push({
+								key: uniqueKey,
+								index: blockComponents[uniqueKey].positions.length / 3 - 1,
+								position: cornerPos,
+								group: element.smoothing_group,
+							});
+						}
 					}
 				}
 			}
 		}
 
-		if (performance.now() - start > 50) {
-			console.error(
-				"Slow block mesh builder",
-				block,
-				"took",
-				performance.now() - start
-			);
+		if (smoothingVertices.length > 0) {
+			this.smoothNormals(blockComponents, smoothingVertices);
+		}
+
+		const elapsed = performance.now() - start;
+		if (elapsed > 50) {
+			const handled = emitWarning(this.options, {
+				type: "slow_block",
+				message: `Building the mesh of ${block.type} took ${elapsed}ms`,
+				block: hashBlockForMap(block),
+				details: { elapsed },
+			});
+			if (!handled) {
+				logger.warn("Slow block mesh builder", block, "took", elapsed);
+			}
 		}
 
 		return blockComponents;
 	}
 
+	// explicit smoothing groups always win, otherwise vertices at the same position are
+	// averaged when the angle between their normals is below normalSmoothingAngle
+	public smoothNormals(
+		blockComponents: any,
+		vertices: {
+			key: string;
+			index: number;
+			position: number[];
+			group?: string;
+		}[]
+	) {
+		const threshold = Math.cos(
+			((this.options.normalSmoothingAngle ?? 0) * Math.PI) / 180
+		);
+		const getNormal = (vertex: (typeof vertices)[number]) =>
+			new THREE.Vector3().fromArray(
+				blockComponents[vertex.key].normals,
+				vertex.index * 3
+			);
+		const buckets = new Map<string, (typeof vertices)[number][]>();
+		for (const vertex of vertices) {
+			const bucketKey = vertex.position
+				.map((value) => Math.round(value * 1e4))
+				.join(",");
+			if (!buckets.has(bucketKey)) {
+				buckets.set(bucketKey, []);
+			}
+			buckets.get(bucketKey)!.push(vertex);
+		}
+		const smoothed: [(typeof vertices)[number], THREE.Vector3][] = [];
+		for (const bucket of buckets.values()) {
+			const normals = bucket.map(getNormal);
+			for (let i = 0; i < bucket.length; i++) {
+				const sum = new THREE.Vector3();
+				for (let j = 0; j < bucket.length; j++) {
+					const sameGroup = bucket[i].group
+						? bucket[i].group === bucket[j].group
+						: !bucket[j].group &&
+						  this.options.normalSmoothingAngle > 0 &&
+						  normals[i].dot(normals[j]) >= threshold;
+					if (i === j || sameGroup) {
+						sum.add(normals[j]);
+					}
+				}
+				if (sum.lengthSq() > 0) {
+					smoothed.push([bucket[i], sum.normalize()]);
+				}
+			}
+		}
+		for (const [vertex, normal] of smoothed) {
+			normal.toArray(blockComponents[vertex.key].normals, vertex.index * 3);
+		}
+	}
+
+	// rounds to 1/grid of a block, closing the hairline cracks left by float noise in custom models
+	public snapToGrid(position: number[], grid: number) {
+		return position.map((value) => Math.round(value * grid) / grid);
+	}
+
 	private applyRotation(
 		position: number[],
 		rotation: { x: number; y: number; z: number },
@@ -364,6 +637,17 @@ export class BlockMeshBuilder {
 		y = -y;
 		x = -x;
 		z = -z;
+		// quarter turns get exact integer sines and cosines so face planes stay on the
+		// 1/16 grid for exact culling
+		const exact = this.options.exactCulling;
+		const sin = (angle: number) =>
+			exact && isQuarterTurn(angle)
+				? Math.round(Math.sin(angle))
+				: Math.sin(angle);
+		const cos = (angle: number) =>
+			exact && isQuarterTurn(angle)
+				? Math.round(Math.cos(angle))
+				: Math.cos(angle);
 
 		const translatedPosition = [
 			position[0] - center[0],
@@ -373,16 +657,16 @@ export class BlockMeshBuilder {
 
 		const rotationMatrix = [
 			[
-				Math.cos(y) * Math.cos(z),
-				Math.sin(x) * Math.sin(y) * Math.cos(z) - Math.cos(x) * Math.sin(z),
-				Math.cos(x) * Math.sin(y) * Math.cos(z) + Math.sin(x) * Math.sin(z),
+				cos(y) * cos(z),
+				sin(x) * sin(y) * cos(z) - cos(x) * sin(z),
+				cos(x) * sin(y) * cos(z) + sin(x) * sin(z),
 			],
 			[
-				Math.cos(y) * Math.sin(z),
-				Math.sin(x) * Math.sin(y) * Math.sin(z) + Math.cos(x) * Math.cos(z),
-				Math.cos(x) * Math.sin(y) * Math.sin(z) - Math.sin(x) * Math.cos(z),
+				cos(y) * sin(z),
+				sin(x) * sin(y) * sin(z) + cos(x) * cos(z),
+				cos(x) * sin(y) * sin(z) - sin(x) * cos(z),
 			],
-			[-Math.sin(y), Math.sin(x) * Math.cos(y), Math.cos(x) * Math.cos(y)],
+			[-sin(y), sin(x) * cos(y), cos(x) * cos(y)],
 		];
 
 		const rotatedPosition = [0, 0, 0];
@@ -457,6 +741,88 @@ export class BlockMeshBuilder {
 
 		return finalPosition;
 	}
+	// derives state that depends on neighbours instead of trusting what was saved,
+	// so grass under snow gets its snowy sides without a pre-duplicated palette entry
+	public resolveNeighborDependentState(block: any, pos: THREE.Vector3) {
+		const overrides = this.getNeighborDependentProperties(block, pos);
+		if (
+			!overrides ||
+			Object.keys(overrides).every(
+				(key) => block.properties?.[key] === overrides[key]
+			)
+		) {
+			return block;
+		}
+		const cacheKey = `${hashBlockForMap(block)}-${JSON.stringify(overrides)}`;
+		if (!this.neighborStateCache.has(cacheKey)) {
+			this.neighborStateCache.set(cacheKey, {
+				...block,
+				properties: { ...block.properties, ...overrides },
+			});
+		}
+		return this.neighborStateCache.get(cacheKey);
+	}
+
+	public getNeighborDependentProperties(
+		block: any,
+		pos: THREE.Vector3
+	): { [key: string]: string } | undefined {
+		const neighbor = (face: string) => {
+			const [dx, dy, dz] = faceToFacingVector(face as Faces);
+			return this.schematic.getBlock(
+				new THREE.Vector3(pos.x + dx, pos.y + dy, pos.z + dz)
+			);
+		};
+		// shapes are recomputed when missing, or always with options.resolveRailShapes
+		if (
+			RAIL_BLOCKS.has(block.type) &&
+			(this.options.resolveRailShapes || !block.properties?.["shape"])
+		) {
+			const isRail = (dx: number, dy: number, dz: number) =>
+				RAIL_BLOCKS.has(
+					this.schematic.getBlock(
+						new THREE.Vector3(pos.x + dx, pos.y + dy, pos.z + dz)
+					)?.type
+				);
+			const connections: { [face: string]: "flat" | "up" | undefined } = {};
+			for (const face of HORIZONTAL_FACES) {
+				const [dx, , dz] = faceToFacingVector(face);
+				if (isRail(dx, 1, dz)) {
+					connections[face] = "up";
+				} else if (isRail(dx, 0, dz) || isRail(dx, -1, dz)) {
+					connections[face] = "flat";
+				}
+			}
+			const shape = getRailShape(block.type, connections);
+			return shape ? { shape } : undefined;
+		}
+		if (SNOWY_BLOCKS.has(block.type)) {
+			const above = neighbor("up");
+			const snowy = above !== undefined && SNOW_COVER_BLOCKS.has(above.type);
+			return { snowy: String(snowy) };
+		}
+		// wall attached blocks face away from the wall they hang on
+		if (isWallAttachedBlock(block.type) && !block.properties?.["facing"]) {
+			const facing = HORIZONTAL_FACES.find((face) =>
+				isOpaqueBlock(neighbor(getOppositeFace(face)))
+			);
+			return facing ? { facing } : undefined;
+		}
+		if (
+			block.type === "vine" &&
+			![...HORIZONTAL_FACES, "up"].some(
+				(face) => block.properties?.[face] === "true"
+			)
+		) {
+			const sides: { [key: string]: string } = {};
+			for (const face of [...HORIZONTAL_FACES, "up"]) {
+				sides[face] = String(isOpaqueBlock(neighbor(face)));
+			}
+			return sides;
+		}
+		return undefined;
+	}
+
 	public occludedFacesListToInt(occludedFaces: { [key: string]: boolean }) {
 		let result = 0;
 		for (const face of POSSIBLE_FACES) {
@@ -484,9 +850,35 @@ export class BlockMeshBuilder {
 			south: false,
 			north: false,
 		} as { [key: string]: boolean };
+		// both halves of a paired structure are culled as one unit, even when they are not opaque
+		const pairedFace = getPairedBlockFace(block);
+		if (pairedFace) {
+			const partner = this.schematic.getBlock(
+				new THREE.Vector3(x, y, z).add(
+					directionVectors[pairedFace as keyof typeof directionVectors]
+				)
+			);
+			if (
+				partner?.type === blockType &&
+				getPairedBlockFace(partner) === getOppositeFace(pairedFace)
+			) {
+				occludedFaces[pairedFace] = true;
+			}
+		}
+		const height = getPartialBlockHeight(block);
+		if (height !== undefined && height < 1) {
+			return this.getOccludedFacesForPartialBlock(
+				block,
+				pos,
+				height,
+				occludedFaces,
+				directionVectors
+			);
+		}
 		if (
-			NON_OCCLUDING_BLOCKS.has(blockType) ||
-			TRANSPARENT_BLOCKS.has(blockType)
+			height === undefined &&
+			(NON_OCCLUDING_BLOCKS.has(blockType) ||
+				TRANSPARENT_BLOCKS.has(blockType))
 		) {
 			return this.occludedFacesListToInt(occludedFaces);
 		}
@@ -505,17 +897,89 @@ export class BlockMeshBuilder {
 			if (adjacentBlock === undefined) {
 				continue;
 			}
-			if (NON_OCCLUDING_BLOCKS.has(adjacentBlock.type)) {
+			occludedFaces[face] ||= this.isFaceCulledBy(
+				block,
+				face,
+				adjacentBlock,
+				() => this.isFaceCoveredBy(face, adjacentBlock)
+			);
+		}
+		return this.occludedFacesListToInt(occludedFaces);
+	}
+
+	// farmland, paths and snow layers have a full bottom and sides that are only hidden
+	// by opaque neighbours or by partial neighbours of the same height, their top is always visible
+	public getOccludedFacesForPartialBlock(
+		block: any,
+		pos: THREE.Vector3,
+		height: number,
+		occludedFaces: { [key: string]: boolean },
+		directionVectors: { [key: string]: THREE.Vector3 }
+	) {
+		for (const face of POSSIBLE_FACES) {
+			if (face === "up") {
 				continue;
 			}
-			if (TRANSPARENT_BLOCKS.has(adjacentBlock.type)) {
+			const adjacentBlock = this.schematic.getBlock(
+				pos.clone().add(directionVectors[face])
+			);
+			if (adjacentBlock === undefined) {
 				continue;
 			}
-			occludedFaces[face] = true;
+			occludedFaces[face] ||= this.isFaceCulledBy(
+				block,
+				face,
+				adjacentBlock,
+				() => this.isFaceCoveredBy(face, adjacentBlock, height)
+			);
 		}
 		return this.occludedFacesListToInt(occludedFaces);
 	}
 
+	// height is set when the block itself is partial, then only neighbours at least as
+	// tall cover its sides
+	public isFaceCoveredBy(face: string, adjacentBlock: any, height?: number) {
+		const adjacentHeight = getPartialBlockHeight(adjacentBlock);
+		if (adjacentHeight !== undefined) {
+			if (height === undefined) {
+				// partial blocks only cover the face below them, unless they fill the whole cell
+				return face === "up" || adjacentHeight >= 1;
+			}
+			const side = face !== "up" && face !== "down";
+			return adjacentHeight >= 1 || (side && adjacentHeight >= height);
+		}
+		return (
+			!NON_OCCLUDING_BLOCKS.has(adjacentBlock.type) &&
+			!TRANSPARENT_BLOCKS.has(adjacentBlock.type)
+		);
+	}
+
+	// whether a face is hidden only depends on the two palette entries and the face, so
+	// each pair is tested once and later lookups skip the geometric checks
+	public isFaceCulledBy(
+		block: any,
+		face: string,
+		adjacentBlock: any,
+		test: () => boolean
+	) {
+		let byFace = this.faceCullingCache.get(block);
+		if (!byFace) {
+			byFace = new Map();
+			this.faceCullingCache.set(block, byFace);
+		}
+		let byAdjacent = byFace.get(face);
+		if (!byAdjacent) {
+			byAdjacent = new WeakMap();
+			byFace.set(face, byAdjacent);
+		}
+		let culled = byAdjacent.get(adjacentBlock);
+		if (culled === undefined) {
+			culled = test();
+			byAdjacent.set(adjacentBlock, culled);
+		}
+		return culled;
+	}
+
 	public async updateBlockModelLookup(
 		blockModelLookup: Map<string, BlockModelData>,
 		loadedSchematic: ReturnType<typeof loadSchematic>
@@ -547,25 +1011,196 @@ export class BlockMeshBuilder {
 		return blockModelLookup;
 	}
 
+	public getCoplanarOffsetMesh(blockComponents: any) {
+		let shifted = this.coplanarOffsetCache.get(blockComponents);
+		if (!shifted) {
+			shifted = offsetCoplanarComponents(
+				blockComponents,
+				this.options.coplanarOffset
+			);
+			this.coplanarOffsetCache.set(blockComponents, shifted);
+		}
+		return shifted;
+	}
+
+	// everything here is rebuilt on demand, the block meshes only go when asked to since
+	// they are the expensive part of a rebuild
+	public compact(options: { dropBlockMeshes?: boolean } = {}) {
+		let released =
+			this.neighborStateCache.size + this.geometryPool.buckets.size;
+		this.rotatedComponentCache = new WeakMap();
+		this.neighborStateCache.clear();
+		this.geometryPool.buckets.clear();
+		this.coplanarOffsetCache = new WeakMap();
+		this.faceCullingCache = new WeakMap();
+		if (options.dropBlockMeshes) {
+			released += this.blockMeshCache.size;
+			this.blockMeshCache.clear();
+			this.paletteBlocks.clear();
+		}
+		return released;
+	}
+
+	// front loads the one time work for a set of palette entries before the first build:
+	// materials, deduplicated geometry, offset and rotated variants and the culling results
+	// against neighbours of the same block, which is the common case
+	public async prepareBlocks(blocks: any[], rotations: BlockRotation[] = []) {
+		for (const block of blocks) {
+			let blockComponents = await this.getBlockMeshFromCache(block);
+			// rotations are cached per input, so rotate what the build will hand in
+			if (this.options.coplanarOffset > 0) {
+				blockComponents = this.getCoplanarOffsetMesh(blockComponents);
+			}
+			for (const rotation of rotations) {
+				this.getRotatedBlockMesh(blockComponents, rotation);
+			}
+			const height = getPartialBlockHeight(block);
+			for (const face of POSSIBLE_FACES) {
+				if (height !== undefined && height < 1 && face === "up") {
+					continue;
+				}
+				this.isFaceCulledBy(block, face, block, () =>
+					this.isFaceCoveredBy(
+						face,
+						block,
+						height !== undefined && height < 1 ? height : undefined
+					)
+				);
+			}
+		}
+		return blocks.length;
+	}
+
+	// per instance rotations from options.blockRotation, keyed by the components rather
+	// than the block since xray shells and coplanar offsets hand in other geometry for it
+	public getRotatedBlockMesh(blockComponents: any, rotation: BlockRotation) {
+		const quantized = quantizeRotation(rotation);
+		if (quantized.x === 0 && quantized.y === 0) {
+			return blockComponents;
+		}
+		let rotations = this.rotatedComponentCache.get(blockComponents);
+		if (!rotations) {
+			rotations = new Map();
+			this.rotatedComponentCache.set(blockComponents, rotations);
+		}
+		const key = `${quantized.x},${quantized.y}`;
+		let rotated = rotations.get(key);
+		if (!rotated) {
+			rotated = rotateBlockComponents(blockComponents, quantized);
+			rotations.set(key, rotated);
+		}
+		return rotated;
+	}
+
 	public async getBlockMeshFromCache(block: any, pos?: any) {
 		const blockUniqueKey = hashBlockForMap(block);
 		if (this.blockMeshCache.has(blockUniqueKey)) {
 			return this.blockMeshCache.get(blockUniqueKey);
 		} else {
 			const start = performance.now();
-			const blockComponents = await this.getBlockMesh(block, pos);
+			const blockComponents = this.geometryPool.dedupe(
+				await this.getBlockMesh(block, pos)
+			);
 			// if over 100ms log the block
-			if (performance.now() - start > 100) {
-				console.error(
-					"Slow block",
-					pos,
+			const elapsed = performance.now() - start;
+			if (elapsed > 100) {
+				const handled = emitWarning(this.options, {
+					type: "slow_block",
+					message: `Slow block ${block.type} took ${elapsed}ms`,
+					block: blockUniqueKey,
+					details: { elapsed, position: pos },
+				});
+				if (!handled) {
+					logger.warn("Slow block", pos, block, "took", elapsed);
+				}
+			}
+			if (this.options.validateGeometry) {
+				const diagnostics = this.validateBlockComponents(
 					block,
-					"took",
-					performance.now() - start
+					blockComponents
 				);
+				if (diagnostics.length > 0) {
+					this.geometryDiagnostics.set(blockUniqueKey, diagnostics);
+					emitWarning(this.options, {
+						type: "invalid_geometry",
+						message: `${diagnostics.length} geometry issue(s) in ${block.type}`,
+						block: blockUniqueKey,
+						details: diagnostics,
+					});
+				}
 			}
 			this.blockMeshCache.set(blockUniqueKey, blockComponents);
+			this.paletteBlocks.set(blockUniqueKey, block);
 			return blockComponents;
 		}
 	}
+
+	public validateBlockComponents(
+		block: any,
+		blockComponents: any
+	): GeometryDiagnostic[] {
+		const diagnostics: GeometryDiagnostic[] = [];
+		const paletteKey = hashBlockForMap(block);
+		const report = (
+			component: string,
+			issue: GeometryDiagnostic["issue"],
+			message: string
+		) => {
+			diagnostics.push({
+				paletteKey,
+				blockName: block.type,
+				component,
+				issue,
+				message,
+			});
+		};
+		const epsilon = 1e-4;
+
+		for (const key in blockComponents) {
+			const { positions, normals, uvs } = blockComponents[key];
+			const vertexCount = positions.length / 3;
+			if (
+				positions.length % 12 !== 0 ||
+				normals.length !== positions.length ||
+				uvs.length !== vertexCount * 2
+			) {
+				report(
+					key,
+					"attribute_mismatch",
+					`positions: ${positions.length}, normals: ${normals.length}, uvs: ${uvs.length}`
+				);
+				continue;
+			}
+			const values = [...positions, ...normals, ...uvs];
+			if (values.some((value) => !Number.isFinite(value))) {
+				report(key, "nan", "non-finite value in vertex attributes");
+				continue;
+			}
+			if (
+				positions.some(
+					(value: number) => value < -epsilon || value > 1 + epsilon
+				)
+			) {
+				report(key, "out_of_bounds", "vertex outside of the unit cube");
+			}
+			for (let i = 0; i < positions.length; i += 12) {
+				const a = new THREE.Vector3().fromArray(positions, i);
+				const b = new THREE.Vector3().fromArray(positions, i + 3);
+				const c = new THREE.Vector3().fromArray(positions, i + 6);
+				const faceNormal = b.sub(a).cross(c.sub(a));
+				if (faceNormal.lengthSq() < epsilon) {
+					continue;
+				}
+				const normal = new THREE.Vector3().fromArray(normals, i);
+				if (faceNormal.dot(normal) < 0) {
+					report(
+						key,
+						"inverted_normal",
+						`quad ${i / 12} winding disagrees with its normal`
+					);
+				}
+			}
+		}
+		return diagnostics;
+	}
 }
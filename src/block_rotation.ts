@@ -0,0 +1,182 @@
+import * as THREE from "three";
+import type { BlockRotation } from "./types";
+
+const FACE_DIRECTIONS: [string, THREE.Vector3][] = [
+	["east", new THREE.Vector3(1, 0, 0)],
+	["west", new THREE.Vector3(-1, 0, 0)],
+	["up", new THREE.Vector3(0, 1, 0)],
+	["down", new THREE.Vector3(0, -1, 0)],
+	["south", new THREE.Vector3(0, 0, 1)],
+	["north", new THREE.Vector3(0, 0, -1)],
+];
+
+// snaps to quarter turns so every block only has 16 possible orientations to cache
+export function quantizeRotation(rotation: BlockRotation) {
+	const snap = (angle = 0) =>
+		(((Math.round(angle / 90) * 90) % 360) + 360) % 360;
+	return { x: snap(rotation.x), y: snap(rotation.y) };
+}
+
+export function getFaceNormal(face: string) {
+	const direction = FACE_DIRECTIONS.find(([name]) => name === face)?.[1];
+	return direction ? direction.toArray() : [0, 0, 0];
+}
+
+function getRotatedFace(face: string, matrix: THREE.Matrix4) {
+	const direction = FACE_DIRECTIONS.find(([name]) => name === face)?.[1];
+	if (!direction) {
+		return face;
+	}
+	const rotated = direction.clone().transformDirection(matrix);
+	let best = face;
+	let bestDot = -Infinity;
+	for (const [name, candidate] of FACE_DIRECTIONS) {
+		const dot = rotated.dot(candidate);
+		if (dot > bestDot) {
+			bestDot = dot;
+			best = name;
+		}
+	}
+	return best;
+}
+
+export function getFaceForNormal(normal: number[]) {
+	const direction = new THREE.Vector3(normal[0], normal[1], normal[2]);
+	let best = FACE_DIRECTIONS[0][0];
+	let bestDot = -Infinity;
+	for (const [name, candidate] of FACE_DIRECTIONS) {
+		const dot = direction.dot(candidate);
+		if (dot > bestDot) {
+			bestDot = dot;
+			best = name;
+		}
+	}
+	return best;
+}
+
+// the uv an unrotated full face pointing this way has at the position, laid out
+// like CORNER_DICTIONARY, so a uvlocked face lines up with the world not the model
+export function getUvLockUv(face: string, pos: number[]) {
+	const [x, y, z] = pos;
+	switch (face) {
+		case "east":
+			return [z, 1 - y];
+		case "west":
+			return [1 - z, 1 - y];
+		case "up":
+			return [x, z];
+		case "down":
+			return [1 - x, 1 - z];
+		default:
+			return [x, 1 - y];
+	}
+}
+
+// the eight ways to turn or mirror the unit square onto itself
+const UV_SYMMETRIES: ((u: number, v: number) => number[])[] = [
+	(u, v) => [u, v],
+	(u, v) => [1 - v, u],
+	(u, v) => [1 - u, 1 - v],
+	(u, v) => [v, 1 - u],
+	(u, v) => [1 - u, v],
+	(u, v) => [u, 1 - v],
+	(u, v) => [v, u],
+	(u, v) => [1 - v, 1 - u],
+];
+
+// the symmetry that takes a face's uvlock uvs before the variant rotation to
+// the ones after it, explicit uvs are carried through it instead of being replaced
+export function getUvLockTransform(modelUvs: number[][], worldUvs: number[][]) {
+	let best = UV_SYMMETRIES[0];
+	let bestError = Infinity;
+	for (const symmetry of UV_SYMMETRIES) {
+		let error = 0;
+		modelUvs.forEach((uv, i) => {
+			const [u, v] = symmetry(uv[0], uv[1]);
+			error += Math.abs(u - worldUvs[i][0]) + Math.abs(v - worldUvs[i][1]);
+		});
+		if (error < bestError) {
+			bestError = error;
+			best = symmetry;
+		}
+	}
+	return (uv: number[]) => best(uv[0], uv[1]);
+}
+
+// model face rotation walks the corners of the uv rect the way vanilla does, a
+// mirrored rect turns the other way, which is why the raw rect is needed here
+export function rotateFaceUv(uv: number[], rect: number[], rotation: number) {
+	const steps = ((Math.round(rotation / 90) % 4) + 4) % 4;
+	if (steps === 0) {
+		return uv;
+	}
+	const cycle = [
+		[rect[0], rect[1]],
+		[rect[0], rect[3]],
+		[rect[2], rect[3]],
+		[rect[2], rect[1]],
+	];
+	let index = 0;
+	let bestDistance = Infinity;
+	for (let i = 0; i < 4; i++) {
+		const distance =
+			Math.abs(cycle[i][0] - uv[0]) + Math.abs(cycle[i][1] - uv[1]);
+		if (distance < bestDistance) {
+			bestDistance = distance;
+			index = i;
+		}
+	}
+	return cycle[(index + steps) % 4];
+}
+
+// same convention as blockstate variants, x first then y, both clockwise, positions are
+// snapped to the 1/16 model grid afterwards so quarter turns don't leave float drift
+export function rotateBlockComponents(
+	blockComponents: { [key: string]: any },
+	rotation: { x: number; y: number }
+) {
+	const matrix = new THREE.Matrix4().makeRotationFromEuler(
+		new THREE.Euler(
+			THREE.MathUtils.degToRad(-rotation.x),
+			THREE.MathUtils.degToRad(-rotation.y),
+			0,
+			"YXZ"
+		)
+	);
+	const vector = new THREE.Vector3();
+	const snap = (value: number) => Math.round(value * 16 * 1024) / (16 * 1024);
+	const rotated: { [key: string]: any } = {};
+	for (const [key, component] of Object.entries(blockComponents)) {
+		const positions = new Array(component.positions.length);
+		const normals = new Array(component.normals.length);
+		for (let i = 0; i < component.positions.length; i += 3) {
+			vector
+				.set(
+					component.positions[i] - 0.5,
+					component.positions[i + 1] - 0.5,
+					component.positions[i + 2] - 0.5
+				)
+				.applyMatrix4(matrix);
+			positions[i] = snap(vector.x + 0.5);
+			positions[i + 1] = snap(vector.y + 0.5);
+			positions[i + 2] = snap(vector.z + 0.5);
+			vector
+				.set(
+					component.normals[i],
+					component.normals[i + 1],
+					component.normals[i + 2]
+				)
+				.transformDirection(matrix);
+			normals[i] = vector.x;
+			normals[i + 1] = vector.y;
+			normals[i + 2] = vector.z;
+		}
+		rotated[key] = {
+			...component,
+			face: getRotatedFace(component.face, matrix),
+			positions,
+			normals,
+		};
+	}
+	return rotated;
+}
@@ -0,0 +1,102 @@
+import { getRenderCategory, TRANSPARENT_BLOCKS } from "./utils";
+import type {
+	CategoryInferenceOverrides,
+	CategoryRenderSettings,
+	PaletteCategory,
+} from "./types";
+
+const FLUID_BLOCKS = new Set(["water", "lava", "bubble_column"]);
+
+const TRANSLUCENT_BLOCKS = new Set([
+	"ice",
+	"frosted_ice",
+	"slime_block",
+	"honey_block",
+	"nether_portal",
+	"tinted_glass",
+]);
+
+const CUTOUT_BLOCKS = new Set([
+	"iron_bars",
+	"chain",
+	"ladder",
+	"cobweb",
+	"scaffolding",
+	"spawner",
+	"beacon",
+	"sugar_cane",
+	"bamboo",
+	"cactus",
+	"fire",
+	"soul_fire",
+]);
+
+// checked in order, the first suffix that matches decides
+const SUFFIX_RULES: [string, PaletteCategory][] = [
+	["_stained_glass_pane", "translucent"],
+	["_stained_glass", "translucent"],
+	["glass_pane", "cutout"],
+	["glass", "cutout"],
+	["_leaves", "cutout"],
+	["_sapling", "cutout"],
+	["_door", "cutout"],
+	["_trapdoor", "cutout"],
+	["rail", "cutout"],
+	["_tulip", "cutout"],
+	["_bush", "cutout"],
+	["_mushroom", "cutout"],
+	["_sign", "decoration"],
+	["_head", "decoration"],
+	["_skull", "decoration"],
+];
+
+// modded ids keep their namespace in the type, the heuristics only look at the path
+function getBlockPath(type: string) {
+	return type.slice(type.indexOf(":") + 1);
+}
+
+export function inferPaletteCategory(
+	block: any,
+	overrides: CategoryInferenceOverrides = {}
+): PaletteCategory {
+	const override =
+		typeof overrides === "function" ? overrides(block) : overrides[block.type];
+	if (override) {
+		return override;
+	}
+	const path = getBlockPath(block.type);
+	if (FLUID_BLOCKS.has(path)) {
+		return "fluid";
+	}
+	if (TRANSLUCENT_BLOCKS.has(path)) {
+		return "translucent";
+	}
+	if (CUTOUT_BLOCKS.has(path)) {
+		return "cutout";
+	}
+	for (const [suffix, category] of SUFFIX_RULES) {
+		if (path.endsWith(suffix)) {
+			return category;
+		}
+	}
+	const renderCategory = getRenderCategory(path);
+	if (renderCategory === "decoration") {
+		return "decoration";
+	}
+	if (renderCategory === "foliage" || TRANSPARENT_BLOCKS.has(path)) {
+		return "cutout";
+	}
+	return "solid";
+}
+
+// merged under options.categoryRenderSettings, so hosts only list what they change,
+// solid is left to the material since the identifier alone can miss transparency
+export const INFERRED_CATEGORY_SETTINGS: {
+	[category in PaletteCategory]: CategoryRenderSettings;
+} = {
+	solid: {},
+	cutout: { alphaMode: "mask" },
+	decoration: { alphaMode: "mask" },
+	translucent: { alphaMode: "blend", renderOrder: 1 },
+	fluid: { alphaMode: "blend", renderOrder: 2 },
+};
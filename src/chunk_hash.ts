@@ -0,0 +1,45 @@
+import { hashBlockForMap, INVISIBLE_BLOCKS } from "./utils";
+import { hashString } from "./mesh_snapshot";
+import { findChunkRegionKey } from "./chunk_sizing";
+
+// each block hashes on its own and the results are summed and xored, so the hash doesn't
+// depend on iteration order and air or missing cells hash the same
+export function hashSchematicChunks(
+	schematic: any,
+	chunkDimensions = { chunkWidth: 64, chunkHeight: 64, chunkLength: 64 },
+	chunkScales: Map<bigint, number> = new Map()
+) {
+	const { chunkWidth, chunkHeight, chunkLength } = chunkDimensions;
+	const sums = new Map<bigint, [number, number]>();
+	const paletteHashes = new Map<any, string>();
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+			continue;
+		}
+		let paletteKey = paletteHashes.get(block);
+		if (paletteKey === undefined) {
+			paletteKey = hashBlockForMap(block);
+			paletteHashes.set(block, paletteKey);
+		}
+		const key = findChunkRegionKey(
+			chunkScales,
+			Math.floor(pos.x / chunkWidth),
+			Math.floor(pos.y / chunkHeight),
+			Math.floor(pos.z / chunkLength)
+		);
+		const blockHash = hashString(`${pos.x},${pos.y},${pos.z}|${paletteKey}`);
+		const sum = sums.get(key) ?? [0, 0];
+		sum[0] = (sum[0] + blockHash) >>> 0;
+		sum[1] = (sum[1] ^ Math.imul(blockHash, 0x9e3779b1)) >>> 0;
+		sums.set(key, sum);
+	}
+	const hashes = new Map<bigint, string>();
+	for (const [key, [sum, xor]] of sums) {
+		hashes.set(
+			key,
+			sum.toString(16).padStart(8, "0") + xor.toString(16).padStart(8, "0")
+		);
+	}
+	return hashes;
+}
@@ -0,0 +1,182 @@
+import { getChunkKey, INVISIBLE_BLOCKS } from "./utils";
+import type { AutoChunkOptions, ChunkPartition, ChunkRegion } from "./types";
+
+const MAX_REGION_SCALE = 1 << 10;
+
+// octree regions sit on multiples of their own size in base chunks, so the one holding a
+// base chunk is found by rounding its coordinates down at every level
+export function findChunkRegionKey(
+	chunkScales: Map<bigint, number>,
+	x: number,
+	y: number,
+	z: number
+) {
+	if (chunkScales.size > 0) {
+		for (let scale = 2; scale <= MAX_REGION_SCALE; scale *= 2) {
+			const key = getChunkKey(
+				Math.floor(x / scale) * scale,
+				Math.floor(y / scale) * scale,
+				Math.floor(z / scale) * scale
+			);
+			if (chunkScales.get(key) === scale) {
+				return key;
+			}
+		}
+	}
+	return getChunkKey(x, y, z);
+}
+
+// an edit remeshes the whole region holding the block, so with n blocks per region the
+// expected remesh over a build of total blocks is sum(n²) / total. a region is split in
+// eight when the remeshing saved is worth more than the draw calls it adds
+function partitionByDensity(
+	baseCounts: Map<string, { cell: number[]; count: number }>,
+	base: number,
+	rootScale: number,
+	blocksPerDrawCall: number
+) {
+	let total = 0;
+	const roots = new Map<string, { cell: number[]; count: number }[]>();
+	for (const entry of baseCounts.values()) {
+		total += entry.count;
+		const key = entry.cell.map((c) => Math.floor(c / rootScale)).join(",");
+		let cells = roots.get(key);
+		if (!cells) {
+			cells = [];
+			roots.set(key, cells);
+		}
+		cells.push(entry);
+	}
+	const regions: ChunkRegion[] = [];
+	const visit = (
+		cells: { cell: number[]; count: number }[],
+		origin: number[],
+		scale: number
+	) => {
+		const count = cells.reduce((sum, entry) => sum + entry.count, 0);
+		if (scale > 1) {
+			const half = scale / 2;
+			const octants = new Map<string, { cell: number[]; count: number }[]>();
+			for (const entry of cells) {
+				const key = entry.cell
+					.map((c, i) => (c - origin[i] >= half ? 1 : 0))
+					.join(",");
+				let octant = octants.get(key);
+				if (!octant) {
+					octant = [];
+					octants.set(key, octant);
+				}
+				octant.push(entry);
+			}
+			let childSquares = 0;
+			for (const octant of octants.values()) {
+				const childCount = octant.reduce((sum, entry) => sum + entry.count, 0);
+				childSquares += childCount * childCount;
+			}
+			const saved = (count * count - childSquares) / total;
+			if (saved > (octants.size - 1) * blocksPerDrawCall) {
+				for (const [key, octant] of octants) {
+					const corner = key.split(",").map(Number);
+					visit(octant, origin.map((c, i) => c + corner[i] * half), half);
+				}
+				return;
+			}
+		}
+		regions.push({
+			key: getChunkKey(origin[0], origin[1], origin[2]),
+			min: origin.map((c) => c * base),
+			size: scale * base,
+			blockCount: count,
+		});
+	};
+	for (const cells of roots.values()) {
+		visit(
+			cells,
+			cells[0].cell.map((c) => Math.floor(c / rootScale) * rootScale),
+			rootScale
+		);
+	}
+	return regions;
+}
+
+// every candidate size must be a multiple of the smallest one, the blocks are counted
+// once on that grid and summed up for the larger sizes
+export function chooseChunkDimensions(
+	schematic: any,
+	options: AutoChunkOptions = {}
+): ChunkPartition {
+	const sizes = [...(options.sizes ?? [16, 32, 64, 128])].sort((a, b) => a - b);
+	const blocksPerDrawCall = options.blocksPerDrawCall ?? 4096;
+	const base = sizes[0];
+	const baseCounts = new Map<string, { cell: number[]; count: number }>();
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+			continue;
+		}
+		const cell = [
+			Math.floor(pos.x / base),
+			Math.floor(pos.y / base),
+			Math.floor(pos.z / base),
+		];
+		const key = cell.join(",");
+		const entry = baseCounts.get(key);
+		if (entry) {
+			entry.count++;
+		} else {
+			baseCounts.set(key, { cell, count: 1 });
+		}
+	}
+
+	// a draw call per occupied chunk against the blocks an edit has to remesh,
+	// dense builds end up on small chunks and sparse ones on large chunks
+	const candidates = sizes.map((size) => {
+		const scale = size / base;
+		const counts = new Map<string, number>();
+		for (const { cell, count } of baseCounts.values()) {
+			const key = cell.map((c) => Math.floor(c / scale)).join(",");
+			counts.set(key, (counts.get(key) ?? 0) + count);
+		}
+		let total = 0;
+		let maxBlocks = 0;
+		for (const count of counts.values()) {
+			total += count;
+			maxBlocks = Math.max(maxBlocks, count);
+		}
+		const meanBlocks = counts.size > 0 ? total / counts.size : 0;
+		return {
+			size,
+			chunkCount: counts.size,
+			meanBlocks,
+			maxBlocks,
+			cost: counts.size + meanBlocks / blocksPerDrawCall,
+		};
+	});
+	// the uniform candidates stay in the result for comparison, the build itself uses
+	// an octree over the largest size that only splits where the blocks are dense
+	const rootScale = 2 ** Math.floor(Math.log2(sizes[sizes.length - 1] / base));
+	const regions = partitionByDensity(
+		baseCounts,
+		base,
+		rootScale,
+		blocksPerDrawCall
+	);
+	let total = 0;
+	let maxBlocks = 0;
+	for (const region of regions) {
+		total += region.blockCount;
+		maxBlocks = Math.max(maxBlocks, region.blockCount);
+	}
+	return {
+		chunkDimensions: {
+			chunkWidth: base,
+			chunkHeight: base,
+			chunkLength: base,
+		},
+		chunkCount: regions.length,
+		meanBlocks: regions.length > 0 ? total / regions.length : 0,
+		maxBlocks,
+		candidates,
+		regions,
+	};
+}
@@ -0,0 +1,110 @@
+const EPSILON = 1e-5;
+
+// quad corners are emitted as (0,1,2),(2,1,3), so these pairs walk the outline
+const QUAD_EDGES = [
+	[0, 1],
+	[1, 3],
+	[3, 2],
+	[2, 0],
+];
+
+function getBorderNormal(
+	a: number[],
+	b: number[],
+	min: number[],
+	max: number[],
+	sides: number[][]
+) {
+	const onPlane = (axis: number, plane: number) =>
+		Math.abs(a[axis] - plane) < EPSILON && Math.abs(b[axis] - plane) < EPSILON;
+	for (const axis of [0, 2]) {
+		const normal = onPlane(axis, min[axis])
+			? -1
+			: onPlane(axis, max[axis])
+			? 1
+			: 0;
+		if (normal !== 0 && sides.some((side) => side[axis] === normal)) {
+			return axis === 0 ? [normal, 0, 0] : [0, 0, normal];
+		}
+	}
+	return undefined;
+}
+
+function buildSkirtComponent(
+	materialId: string,
+	a: number[],
+	b: number[],
+	normal: number[],
+	depth: number
+) {
+	// flip the edge if needed so the skirt faces out of the chunk
+	const edge = [b[0] - a[0], b[2] - a[2]];
+	const facing = edge[1] * normal[0] - edge[0] * normal[2];
+	const [start, end] = facing >= 0 ? [a, b] : [b, a];
+	return {
+		materialId,
+		face: "skirt",
+		positions: [
+			...start,
+			...end,
+			start[0],
+			start[1] - depth,
+			start[2],
+			end[0],
+			end[1] - depth,
+			end[2],
+		],
+		normals: [...normal, ...normal, ...normal, ...normal],
+		uvs: [0, 1, 1, 1, 0, 1 - depth, 1, 1 - depth],
+	};
+}
+
+// hangs a vertical strip below every top face edge that lies on one of the given sides of
+// the chunk box, so a neighbouring chunk meshed at a different detail level can't show a crack
+export function generateChunkSkirts(
+	components: { [materialId: string]: [any, number[]][] },
+	min: number[],
+	max: number[],
+	depth: number,
+	sides: number[][]
+) {
+	for (const materialId in components) {
+		const skirts: [any, number[]][] = [];
+		for (const [blockComponent, worldPos] of components[materialId]) {
+			if (blockComponent.face !== "up") {
+				continue;
+			}
+			const { positions } = blockComponent;
+			for (let quad = 0; quad < positions.length; quad += 12) {
+				const corners = [0, 1, 2, 3].map((i) => [
+					positions[quad + i * 3] + worldPos[0],
+					positions[quad + i * 3 + 1] + worldPos[1],
+					positions[quad + i * 3 + 2] + worldPos[2],
+				]);
+				for (const [i, j] of QUAD_EDGES) {
+					const normal = getBorderNormal(
+						corners[i],
+						corners[j],
+						min,
+						max,
+						sides
+					);
+					if (normal) {
+						skirts.push([
+							buildSkirtComponent(
+								materialId,
+								corners[i],
+								corners[j],
+								normal,
+								depth
+							),
+							[0, 0, 0],
+						]);
+					}
+				}
+			}
+		}
+		components[materialId].push(...skirts);
+	}
+	return components;
+}
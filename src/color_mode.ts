@@ -0,0 +1,88 @@
+import * as THREE from "three";
+import { hashBlockForMap } from "./utils";
+import { getAverageMaterialColor } from "./material_colors";
+
+export const VERTEX_COLOR_GROUP_PREFIX = "vertex-color:";
+export const VERTEX_COLOR_MATERIAL_ID = "vertex-color";
+export const VERTEX_COLOR_TRANSPARENT_MATERIAL_ID = "vertex-color-transparent";
+
+export function createVertexColorMaterials() {
+	return {
+		[VERTEX_COLOR_MATERIAL_ID]: new THREE.MeshStandardMaterial({
+			vertexColors: true,
+		}),
+		[VERTEX_COLOR_TRANSPARENT_MATERIAL_ID]: new THREE.MeshStandardMaterial({
+			vertexColors: true,
+			transparent: true,
+			depthWrite: false,
+		}),
+	};
+}
+
+// host colors win, keyed by palette key or block type, otherwise the face's texture is
+// averaged which needs the texture image to be loaded already
+export function getBlockFaceColor(
+	block: any,
+	material: THREE.Material | undefined,
+	blockColors: { [keyOrType: string]: number[] } = {}
+) {
+	const color = blockColors[hashBlockForMap(block)] ?? blockColors[block.type];
+	if (color) {
+		const [r, g, b, a = 1] = color;
+		return [r, g, b, a];
+	}
+	if (!material) {
+		return [1, 1, 1, 1];
+	}
+	const average = getAverageMaterialColor(material);
+	return [average.r, average.g, average.b, material.transparent ? 0.6 : 1];
+}
+
+export function getVertexColorGroupId(color: number[]) {
+	const channels = color.map((c) => c.toFixed(3)).join(",");
+	return `${VERTEX_COLOR_GROUP_PREFIX}${channels}`;
+}
+
+// groups are kept apart per color until after greedy meshing so differently colored faces
+// never merge, then they collapse into one opaque and one transparent group without uvs
+export function collapseVertexColorGroups(components: {
+	[materialId: string]: [any, number[]][];
+}) {
+	const collapsed: { [materialId: string]: [any, number[]][] } = {};
+	for (const groupId in components) {
+		if (!groupId.startsWith(VERTEX_COLOR_GROUP_PREFIX)) {
+			collapsed[groupId] = components[groupId];
+			continue;
+		}
+		const color = groupId
+			.slice(VERTEX_COLOR_GROUP_PREFIX.length)
+			.split(",")
+			.map(Number);
+		const materialId =
+			color[3] < 1
+				? VERTEX_COLOR_TRANSPARENT_MATERIAL_ID
+				: VERTEX_COLOR_MATERIAL_ID;
+		collapsed[materialId] ??= [];
+		for (const [blockComponent, worldPos] of components[groupId]) {
+			const vertexCount = blockComponent.positions.length / 3;
+			const colors = new Array(vertexCount * 4);
+			for (let i = 0; i < vertexCount; i++) {
+				colors[i * 4] = color[0];
+				colors[i * 4 + 1] = color[1];
+				colors[i * 4 + 2] = color[2];
+				colors[i * 4 + 3] = color[3];
+			}
+			collapsed[materialId].push([
+				{
+					materialId,
+					face: blockComponent.face,
+					positions: blockComponent.positions,
+					normals: blockComponent.normals,
+					colors,
+				},
+				worldPos,
+			]);
+		}
+	}
+	return collapsed;
+}
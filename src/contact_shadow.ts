@@ -0,0 +1,126 @@
+import * as THREE from "three";
+import { INVISIBLE_BLOCKS } from "./utils";
+import type { ContactShadowOptions } from "./types";
+
+// three box blur passes approximate a gaussian closely enough for a soft shadow
+function boxBlur(
+	values: Float32Array,
+	width: number,
+	height: number,
+	radius: number
+) {
+	const scratch = new Float32Array(values.length);
+	const size = radius * 2 + 1;
+	for (let pass = 0; pass < 3; pass++) {
+		for (let y = 0; y < height; y++) {
+			let sum = 0;
+			for (let x = -radius; x <= radius; x++) {
+				sum += values[y * width + Math.min(Math.max(x, 0), width - 1)];
+			}
+			for (let x = 0; x < width; x++) {
+				scratch[y * width + x] = sum / size;
+				const add = Math.min(x + radius + 1, width - 1);
+				const remove = Math.max(x - radius, 0);
+				sum += values[y * width + add] - values[y * width + remove];
+			}
+		}
+		for (let x = 0; x < width; x++) {
+			let sum = 0;
+			for (let y = -radius; y <= radius; y++) {
+				sum += scratch[Math.min(Math.max(y, 0), height - 1) * width + x];
+			}
+			for (let y = 0; y < height; y++) {
+				values[y * width + x] = sum / size;
+				const add = Math.min(y + radius + 1, height - 1);
+				const remove = Math.max(y - radius, 0);
+				sum += scratch[add * width + x] - scratch[remove * width + x];
+			}
+		}
+	}
+}
+
+// projects every column down onto the ground, columns whose lowest block sits higher up
+// cast a fainter shadow, then bakes a blurred copy into a small alpha texture
+export function buildContactShadow(
+	schematic: any,
+	offset: { x: number; y: number; z: number },
+	options: ContactShadowOptions = {}
+) {
+	const texelsPerBlock = options.texelsPerBlock ?? 4;
+	const padding = options.padding ?? 2;
+	const falloff = options.heightFalloff ?? 4;
+	const opacity = options.opacity ?? 0.6;
+	const radius = Math.max(
+		1,
+		Math.round((options.blurRadius ?? 0.75) * texelsPerBlock)
+	);
+
+	const columns = new Float32Array(schematic.width * schematic.length);
+	columns.fill(Infinity);
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+			continue;
+		}
+		const column = pos.x * schematic.length + pos.z;
+		columns[column] = Math.min(columns[column], pos.y);
+	}
+
+	const width = (schematic.width + padding * 2) * texelsPerBlock;
+	const height = (schematic.length + padding * 2) * texelsPerBlock;
+	const values = new Float32Array(width * height);
+	for (let x = 0; x < schematic.width; x++) {
+		for (let z = 0; z < schematic.length; z++) {
+			const lowest = columns[x * schematic.length + z];
+			if (lowest === Infinity) {
+				continue;
+			}
+			const strength = Math.exp(-lowest / falloff);
+			for (let tx = 0; tx < texelsPerBlock; tx++) {
+				for (let tz = 0; tz < texelsPerBlock; tz++) {
+					const u = (x + padding) * texelsPerBlock + tx;
+					const v = (z + padding) * texelsPerBlock + tz;
+					values[v * width + u] = strength;
+				}
+			}
+		}
+	}
+	boxBlur(values, width, height, radius);
+
+	const data = new Uint8Array(width * height * 4);
+	// the plane maps +z to the top of the texture once laid flat, so rows are flipped
+	for (let row = 0; row < height; row++) {
+		for (let u = 0; u < width; u++) {
+			const i = row * width + u;
+			const value = values[(height - 1 - row) * width + u];
+			data.fill(255, i * 4, i * 4 + 3);
+			data[i * 4 + 3] = Math.round(Math.min(value, 1) * 255);
+		}
+	}
+	const texture = new THREE.DataTexture(data, width, height);
+	texture.magFilter = THREE.LinearFilter;
+	texture.minFilter = THREE.LinearFilter;
+	texture.needsUpdate = true;
+
+	const geometry = new THREE.PlaneGeometry(
+		schematic.width + padding * 2,
+		schematic.length + padding * 2
+	);
+	geometry.rotateX(-Math.PI / 2);
+	const material = new THREE.MeshBasicMaterial({
+		color: options.color ?? 0x000000,
+		map: texture,
+		transparent: true,
+		opacity,
+		depthWrite: false,
+	});
+	const mesh = new THREE.Mesh(geometry, material);
+	mesh.name = "ContactShadow";
+	mesh.position.set(
+		offset.x + schematic.width / 2,
+		offset.y - (options.heightOffset ?? 0.01),
+		offset.z + schematic.length / 2
+	);
+	mesh.renderOrder = -1;
+	return mesh;
+}
@@ -0,0 +1,101 @@
+import * as THREE from "three";
+import { unpackChunkKey } from "./utils";
+import { findChunkRegionKey } from "./chunk_sizing";
+import type { CoordinateFrame } from "./types";
+
+// world space is (schematic + offset) * scale, the offset being the one the meshes were
+// built with and scale whatever the host applied to their parent
+export function worldToSchematic(
+	frame: CoordinateFrame,
+	world: THREE.Vector3,
+	normal?: THREE.Vector3
+) {
+	const scale = frame.scale ?? 1;
+	const local = world.clone().divideScalar(scale);
+	// a hit point sits exactly on the face between two blocks, stepping back against
+	// the face normal picks the block that was hit rather than the air in front of it
+	if (normal) {
+		local.addScaledVector(normal, -1e-3);
+	}
+	return new THREE.Vector3(
+		Math.floor(local.x - frame.offset.x),
+		Math.floor(local.y - frame.offset.y),
+		Math.floor(local.z - frame.offset.z)
+	);
+}
+
+// the block's minimum corner, or its center with `center`
+export function schematicToWorld(
+	frame: CoordinateFrame,
+	cell: { x: number; y: number; z: number },
+	center = false
+) {
+	const half = center ? 0.5 : 0;
+	return new THREE.Vector3(
+		cell.x + frame.offset.x + half,
+		cell.y + frame.offset.y + half,
+		cell.z + frame.offset.z + half
+	).multiplyScalar(frame.scale ?? 1);
+}
+
+export function schematicToChunkKey(
+	frame: CoordinateFrame,
+	cell: { x: number; y: number; z: number }
+) {
+	const { chunkWidth, chunkHeight, chunkLength } = frame.chunkDimensions;
+	return findChunkRegionKey(
+		frame.chunkScales ?? new Map(),
+		Math.floor(cell.x / chunkWidth),
+		Math.floor(cell.y / chunkHeight),
+		Math.floor(cell.z / chunkLength)
+	);
+}
+
+// max is exclusive, cells past the schematic edge are not clipped
+export function chunkKeyToSchematicBox(frame: CoordinateFrame, key: bigint) {
+	const { chunkWidth, chunkHeight, chunkLength } = frame.chunkDimensions;
+	const chunk = unpackChunkKey(key);
+	const min = new THREE.Vector3(
+		chunk.x * chunkWidth,
+		chunk.y * chunkHeight,
+		chunk.z * chunkLength
+	);
+	const scale = frame.chunkScales?.get(key) ?? 1;
+	const size = new THREE.Vector3(
+		chunkWidth * scale,
+		chunkHeight * scale,
+		chunkLength * scale
+	);
+	return { min, max: min.clone().add(size) };
+}
+
+// recentered chunk meshes keep their vertices relative to mesh.position, `quantize`
+// snaps the result to that many steps per block, 16 matching model pixels
+export function worldToMeshLocal(
+	frame: CoordinateFrame,
+	mesh: THREE.Object3D,
+	world: THREE.Vector3,
+	quantize?: number
+) {
+	const local = world
+		.clone()
+		.divideScalar(frame.scale ?? 1)
+		.sub(mesh.position);
+	if (quantize) {
+		local.multiplyScalar(quantize).round().divideScalar(quantize);
+	}
+	return local;
+}
+
+export function meshLocalToSchematic(
+	frame: CoordinateFrame,
+	mesh: THREE.Object3D,
+	local: THREE.Vector3,
+	normal?: THREE.Vector3
+) {
+	const world = local
+		.clone()
+		.add(mesh.position)
+		.multiplyScalar(frame.scale ?? 1);
+	return worldToSchematic(frame, world, normal);
+}
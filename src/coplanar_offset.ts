@@ -0,0 +1,85 @@
+import { FACE_AXES } from "./greedy_mesher";
+
+const FACE_SIGNS: { [face: string]: number } = {
+	east: 1,
+	west: -1,
+	up: 1,
+	down: -1,
+	south: 1,
+	north: -1,
+};
+
+function getQuadPlane(positions: number[], quad: number, axis: number) {
+	const plane = positions[quad * 12 + axis];
+	for (let v = 1; v < 4; v++) {
+		if (positions[quad * 12 + v * 3 + axis] !== plane) {
+			return undefined;
+		}
+	}
+	return plane;
+}
+
+// overlays are drawn over the face they share a plane with, so they are layered on
+// top of it, otherwise the component declared later is
+function getLayerOrder(keys: string[], blockComponents: any) {
+	return [...keys].sort((a, b) => {
+		const overlayA = blockComponents[a].materialId.includes("overlay") ? 1 : 0;
+		const overlayB = blockComponents[b].materialId.includes("overlay") ? 1 : 0;
+		return overlayA - overlayB || keys.indexOf(a) - keys.indexOf(b);
+	});
+}
+
+// pushes quads that share a plane with another component of the same block out along
+// their normal, one step per layer, so the two don't z-fight
+export function offsetCoplanarComponents(
+	blockComponents: { [key: string]: any },
+	offset: number
+) {
+	const keys = Object.keys(blockComponents);
+	const planes = new Map<string, Set<string>>();
+	for (const key of keys) {
+		const { face, positions } = blockComponents[key];
+		const axes = FACE_AXES[face];
+		if (!axes) {
+			continue;
+		}
+		for (let quad = 0; quad < positions.length / 12; quad++) {
+			const plane = getQuadPlane(positions, quad, axes.normalAxis);
+			if (plane === undefined) {
+				continue;
+			}
+			const planeKey = `${face}|${plane}`;
+			if (!planes.has(planeKey)) {
+				planes.set(planeKey, new Set());
+			}
+			planes.get(planeKey)!.add(key);
+		}
+	}
+	const shifted: { [key: string]: any } = { ...blockComponents };
+	for (const [planeKey, planeComponents] of planes) {
+		if (planeComponents.size < 2) {
+			continue;
+		}
+		const face = planeKey.split("|")[0];
+		const axis = FACE_AXES[face].normalAxis;
+		const plane = Number(planeKey.split("|")[1]);
+		const layers = getLayerOrder([...planeComponents], blockComponents);
+		layers.forEach((key, layer) => {
+			if (layer === 0) {
+				return;
+			}
+			const positions = [...shifted[key].positions];
+			for (let quad = 0; quad < positions.length / 12; quad++) {
+				if (getQuadPlane(positions, quad, axis) !== plane) {
+					continue;
+				}
+				for (let v = 0; v < 4; v++) {
+					positions[quad * 12 + v * 3 + axis] +=
+						FACE_SIGNS[face] * offset * layer;
+				}
+			}
+			shifted[key] = { ...shifted[key], positions };
+		});
+	}
+	return shifted;
+}
@@ -0,0 +1,183 @@
+import * as THREE from "three";
+import { INVISIBLE_BLOCKS, isOpaqueBlock, POSSIBLE_FACES } from "./utils";
+import type { DebugMeshOptions } from "./types";
+
+const FACE_FRAMES: { [face: string]: number[][] } = {
+	south: [
+		[0, 0, 1],
+		[1, 0, 0],
+		[0, 1, 0],
+	],
+	north: [
+		[0, 0, -1],
+		[1, 0, 0],
+		[0, 1, 0],
+	],
+	east: [
+		[1, 0, 0],
+		[0, 0, 1],
+		[0, 1, 0],
+	],
+	west: [
+		[-1, 0, 0],
+		[0, 0, 1],
+		[0, 1, 0],
+	],
+	up: [
+		[0, 1, 0],
+		[1, 0, 0],
+		[0, 0, 1],
+	],
+	down: [
+		[0, -1, 0],
+		[1, 0, 0],
+		[0, 0, 1],
+	],
+};
+
+const CULLED_COLOR = new THREE.Color(1, 0.1, 0.1);
+const VISIBLE_COLOR = new THREE.Color(0.1, 1, 0.1);
+
+// one point per occupied cell, opaque cells white and the rest blue
+export function buildOccupancyPoints(
+	schematic: any,
+	offset: { x: number; y: number; z: number }
+) {
+	const positions: number[] = [];
+	const colors: number[] = [];
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+			continue;
+		}
+		positions.push(
+			pos.x + 0.5 + offset.x,
+			pos.y + 0.5 + offset.y,
+			pos.z + 0.5 + offset.z
+		);
+		if (isOpaqueBlock(block)) {
+			colors.push(1, 1, 1);
+		} else {
+			colors.push(0.2, 0.4, 1);
+		}
+	}
+	const geometry = new THREE.BufferGeometry();
+	geometry.setAttribute(
+		"position",
+		new THREE.BufferAttribute(new Float32Array(positions), 3)
+	);
+	geometry.setAttribute(
+		"color",
+		new THREE.BufferAttribute(new Float32Array(colors), 3)
+	);
+	const points = new THREE.Points(
+		geometry,
+		new THREE.PointsMaterial({ size: 0.15, vertexColors: true })
+	);
+	points.name = "DebugOccupancy";
+	return points;
+}
+
+// a small quad in front of every block face, red when the mesher culls it and green
+// when it is emitted
+export function buildOcclusionOverlay(
+	schematic: any,
+	offset: { x: number; y: number; z: number },
+	getOccludedFaces: (block: any, pos: THREE.Vector3) => number
+) {
+	const positions: number[] = [];
+	const colors: number[] = [];
+	const indices: number[] = [];
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+			continue;
+		}
+		const occludedFaces = getOccludedFaces(block, pos);
+		POSSIBLE_FACES.forEach((face, i) => {
+			const culled = (occludedFaces >> (POSSIBLE_FACES.length - 1 - i)) & 1;
+			const color = culled ? CULLED_COLOR : VISIBLE_COLOR;
+			const [normal, u, v] = FACE_FRAMES[face];
+			const vertexOffset = positions.length / 3;
+			for (const [cu, cv] of [
+				[-0.4, -0.4],
+				[0.4, -0.4],
+				[-0.4, 0.4],
+				[0.4, 0.4],
+			]) {
+				positions.push(
+					...[0, 1, 2].map(
+						(axis) =>
+							[pos.x, pos.y, pos.z][axis] +
+							[offset.x, offset.y, offset.z][axis] +
+							0.5 +
+							normal[axis] * 0.51 +
+							u[axis] * cu +
+							v[axis] * cv
+					)
+				);
+				colors.push(color.r, color.g, color.b);
+			}
+			indices.push(
+				vertexOffset,
+				vertexOffset + 1,
+				vertexOffset + 2,
+				vertexOffset + 2,
+				vertexOffset + 1,
+				vertexOffset + 3
+			);
+		});
+	}
+	const geometry = new THREE.BufferGeometry();
+	geometry.setAttribute(
+		"position",
+		new THREE.BufferAttribute(new Float32Array(positions), 3)
+	);
+	geometry.setAttribute(
+		"color",
+		new THREE.BufferAttribute(new Float32Array(colors), 3)
+	);
+	geometry.setIndex(indices);
+	const mesh = new THREE.Mesh(
+		geometry,
+		new THREE.MeshBasicMaterial({
+			vertexColors: true,
+			transparent: true,
+			opacity: 0.6,
+			side: THREE.DoubleSide,
+			depthWrite: false,
+		})
+	);
+	mesh.name = "DebugOcclusion";
+	return mesh;
+}
+
+export function buildDirtyRegionBoxes(boxes: THREE.Box3[]) {
+	const group = new THREE.Group();
+	group.name = "DebugDirtyRegions";
+	for (const box of boxes) {
+		group.add(new THREE.Box3Helper(box, new THREE.Color(1, 0.6, 0)));
+	}
+	return group;
+}
+
+export function buildDebugMeshes(
+	schematic: any,
+	offset: { x: number; y: number; z: number },
+	getOccludedFaces: (block: any, pos: THREE.Vector3) => number,
+	dirtyBoxes: THREE.Box3[],
+	options: DebugMeshOptions = {}
+) {
+	const group = new THREE.Group();
+	group.name = "DebugMeshes";
+	if (options.occupancy ?? true) {
+		group.add(buildOccupancyPoints(schematic, offset));
+	}
+	if (options.occlusion ?? true) {
+		group.add(buildOcclusionOverlay(schematic, offset, getOccludedFaces));
+	}
+	if (options.dirtyRegions ?? true) {
+		group.add(buildDirtyRegionBoxes(dirtyBoxes));
+	}
+	return group;
+}
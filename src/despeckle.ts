@@ -0,0 +1,94 @@
+import * as THREE from "three";
+import { createVoxelMap } from "./measurement";
+import { hashBlockForMap } from "./utils";
+import type { DespeckleOptions } from "./types";
+
+function getKernelOffsets(kernel: DespeckleOptions["kernel"]) {
+	const offsets: number[][] = [];
+	for (let dx = -1; dx <= 1; dx++) {
+		for (let dy = -1; dy <= 1; dy++) {
+			for (let dz = -1; dz <= 1; dz++) {
+				const distance = Math.abs(dx) + Math.abs(dy) + Math.abs(dz);
+				if (distance === 0 || (kernel !== "full" && distance > 1)) {
+					continue;
+				}
+				offsets.push([dx, dy, dz]);
+			}
+		}
+	}
+	return offsets;
+}
+
+// wraps the schematic instead of copying it, only the changed cells are stored
+export function despeckleSchematic(
+	schematic: any,
+	options: DespeckleOptions = {}
+) {
+	const { voxels, index } = createVoxelMap(schematic);
+	const offsets = getKernelOffsets(options.kernel);
+	const minNeighbors = options.minNeighbors ?? 1;
+	const removeIsolated = options.removeIsolated ?? true;
+	const fillHoles = options.fillHoles ?? false;
+	const overrides = new Map<string, any>();
+	const air = { type: "air", properties: {} };
+
+	for (const pos of schematic) {
+		const filled = voxels[index(pos.x + 1, pos.y + 1, pos.z + 1)] === 1;
+		if (filled && !removeIsolated) {
+			continue;
+		}
+		if (!filled && !fillHoles) {
+			continue;
+		}
+		let neighbors = 0;
+		for (const [dx, dy, dz] of offsets) {
+			neighbors +=
+				voxels[index(pos.x + 1 + dx, pos.y + 1 + dy, pos.z + 1 + dz)];
+		}
+		if (filled && neighbors < minNeighbors) {
+			overrides.set(`${pos.x},${pos.y},${pos.z}`, air);
+		} else if (!filled && neighbors === offsets.length) {
+			// holes take the most common block around them
+			const counts = new Map<string, { block: any; count: number }>();
+			for (const [dx, dy, dz] of offsets) {
+				const block = schematic.getBlock(
+					new THREE.Vector3(pos.x + dx, pos.y + dy, pos.z + dz)
+				);
+				if (!block) {
+					continue;
+				}
+				const key = hashBlockForMap(block);
+				const entry = counts.get(key) ?? { block, count: 0 };
+				entry.count++;
+				counts.set(key, entry);
+			}
+			let best: { block: any; count: number } | undefined;
+			for (const entry of counts.values()) {
+				if (!best || entry.count > best.count) {
+					best = entry;
+				}
+			}
+			if (best) {
+				overrides.set(`${pos.x},${pos.y},${pos.z}`, best.block);
+			}
+		}
+	}
+
+	return {
+		width: schematic.width,
+		height: schematic.height,
+		length: schematic.length,
+		blockTypes: schematic.blockTypes,
+		metadata: schematic.metadata,
+		blockEntities: schematic.blockEntities,
+		despeckledCount: overrides.size,
+		getBlock(pos: THREE.Vector3) {
+			return (
+				overrides.get(`${pos.x},${pos.y},${pos.z}`) ?? schematic.getBlock(pos)
+			);
+		},
+		[Symbol.iterator]() {
+			return schematic[Symbol.iterator]();
+		},
+	};
+}
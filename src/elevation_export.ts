@@ -0,0 +1,126 @@
+import * as THREE from "three";
+import { readTargetRows } from "./turntable";
+import type { ElevationImage, ElevationOptions, ElevationView } from "./types";
+
+// direction the camera looks from, a north elevation is seen from the north side
+const VIEW_DIRECTIONS: { [view in ElevationView]: number[] } = {
+	north: [0, 0, -1],
+	south: [0, 0, 1],
+	east: [1, 0, 0],
+	west: [-1, 0, 0],
+	top: [0, 1, 0],
+};
+
+// world units per image axis, top views have north up
+function getViewAxes(view: ElevationView) {
+	if (view === "top") {
+		return { right: 0, up: 2, up3: [0, 0, -1] };
+	}
+	const right = view === "north" || view === "south" ? 0 : 2;
+	return { right, up: 1, up3: [0, 1, 0] };
+}
+
+// the frustum fits the box exactly, so one block always covers pixelsPerBlock pixels
+export function createElevationCamera(
+	box: THREE.Box3,
+	view: ElevationView,
+	margin: number
+) {
+	const size = box.getSize(new THREE.Vector3()).toArray();
+	const center = box.getCenter(new THREE.Vector3());
+	const { right, up, up3 } = getViewAxes(view);
+	const halfWidth = size[right] / 2 + margin;
+	const halfHeight = size[up] / 2 + margin;
+	const direction = new THREE.Vector3().fromArray(VIEW_DIRECTIONS[view]);
+	const depthAxis = VIEW_DIRECTIONS[view].findIndex((value) => value !== 0);
+	const distance = size[depthAxis] / 2 + 1;
+	const camera = new THREE.OrthographicCamera(
+		-halfWidth,
+		halfWidth,
+		halfHeight,
+		-halfHeight,
+		0,
+		size[depthAxis] + 2
+	);
+	camera.position.copy(center).addScaledVector(direction, distance);
+	camera.up.fromArray(up3);
+	camera.lookAt(center);
+	camera.updateProjectionMatrix();
+	return { camera, width: halfWidth * 2, height: halfHeight * 2 };
+}
+
+// three packs depth as base 256 digits, see unpackRGBAToDepth in packing.glsl
+function unpackDepth(pixels: Uint8ClampedArray, i: number) {
+	return (
+		(255 / 256) *
+		(pixels[i] / 255 / (256 * 256 * 256) +
+			pixels[i + 1] / 255 / (256 * 256) +
+			pixels[i + 2] / 255 / 256 +
+			pixels[i + 3] / 255)
+	);
+}
+
+// color rows run top to bottom, depth is the distance in blocks from the front of the
+// bounding box and Infinity where nothing was drawn
+export function renderElevations(
+	renderer: THREE.WebGLRenderer,
+	scene: THREE.Scene,
+	box: THREE.Box3,
+	options: ElevationOptions = {}
+): ElevationImage[] {
+	const views = options.views ?? ["north", "south", "east", "west", "top"];
+	const pixelsPerBlock = options.pixelsPerBlock ?? 16;
+	const margin = options.margin ?? 0;
+	const previousTarget = renderer.getRenderTarget();
+	const previousOverride = scene.overrideMaterial;
+	const previousBackground = scene.background;
+	const depthMaterial = new THREE.MeshDepthMaterial({
+		depthPacking: THREE.RGBADepthPacking,
+	});
+	const images = views.map((view) => {
+		const { camera, width, height } = createElevationCamera(box, view, margin);
+		const pixelWidth = Math.max(1, Math.round(width * pixelsPerBlock));
+		const pixelHeight = Math.max(1, Math.round(height * pixelsPerBlock));
+		// an srgb target gets the same tone mapping and encoding as the canvas, the
+		// packed depth has to stay linear
+		const colorTarget = new THREE.WebGLRenderTarget(pixelWidth, pixelHeight);
+		colorTarget.texture.colorSpace = THREE.SRGBColorSpace;
+		const depthTarget = new THREE.WebGLRenderTarget(pixelWidth, pixelHeight);
+
+		renderer.setRenderTarget(colorTarget);
+		renderer.render(scene, camera);
+		const color = readTargetRows(
+			renderer,
+			colorTarget,
+			pixelWidth,
+			pixelHeight
+		);
+
+		scene.overrideMaterial = depthMaterial;
+		scene.background = new THREE.Color(1, 1, 1);
+		renderer.setRenderTarget(depthTarget);
+		renderer.render(scene, camera);
+		const packed = readTargetRows(
+			renderer,
+			depthTarget,
+			pixelWidth,
+			pixelHeight
+		);
+		scene.overrideMaterial = previousOverride;
+		scene.background = previousBackground;
+		colorTarget.dispose();
+		depthTarget.dispose();
+
+		const depth = new Float32Array(pixelWidth * pixelHeight);
+		const range = camera.far - camera.near;
+		for (let i = 0; i < depth.length; i++) {
+			const value = unpackDepth(packed, i * 4);
+			// the cleared background reads as the far plane
+			depth[i] = value >= 0.999 ? Infinity : value * range - 1;
+		}
+		return { view, width: pixelWidth, height: pixelHeight, color, depth };
+	});
+	renderer.setRenderTarget(previousTarget);
+	depthMaterial.dispose();
+	return images;
+}
@@ -0,0 +1,142 @@
+import * as THREE from "three";
+import type { CutCapOptions, CutVolume, ExclusionBox } from "./types";
+import { isOpaqueBlock } from "./utils";
+
+const AIR = { type: "air", properties: {} };
+
+export function isExcluded(
+	boxes: ExclusionBox[],
+	x: number,
+	y: number,
+	z: number
+) {
+	return boxes.some(
+		({ min, max }) =>
+			x >= min[0] &&
+			y >= min[1] &&
+			z >= min[2] &&
+			x <= max[0] &&
+			y <= max[1] &&
+			z <= max[2]
+	);
+}
+
+export function createVolumeTest(volumes: CutVolume[]) {
+	const boxes = volumes.filter(
+		(volume): volume is ExclusionBox => "min" in volume
+	);
+	const cells = new Set<string>();
+	for (const volume of volumes) {
+		if ("cells" in volume) {
+			volume.cells.forEach(([x, y, z]) => cells.add(`${x},${y},${z}`));
+		}
+	}
+	return (x: number, y: number, z: number) =>
+		isExcluded(boxes, x, y, z) || cells.has(`${x},${y},${z}`);
+}
+
+// cells inside any box read as air, so they are neither meshed nor occlude their
+// neighbours, box corners are inclusive schematic coordinates
+export function excludeSchematicBoxes(schematic: any, volumes: CutVolume[]) {
+	const isCut = createVolumeTest(volumes);
+	return {
+		width: schematic.width,
+		height: schematic.height,
+		length: schematic.length,
+		blockTypes: schematic.blockTypes,
+		metadata: schematic.metadata,
+		source: schematic,
+		getBlock(pos: THREE.Vector3) {
+			return isCut(pos.x, pos.y, pos.z) ? AIR : schematic.getBlock(pos);
+		},
+		*[Symbol.iterator]() {
+			for (const pos of schematic) {
+				if (!isCut(pos.x, pos.y, pos.z)) {
+					yield pos;
+				}
+			}
+		},
+	};
+}
+
+const CAP_FACES = [
+	[1, 0, 0],
+	[-1, 0, 0],
+	[0, 1, 0],
+	[0, -1, 0],
+	[0, 0, 1],
+	[0, 0, -1],
+];
+
+// a flat colored quad on every face where an opaque block meets a cut volume, so the
+// section reads as solid instead of showing the textured sides of the blocks behind it
+export function buildCutCaps(
+	schematic: any,
+	volumes: CutVolume[],
+	offset: { x: number; y: number; z: number },
+	options: CutCapOptions = {}
+) {
+	const isCut = createVolumeTest(volumes);
+	const positions: number[] = [];
+	const indices: number[] = [];
+	for (const pos of schematic) {
+		if (isCut(pos.x, pos.y, pos.z) || !isOpaqueBlock(schematic.getBlock(pos))) {
+			continue;
+		}
+		for (const normal of CAP_FACES) {
+			const [nx, ny, nz] = normal;
+			if (!isCut(pos.x + nx, pos.y + ny, pos.z + nz)) {
+				continue;
+			}
+			const axis = normal.findIndex((value) => value !== 0);
+			const [u, v] = [0, 1, 2].filter((other) => other !== axis);
+			const vertexOffset = positions.length / 3;
+			for (const [cu, cv] of [
+				[0, 0],
+				[1, 0],
+				[0, 1],
+				[1, 1],
+			]) {
+				const corner = [pos.x, pos.y, pos.z];
+				corner[axis] += normal[axis] > 0 ? 1 : 0;
+				corner[u] += cu;
+				corner[v] += cv;
+				positions.push(
+					corner[0] + offset.x,
+					corner[1] + offset.y,
+					corner[2] + offset.z
+				);
+			}
+			indices.push(
+				vertexOffset,
+				vertexOffset + 1,
+				vertexOffset + 2,
+				vertexOffset + 2,
+				vertexOffset + 1,
+				vertexOffset + 3
+			);
+		}
+	}
+	const geometry = new THREE.BufferGeometry();
+	geometry.setAttribute(
+		"position",
+		new THREE.BufferAttribute(new Float32Array(positions), 3)
+	);
+	geometry.setIndex(indices);
+	const opacity = options.opacity ?? 1;
+	const mesh = new THREE.Mesh(
+		geometry,
+		new THREE.MeshBasicMaterial({
+			color: options.color ?? 0x404040,
+			transparent: opacity < 1,
+			opacity,
+			side: THREE.DoubleSide,
+			// drawn over the coplanar block faces it covers
+			polygonOffset: true,
+			polygonOffsetFactor: -1,
+			polygonOffsetUnits: -1,
+		})
+	);
+	mesh.name = "CutCaps";
+	return mesh;
+}
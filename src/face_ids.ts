@@ -0,0 +1,70 @@
+import * as THREE from "three";
+
+// same order as the greedy mesher's face table
+export const FACE_ID_NORMALS = [
+	[1, 0, 0],
+	[-1, 0, 0],
+	[0, 1, 0],
+	[0, -1, 0],
+	[0, 0, 1],
+	[0, 0, -1],
+];
+
+function getFaceId(x: number, y: number, z: number) {
+	return FACE_ID_NORMALS.findIndex(
+		([nx, ny, nz]) =>
+			Math.abs(x - nx) < 1e-4 &&
+			Math.abs(y - ny) < 1e-4 &&
+			Math.abs(z - nz) < 1e-4
+	);
+}
+
+// swaps the normal buffer for one byte per vertex, geometry with any slanted face
+// (cross plants, rotated models) is left untouched and false is returned
+export function packFaceIds(geometry: THREE.BufferGeometry) {
+	const normals = geometry.getAttribute("normal");
+	if (!normals) {
+		return false;
+	}
+	const faceIds = new Uint8Array(normals.count);
+	for (let i = 0; i < normals.count; i++) {
+		const faceId = getFaceId(normals.getX(i), normals.getY(i), normals.getZ(i));
+		if (faceId === -1) {
+			return false;
+		}
+		faceIds[i] = faceId;
+	}
+	geometry.deleteAttribute("normal");
+	geometry.setAttribute("faceId", new THREE.BufferAttribute(faceIds, 1));
+	return true;
+}
+
+const FACE_ID_VERTEX = `
+	vec3 objectNormal = vec3( 0.0, 0.0, -1.0 );
+	if ( faceId < 0.5 ) objectNormal = vec3( 1.0, 0.0, 0.0 );
+	else if ( faceId < 1.5 ) objectNormal = vec3( -1.0, 0.0, 0.0 );
+	else if ( faceId < 2.5 ) objectNormal = vec3( 0.0, 1.0, 0.0 );
+	else if ( faceId < 3.5 ) objectNormal = vec3( 0.0, -1.0, 0.0 );
+	else if ( faceId < 4.5 ) objectNormal = vec3( 0.0, 0.0, 1.0 );
+	#ifdef USE_TANGENT
+		vec3 objectTangent = vec3( tangent.xyz );
+	#endif
+`;
+
+// a copy of the material that rebuilds the normal from the faceId attribute, clone
+// doesn't carry shader hooks over so the base material's are chained by hand
+export function createFaceIdMaterial(material: THREE.Material) {
+	const faceIdMaterial = material.clone();
+	faceIdMaterial.onBeforeCompile = (shader, renderer) => {
+		material.onBeforeCompile(shader, renderer);
+		shader.vertexShader = shader.vertexShader
+			.replace(
+				"#include <common>",
+				"#include <common>\nattribute float faceId;"
+			)
+			.replace("#include <beginnormal_vertex>", FACE_ID_VERTEX);
+	};
+	faceIdMaterial.customProgramCacheKey = () =>
+		`${material.customProgramCacheKey()}|faceId`;
+	return faceIdMaterial;
+}
@@ -0,0 +1,73 @@
+import { hashString } from "./mesh_snapshot";
+
+function sameNumbers(a: ArrayLike<number>, b: ArrayLike<number>) {
+	if (a.length !== b.length) {
+		return false;
+	}
+	for (let i = 0; i < a.length; i++) {
+		if (a[i] !== b[i]) {
+			return false;
+		}
+	}
+	return true;
+}
+
+function sameComponent(a: any, b: any) {
+	return (
+		a.materialId === b.materialId &&
+		a.face === b.face &&
+		a.cullable === b.cullable &&
+		sameNumbers(a.positions, b.positions) &&
+		sameNumbers(a.normals, b.normals) &&
+		sameNumbers(a.uvs, b.uvs)
+	);
+}
+
+// many blockstates resolve to the same model, so identical components are stored once
+// and shared by reference, cached components are never mutated after this point
+export class GeometryPool {
+	buckets: Map<number, any[]> = new Map();
+	componentCount = 0;
+	uniqueCount = 0;
+	sharedFloats = 0;
+
+	public dedupe(blockComponents: { [key: string]: any }) {
+		const deduped: { [key: string]: any } = {};
+		for (const [key, component] of Object.entries(blockComponents)) {
+			deduped[key] = this.intern(component);
+		}
+		return deduped;
+	}
+
+	public intern(component: any) {
+		this.componentCount++;
+		const hash = hashString(
+			`${component.materialId}|${component.face}|${component.positions.join(
+				","
+			)}|${component.uvs.join(",")}`
+		);
+		const bucket = this.buckets.get(hash) ?? [];
+		const existing = bucket.find((candidate) =>
+			sameComponent(candidate, component)
+		);
+		if (existing) {
+			this.sharedFloats +=
+				component.positions.length +
+				component.normals.length +
+				component.uvs.length;
+			return existing;
+		}
+		bucket.push(component);
+		this.buckets.set(hash, bucket);
+		this.uniqueCount++;
+		return component;
+	}
+
+	public getStats() {
+		return {
+			componentCount: this.componentCount,
+			uniqueCount: this.uniqueCount,
+			savedBytes: this.sharedFloats * Float32Array.BYTES_PER_ELEMENT,
+		};
+	}
+}
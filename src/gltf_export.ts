@@ -0,0 +1,280 @@
+import * as THREE from "three";
+import { GLTFExporter } from "three/examples/jsm/exporters/GLTFExporter.js";
+
+const GLB_MAGIC = 0x46546c67;
+const CHUNK_JSON = 0x4e4f534a;
+const CHUNK_BIN = 0x004e4942;
+
+const DRACO_ATTRIBUTES: { [name: string]: string } = {
+	POSITION: "POSITION",
+	NORMAL: "NORMAL",
+	TEXCOORD_0: "TEX_COORD",
+	TEXCOORD_1: "TEX_COORD",
+	COLOR_0: "COLOR",
+};
+
+const COMPONENT_ARRAYS: { [componentType: number]: any } = {
+	5121: Uint8Array,
+	5123: Uint16Array,
+	5125: Uint32Array,
+	5126: Float32Array,
+};
+
+const TYPE_SIZES: { [type: string]: number } = {
+	SCALAR: 1,
+	VEC2: 2,
+	VEC3: 3,
+	VEC4: 4,
+};
+
+export interface GltfExportOptions {
+	draco?: boolean;
+	// an initialised draco3d encoder module, falls back to the DracoEncoderModule global
+	dracoEncoder?: any;
+	quantizationBits?: { [attribute: string]: number };
+}
+
+export async function exportGlb(object: THREE.Object3D | THREE.Object3D[]) {
+	const exporter = new GLTFExporter();
+	return (await exporter.parseAsync(object, { binary: true })) as ArrayBuffer;
+}
+
+function readGlb(glb: ArrayBuffer) {
+	const view = new DataView(glb);
+	if (view.getUint32(0, true) !== GLB_MAGIC) {
+		throw new Error("Not a binary glTF file");
+	}
+	let offset = 12;
+	let json: any;
+	let bin = new Uint8Array(0);
+	while (offset < glb.byteLength) {
+		const length = view.getUint32(offset, true);
+		const type = view.getUint32(offset + 4, true);
+		const data = new Uint8Array(glb, offset + 8, length);
+		if (type === CHUNK_JSON) {
+			json = JSON.parse(new TextDecoder().decode(data));
+		} else if (type === CHUNK_BIN) {
+			bin = data;
+		}
+		offset += 8 + length;
+	}
+	return { json, bin };
+}
+
+function writeGlb(json: any, bin: Uint8Array) {
+	const pad = (length: number) => Math.ceil(length / 4) * 4;
+	const jsonBytes = new TextEncoder().encode(JSON.stringify(json));
+	const jsonLength = pad(jsonBytes.length);
+	const binLength = pad(bin.length);
+	const total = 12 + 8 + jsonLength + (binLength > 0 ? 8 + binLength : 0);
+	const glb = new ArrayBuffer(total);
+	const view = new DataView(glb);
+	const bytes = new Uint8Array(glb);
+	view.setUint32(0, GLB_MAGIC, true);
+	view.setUint32(4, 2, true);
+	view.setUint32(8, total, true);
+	view.setUint32(12, jsonLength, true);
+	view.setUint32(16, CHUNK_JSON, true);
+	bytes.fill(0x20, 20, 20 + jsonLength);
+	bytes.set(jsonBytes, 20);
+	if (binLength > 0) {
+		const binOffset = 20 + jsonLength;
+		view.setUint32(binOffset, binLength, true);
+		view.setUint32(binOffset + 4, CHUNK_BIN, true);
+		bytes.set(bin, binOffset + 8);
+	}
+	return glb;
+}
+
+function readAccessor(json: any, bin: Uint8Array, accessorIndex: number) {
+	const accessor = json.accessors[accessorIndex];
+	const bufferView = json.bufferViews[accessor.bufferView];
+	const ArrayType = COMPONENT_ARRAYS[accessor.componentType];
+	const itemSize = TYPE_SIZES[accessor.type];
+	const byteOffset =
+		bin.byteOffset + (bufferView.byteOffset ?? 0) + (accessor.byteOffset ?? 0);
+	// copy so the typed array is aligned regardless of where the view starts
+	const bytes = bin.buffer.slice(
+		byteOffset,
+		byteOffset + accessor.count * itemSize * ArrayType.BYTES_PER_ELEMENT
+	);
+	return { array: new ArrayType(bytes), itemSize, count: accessor.count };
+}
+
+function encodePrimitive(
+	draco: any,
+	json: any,
+	bin: Uint8Array,
+	primitive: any,
+	options: GltfExportOptions
+) {
+	const encoder = new draco.Encoder();
+	const builder = new draco.MeshBuilder();
+	const mesh = new draco.Mesh();
+	const indices = readAccessor(json, bin, primitive.indices);
+	builder.AddFacesToMesh(
+		mesh,
+		indices.count / 3,
+		new Uint32Array(indices.array)
+	);
+	const attributeIds: { [name: string]: number } = {};
+	for (const [name, accessorIndex] of Object.entries(primitive.attributes)) {
+		const dracoName = DRACO_ATTRIBUTES[name];
+		if (!dracoName) {
+			continue;
+		}
+		const { array, itemSize, count } = readAccessor(
+			json,
+			bin,
+			accessorIndex as number
+		);
+		attributeIds[name] = builder.AddFloatAttributeToMesh(
+			mesh,
+			draco[dracoName],
+			count,
+			itemSize,
+			new Float32Array(array)
+		);
+	}
+	const quantization = {
+		POSITION: 14,
+		NORMAL: 10,
+		TEX_COORD: 12,
+		COLOR: 8,
+		...options.quantizationBits,
+	};
+	for (const [name, bits] of Object.entries(quantization)) {
+		encoder.SetAttributeQuantization(draco[name], bits);
+	}
+	encoder.SetEncodingMethod(draco.MESH_EDGEBREAKER_ENCODING);
+	const dracoBuffer = new draco.DracoInt8Array();
+	const length = encoder.EncodeMeshToDracoBuffer(mesh, dracoBuffer);
+	const encoded = new Uint8Array(length);
+	for (let i = 0; i < length; i++) {
+		encoded[i] = dracoBuffer.GetValue(i);
+	}
+	draco.destroy(dracoBuffer);
+	draco.destroy(mesh);
+	draco.destroy(builder);
+	draco.destroy(encoder);
+	return { encoded, attributeIds };
+}
+
+// rewrites every triangle primitive with KHR_draco_mesh_compression and repacks the binary
+// chunk without the raw vertex data it replaced
+export function compressGlbWithDraco(
+	glb: ArrayBuffer,
+	options: GltfExportOptions = {}
+) {
+	const draco = options.dracoEncoder ?? (globalThis as any).DracoEncoderModule;
+	if (!draco?.Encoder) {
+		throw new Error("Draco export needs an initialised draco3d encoder module");
+	}
+	const { json, bin } = readGlb(glb);
+	const keptViews = new Set<number>(
+		json.bufferViews.map((_: any, i: number) => i)
+	);
+	const encodedViews: Uint8Array[] = [];
+	for (const gltfMesh of json.meshes ?? []) {
+		for (const primitive of gltfMesh.primitives) {
+			if ((primitive.mode ?? 4) !== 4 || primitive.indices === undefined) {
+				continue;
+			}
+			const { encoded, attributeIds } = encodePrimitive(
+				draco,
+				json,
+				bin,
+				primitive,
+				options
+			);
+			// attributes draco didn't encode, like custom ones, keep their raw data
+			for (const accessorIndex of [
+				primitive.indices,
+				...Object.keys(attributeIds).map(
+					(semantic) => primitive.attributes[semantic]
+				),
+			] as number[]) {
+				const accessor = json.accessors[accessorIndex];
+				keptViews.delete(accessor.bufferView);
+				delete accessor.bufferView;
+				delete accessor.byteOffset;
+			}
+			primitive.extensions = {
+				...primitive.extensions,
+				KHR_draco_mesh_compression: {
+					bufferView: json.bufferViews.length + encodedViews.length,
+					attributes: attributeIds,
+				},
+			};
+			encodedViews.push(encoded);
+		}
+	}
+
+	// a view may be shared with an accessor that still reads from it
+	for (const accessor of json.accessors) {
+		if (accessor.bufferView !== undefined) {
+			keptViews.add(accessor.bufferView);
+		}
+	}
+
+	// repack the surviving views followed by the draco streams, each 4 byte aligned
+	const chunks: Uint8Array[] = [];
+	const bufferViews: any[] = [];
+	const viewRemap = new Map<number, number>();
+	let byteLength = 0;
+	const append = (data: Uint8Array, view: any) => {
+		bufferViews.push({ ...view, buffer: 0, byteOffset: byteLength });
+		chunks.push(data);
+		byteLength += Math.ceil(data.length / 4) * 4;
+	};
+	json.bufferViews.forEach((view: any, i: number) => {
+		if (!keptViews.has(i)) {
+			return;
+		}
+		viewRemap.set(i, bufferViews.length);
+		const start = view.byteOffset ?? 0;
+		append(bin.subarray(start, start + view.byteLength), view);
+	});
+	const firstDracoView = bufferViews.length;
+	for (const encoded of encodedViews) {
+		append(encoded, { byteLength: encoded.length });
+	}
+	for (const accessor of json.accessors) {
+		if (accessor.bufferView !== undefined) {
+			accessor.bufferView = viewRemap.get(accessor.bufferView);
+		}
+	}
+	for (const image of json.images ?? []) {
+		if (image.bufferView !== undefined) {
+			image.bufferView = viewRemap.get(image.bufferView);
+		}
+	}
+	for (const gltfMesh of json.meshes ?? []) {
+		for (const primitive of gltfMesh.primitives) {
+			const extension = primitive.extensions?.KHR_draco_mesh_compression;
+			if (extension) {
+				extension.bufferView =
+					firstDracoView + extension.bufferView - json.bufferViews.length;
+			}
+		}
+	}
+	json.bufferViews = bufferViews;
+	json.buffers = [{ byteLength }];
+	json.extensionsUsed = [
+		...new Set([...(json.extensionsUsed ?? []), "KHR_draco_mesh_compression"]),
+	];
+	json.extensionsRequired = [
+		...new Set([
+			...(json.extensionsRequired ?? []),
+			"KHR_draco_mesh_compression",
+		]),
+	];
+
+	const packed = new Uint8Array(byteLength);
+	let offset = 0;
+	for (const chunk of chunks) {
+		packed.set(chunk, offset);
+		offset += Math.ceil(chunk.length / 4) * 4;
+	}
+	return writeGlb(json, packed);
+}
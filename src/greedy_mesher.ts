@@ -0,0 +1,338 @@
+import type { GreedyMergeKey, MergeReport, MergeStats } from "./types";
+
+const EPSILON = 1e-5;
+
+export const FACE_AXES: {
+	[face: string]: {
+		normalAxis: number;
+		plane: number;
+		uAxis: number;
+		vAxis: number;
+	};
+} = {
+	east: { normalAxis: 0, plane: 1, uAxis: 2, vAxis: 1 },
+	west: { normalAxis: 0, plane: 0, uAxis: 2, vAxis: 1 },
+	up: { normalAxis: 1, plane: 1, uAxis: 0, vAxis: 2 },
+	down: { normalAxis: 1, plane: 0, uAxis: 0, vAxis: 2 },
+	south: { normalAxis: 2, plane: 1, uAxis: 0, vAxis: 1 },
+	north: { normalAxis: 2, plane: 0, uAxis: 0, vAxis: 1 },
+};
+
+interface MergeableFace {
+	depth: number;
+	uvOrigin: number[];
+	uStep: number[];
+	vStep: number[];
+}
+
+interface GreedyCell {
+	u: number;
+	v: number;
+	entry: [any, number[]];
+	mergeable: MergeableFace;
+}
+
+function isClose(a: number, b: number) {
+	return Math.abs(a - b) < EPSILON;
+}
+
+function isUnitCoordinate(value: number) {
+	return isClose(value, 0) || isClose(value, 1);
+}
+
+function isUnitAxisStep(step: number[]) {
+	return (
+		(isClose(Math.abs(step[0]), 1) && isClose(step[1], 0)) ||
+		(isClose(step[0], 0) && isClose(Math.abs(step[1]), 1))
+	);
+}
+
+// a face can only be merged if it spans its whole block cell across and maps the whole
+// texture, otherwise repeating the texture across the merged quad would not match the
+// unmerged result. it may sit at any depth inside the cell, so pushed out overlays like
+// grass block sides and inset faces like cactus sides merge with their own layer
+export function getMergeableFace(blockComponent: any): MergeableFace | null {
+	const { positions, uvs, face } = blockComponent;
+	const axes = FACE_AXES[face];
+	if (!axes || !uvs || positions.length !== 12) {
+		return null;
+	}
+	const depth = positions[axes.normalAxis];
+	const cornerUvs: number[][] = [];
+	for (let i = 0; i < 4; i++) {
+		const pos = positions.slice(i * 3, i * 3 + 3);
+		if (!isClose(pos[axes.normalAxis], depth)) {
+			return null;
+		}
+		const u = pos[axes.uAxis];
+		const v = pos[axes.vAxis];
+		if (!isUnitCoordinate(u) || !isUnitCoordinate(v)) {
+			return null;
+		}
+		cornerUvs[Math.round(u) + 2 * Math.round(v)] = [
+			uvs[i * 2],
+			uvs[i * 2 + 1],
+		];
+	}
+	for (let i = 0; i < 4; i++) {
+		if (!cornerUvs[i]) {
+			return null;
+		}
+	}
+	const [uv00, uv10, uv01, uv11] = cornerUvs;
+	const uStep = [uv10[0] - uv00[0], uv10[1] - uv00[1]];
+	const vStep = [uv01[0] - uv00[0], uv01[1] - uv00[1]];
+	if (
+		!isUnitAxisStep(uStep) ||
+		!isUnitAxisStep(vStep) ||
+		!isClose(uStep[0] * vStep[0] + uStep[1] * vStep[1], 0) ||
+		!isClose(uv11[0], uv00[0] + uStep[0] + vStep[0]) ||
+		!isClose(uv11[1], uv00[1] + uStep[1] + vStep[1])
+	) {
+		return null;
+	}
+	return { depth, uvOrigin: uv00, uStep, vStep };
+}
+
+export function buildMergedComponent(
+	template: any,
+	mergeable: MergeableFace,
+	width: number,
+	height: number,
+	carriedFields: string[] = []
+) {
+	const axes = FACE_AXES[template.face];
+	const { uvOrigin, uStep, vStep } = mergeable;
+	const positions: number[] = [];
+	const uvs: number[] = [];
+	for (let i = 0; i < 4; i++) {
+		const pos = template.positions.slice(i * 3, i * 3 + 3);
+		const u = Math.round(pos[axes.uAxis]);
+		const v = Math.round(pos[axes.vAxis]);
+		pos[axes.uAxis] = u * width;
+		pos[axes.vAxis] = v * height;
+		positions.push(...pos);
+		uvs.push(
+			uvOrigin[0] + u * width * uStep[0] + v * height * vStep[0],
+			uvOrigin[1] + u * width * uStep[1] + v * height * vStep[1]
+		);
+	}
+	const merged: any = {
+		materialId: template.materialId,
+		face: template.face,
+		positions,
+		normals: template.normals.slice(),
+		uvs,
+		merged: true,
+	};
+	// every cell of the quad agreed on these, so the template's copy holds for all of them
+	for (const field of carriedFields) {
+		if (template[field] !== undefined) {
+			merged[field] = Array.isArray(template[field])
+				? template[field].slice()
+				: template[field];
+		}
+	}
+	return merged;
+}
+
+export function getMergeKeyValue(
+	key: GreedyMergeKey,
+	blockComponent: any,
+	worldPos: number[]
+) {
+	const value =
+		typeof key === "function"
+			? key(blockComponent, worldPos)
+			: blockComponent[key];
+	return value === undefined ? "" : JSON.stringify(value);
+}
+
+// quads stop at multiples of maxQuadSize, so they never straddle a grid cell of that size
+function getGridLimit(start: number, maxQuadSize: number) {
+	return (Math.floor(start / maxQuadSize) + 1) * maxQuadSize - start;
+}
+
+// one bit per cell, rows along v and 32 cells of u per word, so expanding a quad
+// upwards tests a whole run of cells with a mask instead of looking each one up
+function mergePlane(
+	cells: GreedyCell[],
+	maxQuadSize = Infinity,
+	carriedFields: string[] = []
+) {
+	let minU = Infinity;
+	let minV = Infinity;
+	let maxU = -Infinity;
+	let maxV = -Infinity;
+	for (const cell of cells) {
+		minU = Math.min(minU, cell.u);
+		minV = Math.min(minV, cell.v);
+		maxU = Math.max(maxU, cell.u);
+		maxV = Math.max(maxV, cell.v);
+	}
+	const width = Math.round(maxU - minU) + 1;
+	const height = Math.round(maxV - minV) + 1;
+	const words = Math.ceil(width / 32);
+	const rows = new Uint32Array(words * height);
+	const grid: (GreedyCell | undefined)[] = new Array(width * height);
+	for (const cell of cells) {
+		const u = Math.round(cell.u - minU);
+		const v = Math.round(cell.v - minV);
+		rows[v * words + (u >>> 5)] |= 1 << (u & 31);
+		grid[v * width + u] = cell;
+	}
+	const isSet = (u: number, v: number) =>
+		(rows[v * words + (u >>> 5)] & (1 << (u & 31))) !== 0;
+
+	const merged: [any, number[]][] = [];
+	const run = new Uint32Array(words);
+	for (let v = 0; v < height; v++) {
+		for (let word = 0; word < words; word++) {
+			while (rows[v * words + word] !== 0) {
+				const bits = rows[v * words + word];
+				const u = word * 32 + (31 - Math.clz32(bits & -bits));
+				const cell = grid[v * width + u]!;
+				const maxWidth = Math.min(
+					getGridLimit(cell.u, maxQuadSize),
+					width - u
+				);
+				const maxHeight = Math.min(
+					getGridLimit(cell.v, maxQuadSize),
+					height - v
+				);
+				let quadWidth = 1;
+				while (quadWidth < maxWidth && isSet(u + quadWidth, v)) {
+					quadWidth++;
+				}
+				run.fill(0);
+				for (let du = 0; du < quadWidth; du++) {
+					run[(u + du) >>> 5] |= 1 << ((u + du) & 31);
+				}
+				let quadHeight = 1;
+				expand: while (quadHeight < maxHeight) {
+					const row = (v + quadHeight) * words;
+					for (let w = 0; w < words; w++) {
+						if ((rows[row + w] & run[w]) >>> 0 !== run[w]) {
+							break expand;
+						}
+					}
+					quadHeight++;
+				}
+				for (let dv = 0; dv < quadHeight; dv++) {
+					const row = (v + dv) * words;
+					for (let w = 0; w < words; w++) {
+						rows[row + w] &= ~run[w];
+					}
+				}
+				const component = buildMergedComponent(
+					cell.entry[0],
+					cell.mergeable,
+					quadWidth,
+					quadHeight,
+					carriedFields
+				);
+				// how many blocks the quad covers along each axis, for picking
+				const axes = FACE_AXES[component.face];
+				component.blockSpan = [1, 1, 1];
+				component.blockSpan[axes.uAxis] = quadWidth;
+				component.blockSpan[axes.vAxis] = quadHeight;
+				merged.push([component, cell.entry[1]]);
+			}
+		}
+	}
+	return merged;
+}
+
+// faces are only merged within a material, mergeKeys add component fields or callbacks
+// that must also match, so per block data like tint or light never spreads across a quad
+export function greedyMergeComponents(
+	components: {
+		[materialId: string]: [any, number[]][];
+	},
+	maxQuadSize?: number,
+	mergeKeys: GreedyMergeKey[] = []
+) {
+	const carriedFields = mergeKeys.filter(
+		(key): key is string => typeof key === "string"
+	);
+	const mergedComponents: { [materialId: string]: [any, number[]][] } = {};
+	for (const materialId in components) {
+		const kept: [any, number[]][] = [];
+		const planes = new Map<string, GreedyCell[]>();
+		for (const entry of components[materialId]) {
+			const [blockComponent, worldPos] = entry;
+			const mergeable = getMergeableFace(blockComponent);
+			if (!mergeable) {
+				kept.push(entry);
+				continue;
+			}
+			const axes = FACE_AXES[blockComponent.face];
+			const planeKey = [
+				blockComponent.face,
+				worldPos[axes.normalAxis],
+				Math.round(mergeable.depth / EPSILON),
+				...mergeable.uvOrigin,
+				...mergeable.uStep,
+				...mergeable.vStep,
+				...blockComponent.normals.slice(0, 3),
+				...mergeKeys.map((key) =>
+					getMergeKeyValue(key, blockComponent, worldPos)
+				),
+			].join(",");
+			if (!planes.has(planeKey)) {
+				planes.set(planeKey, []);
+			}
+			const u = worldPos[axes.uAxis];
+			const v = worldPos[axes.vAxis];
+			planes.get(planeKey)!.push({ u, v, entry, mergeable });
+		}
+		for (const cells of planes.values()) {
+			kept.push(...mergePlane(cells, maxQuadSize, carriedFields));
+		}
+		mergedComponents[materialId] = kept;
+	}
+	return mergedComponents;
+}
+
+export function createMergeStats(): MergeStats {
+	return {
+		facesConsidered: 0,
+		facesCulled: 0,
+		quadsEmitted: 0,
+		naiveVertexCount: 0,
+		vertexCount: 0,
+		vertexReduction: 0,
+	};
+}
+
+export function createMergeReport(): MergeReport {
+	return {
+		total: createMergeStats(),
+		byFace: {},
+		byMaterial: {},
+	};
+}
+
+export function recordMergeStats(
+	report: MergeReport,
+	face: string,
+	materialId: string,
+	field: "facesConsidered" | "facesCulled" | "quadsEmitted",
+	count: number
+) {
+	report.byFace[face] ??= createMergeStats();
+	report.byMaterial[materialId] ??= createMergeStats();
+	for (const stats of [
+		report.total,
+		report.byFace[face],
+		report.byMaterial[materialId],
+	]) {
+		stats[field] += count;
+		stats.naiveVertexCount = (stats.facesConsidered - stats.facesCulled) * 4;
+		stats.vertexCount = stats.quadsEmitted * 4;
+		stats.vertexReduction =
+			stats.naiveVertexCount > 0
+				? (1 - stats.vertexCount / stats.naiveVertexCount) * 100
+				: 0;
+	}
+}
@@ -0,0 +1,66 @@
+import * as THREE from "three";
+import { hashBlockForMap, INVISIBLE_BLOCKS } from "./utils";
+import type { HighlightPredicate } from "./types";
+
+// all set conditions of the predicate have to match, palette indices are resolved to
+// palette keys by the caller since the index order belongs to the block mesh cache
+export function matchesHighlightPredicate(
+	block: any,
+	predicate: HighlightPredicate,
+	paletteKeys?: Set<string>
+) {
+	if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+		return false;
+	}
+	if (paletteKeys && !paletteKeys.has(hashBlockForMap(block))) {
+		return false;
+	}
+	if (predicate.types && !predicate.types.includes(block.type)) {
+		return false;
+	}
+	for (const key in predicate.properties ?? {}) {
+		if (block.properties?.[key] !== predicate.properties![key]) {
+			return false;
+		}
+	}
+	return true;
+}
+
+export function buildHighlightMesh(
+	schematic: any,
+	predicate: HighlightPredicate,
+	offset: { x: number; y: number; z: number },
+	paletteKeys?: Set<string>
+) {
+	const positions: THREE.Vector3[] = [];
+	for (const pos of schematic) {
+		if (
+			matchesHighlightPredicate(schematic.getBlock(pos), predicate, paletteKeys)
+		) {
+			positions.push(pos);
+		}
+	}
+	// slightly larger than a block so the highlight doesn't z-fight with the faces
+	const geometry = new THREE.BoxGeometry(1.02, 1.02, 1.02);
+	geometry.translate(0.5, 0.5, 0.5);
+	const material = new THREE.MeshBasicMaterial({
+		color: predicate.color ?? 0xffff00,
+		transparent: true,
+		opacity: predicate.opacity ?? 0.4,
+		depthWrite: false,
+	});
+	const mesh = new THREE.InstancedMesh(geometry, material, positions.length);
+	const matrix = new THREE.Matrix4();
+	positions.forEach((pos, i) => {
+		matrix.makeTranslation(
+			pos.x + offset.x,
+			pos.y + offset.y,
+			pos.z + offset.z
+		);
+		mesh.setMatrixAt(i, matrix);
+	});
+	mesh.instanceMatrix.needsUpdate = true;
+	mesh.name = "Highlight";
+	mesh.userData.positions = positions;
+	return mesh;
+}
@@ -0,0 +1,121 @@
+import * as THREE from "three";
+
+export type IndexMode = "triangles" | "strip" | "quads" | "none";
+
+export interface IndexBufferOutput {
+	mode: IndexMode;
+	indices: Uint16Array | Uint32Array;
+	restartIndex?: number;
+	quadCount: number;
+	vertexCount: number;
+}
+
+// every quad emitted by the builder is 4 consecutive vertices drawn as (0, 1, 2) and (2, 1, 3),
+// which is exactly the winding of a 4 vertex triangle strip
+export function createIndexBuffer(
+	vertexCount: number,
+	mode: IndexMode
+): IndexBufferOutput {
+	const quadCount = Math.floor(vertexCount / 4);
+	const wide = vertexCount >= 0xffff;
+	const IndexArray = wide ? Uint32Array : Uint16Array;
+	switch (mode) {
+		case "strip": {
+			const restartIndex = wide ? 0xffffffff : 0xffff;
+			const indices = new IndexArray(quadCount * 5);
+			for (let quad = 0; quad < quadCount; quad++) {
+				const vertex = quad * 4;
+				indices.set(
+					[vertex, vertex + 1, vertex + 2, vertex + 3, restartIndex],
+					quad * 5
+				);
+			}
+			return { mode, indices, restartIndex, quadCount, vertexCount };
+		}
+		case "quads": {
+			const indices = new IndexArray(quadCount * 4);
+			for (let i = 0; i < quadCount * 4; i++) {
+				indices[i] = i;
+			}
+			return { mode, indices, quadCount, vertexCount };
+		}
+		// for vertex pulling shaders, quad = vertex_index / 4 and corner = vertex_index % 4
+		// already give the draw order so nothing needs to be uploaded besides the count
+		case "none":
+			return {
+				mode,
+				indices: new IndexArray(0),
+				quadCount,
+				vertexCount: quadCount * 4,
+			};
+		default: {
+			const indices = new IndexArray(quadCount * 6);
+			for (let quad = 0; quad < quadCount; quad++) {
+				const vertex = quad * 4;
+				indices.set(
+					[vertex, vertex + 1, vertex + 2, vertex + 2, vertex + 1, vertex + 3],
+					quad * 6
+				);
+			}
+			return { mode: "triangles", indices, quadCount, vertexCount };
+		}
+	}
+}
+
+export function getMeshIndexBuffer(mesh: THREE.Mesh, mode: IndexMode) {
+	const position = mesh.geometry.getAttribute("position");
+	return createIndexBuffer(position ? position.count : 0, mode);
+}
+
+export interface AxisIndexRange {
+	coordinate: number;
+	start: number;
+	count: number;
+}
+
+// quads are ordered by the block layer they sit in along the axis, so drawing the first
+// ranges up to some coordinate reveals the build layer by layer with setDrawRange. a
+// quad goes into the layer of its lowest corner, greedy meshed quads spanning several
+// layers are not split and appear whole with the first of them
+export function createAxisSortedIndices(
+	mesh: THREE.Mesh,
+	axis: "x" | "y" | "z"
+) {
+	const position = mesh.geometry.getAttribute("position");
+	const normal = mesh.geometry.getAttribute("normal");
+	const quadCount = position ? Math.floor(position.count / 4) : 0;
+	const axisIndex = ["x", "y", "z"].indexOf(axis);
+	const offset = mesh.position.getComponent(axisIndex);
+	const layers = new Int32Array(quadCount);
+	for (let quad = 0; quad < quadCount; quad++) {
+		let min = Infinity;
+		for (let vertex = quad * 4; vertex < quad * 4 + 4; vertex++) {
+			min = Math.min(min, position.getComponent(vertex, axisIndex));
+		}
+		// faces sit on the boundary of their block, nudging against the normal moves
+		// them back inside, e.g. top faces down and bottom faces up
+		const facing = normal ? normal.getComponent(quad * 4, axisIndex) : 1;
+		layers[quad] = Math.floor(min + offset - facing * 1e-4);
+	}
+	const order = Array.from({ length: quadCount }, (_, quad) => quad).sort(
+		(a, b) => layers[a] - layers[b] || a - b
+	);
+	const wide = position && position.count >= 0xffff;
+	const IndexArray = wide ? Uint32Array : Uint16Array;
+	const indices = new IndexArray(quadCount * 6);
+	const ranges: AxisIndexRange[] = [];
+	order.forEach((quad, i) => {
+		const vertex = quad * 4;
+		indices.set(
+			[vertex, vertex + 1, vertex + 2, vertex + 2, vertex + 1, vertex + 3],
+			i * 6
+		);
+		const last = ranges[ranges.length - 1];
+		if (last?.coordinate === layers[quad]) {
+			last.count += 6;
+		} else {
+			ranges.push({ coordinate: layers[quad], start: i * 6, count: 6 });
+		}
+	});
+	return { indices, ranges };
+}
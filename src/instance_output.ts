@@ -0,0 +1,66 @@
+import * as THREE from "three";
+import { hashBlockForMap, INVISIBLE_BLOCKS } from "./utils";
+import { quantizeRotation } from "./block_rotation";
+import type { InstanceBufferOptions, PaletteInstanceBuffer } from "./types";
+
+// one entry per palette key with every block instance and nothing merged or culled, the
+// geometry itself is the palette's cached block mesh
+export function createInstanceBuffers(
+	schematic: any,
+	offset: { x: number; y: number; z: number },
+	options: InstanceBufferOptions = {}
+) {
+	const format = options.format ?? "mat4";
+	const instances = new Map<string, { block: any; values: number[] }>();
+	const matrix = new THREE.Matrix4();
+	const rotationMatrix = new THREE.Matrix4();
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+			continue;
+		}
+		const key = hashBlockForMap(block);
+		let entry = instances.get(key);
+		if (!entry) {
+			entry = { block, values: [] };
+			instances.set(key, entry);
+		}
+		const rotation = quantizeRotation(
+			options.blockRotation?.(block, pos) ?? {}
+		);
+		const x = pos.x + offset.x;
+		const y = pos.y + offset.y;
+		const z = pos.z + offset.z;
+		if (format === "packed") {
+			// orientation index is x quarter turns * 4 + y quarter turns
+			entry.values.push(x, y, z, (rotation.x / 90) * 4 + rotation.y / 90);
+			continue;
+		}
+		// rotates about the block center like the merged builder does
+		rotationMatrix.makeRotationFromEuler(
+			new THREE.Euler(
+				THREE.MathUtils.degToRad(-rotation.x),
+				THREE.MathUtils.degToRad(-rotation.y),
+				0,
+				"YXZ"
+			)
+		);
+		matrix
+			.makeTranslation(x + 0.5, y + 0.5, z + 0.5)
+			.multiply(rotationMatrix)
+			.multiply(new THREE.Matrix4().makeTranslation(-0.5, -0.5, -0.5));
+		entry.values.push(...matrix.elements);
+	}
+	const buffers: PaletteInstanceBuffer[] = [];
+	for (const [paletteKey, { block, values }] of instances) {
+		buffers.push({
+			paletteKey,
+			block,
+			format,
+			stride: format === "packed" ? 4 : 16,
+			count: values.length / (format === "packed" ? 4 : 16),
+			data: new Float32Array(values),
+		});
+	}
+	return buffers;
+}
@@ -0,0 +1,171 @@
+import * as THREE from "three";
+import { TagMap } from "@enginehub/nbt-ts";
+import type {
+	BlockEntity,
+	InventoryItem,
+	InventoryPreviewOptions,
+} from "./types";
+
+const FACING_NORMALS: { [facing: string]: number[] } = {
+	north: [0, 0, -1],
+	south: [0, 0, 1],
+	west: [-1, 0, 0],
+	east: [1, 0, 0],
+	up: [0, 1, 0],
+	down: [0, -1, 0],
+};
+
+function toNumber(tag: any): number {
+	return typeof tag === "number" ? tag : Number(tag?.value ?? tag);
+}
+
+function toObject(tag: any): any {
+	if (tag instanceof Map) {
+		const result: any = {};
+		for (const [key, value] of tag) {
+			result[key] = toObject(value);
+		}
+		return result;
+	}
+	if (Array.isArray(tag)) {
+		return tag.map(toObject);
+	}
+	return tag;
+}
+
+// sponge v3 nests entities under Schematic.Blocks with the payload in Data,
+// v1/v2 and mcedit keep them at the root inline with the id and position
+export function readBlockEntities(nbt: TagMap): BlockEntity[] {
+	const root = (nbt.get("Schematic") as TagMap | undefined) ?? nbt;
+	const blocks = root.get("Blocks");
+	const entries =
+		(blocks instanceof Map ? blocks.get("BlockEntities") : undefined) ??
+		root.get("BlockEntities") ??
+		root.get("TileEntities") ??
+		[];
+	if (!Array.isArray(entries)) {
+		return [];
+	}
+	return entries.map((entry: any) => {
+		const fields = toObject(entry);
+		const position = fields.Pos
+			? Array.from(fields.Pos as ArrayLike<any>, toNumber)
+			: [fields.x, fields.y, fields.z].map(toNumber);
+		const id = String(fields.Id ?? fields.id ?? "");
+		return { id, position, data: fields.Data ?? fields };
+	});
+}
+
+// stacks of the same item are summed so the preview shows what a chest holds
+// rather than how it is laid out
+export function getContainerItems(entity: BlockEntity): InventoryItem[] {
+	const items = entity.data?.Items;
+	if (!Array.isArray(items)) {
+		return [];
+	}
+	const counts = new Map<string, number>();
+	for (const item of items) {
+		const id = item.id ?? item.Id;
+		if (typeof id !== "string") {
+			continue;
+		}
+		const count = toNumber(item.count ?? item.Count ?? 1);
+		counts.set(id, (counts.get(id) ?? 0) + count);
+	}
+	return [...counts.entries()]
+		.map(([id, count]) => ({ id, count }))
+		.sort((a, b) => b.count - a.count);
+}
+
+export async function buildInventoryPreview(
+	schematic: any,
+	offset: { x: number; y: number; z: number },
+	getItemTexture: (itemId: string) => Promise<THREE.Texture | undefined>,
+	options: InventoryPreviewOptions = {}
+) {
+	const placement = options.placement ?? "above";
+	const maxItems = options.maxItems ?? 4;
+	const itemSize = options.itemSize ?? 0.3;
+	const heightOffset = options.heightOffset ?? 0.25;
+	const group = new THREE.Group();
+	group.name = "InventoryPreview";
+
+	const materials = new Map<string, THREE.Material | undefined>();
+	const getMaterial = async (itemId: string) => {
+		if (!materials.has(itemId)) {
+			const texture = await getItemTexture(itemId);
+			materials.set(
+				itemId,
+				texture &&
+					(placement === "above"
+						? new THREE.SpriteMaterial({ map: texture, alphaTest: 0.1 })
+						: new THREE.MeshBasicMaterial({
+								map: texture,
+								alphaTest: 0.1,
+								transparent: true,
+						  }))
+			);
+		}
+		return materials.get(itemId);
+	};
+	const plane = new THREE.PlaneGeometry(itemSize, itemSize);
+
+	for (const entity of schematic.blockEntities ?? []) {
+		const items = getContainerItems(entity).slice(0, maxItems);
+		if (items.length === 0) {
+			continue;
+		}
+		const [x, y, z] = entity.position;
+		const center = new THREE.Vector3(
+			x + offset.x + 0.5,
+			y + offset.y + 0.5,
+			z + offset.z + 0.5
+		);
+		const block = schematic.getBlock(new THREE.Vector3(x, y, z));
+		const normal = FACING_NORMALS[block?.properties?.facing ?? "up"];
+		// the front face only makes sense for containers that have one
+		const onFace = placement === "front" && normal[1] === 0;
+		const columns = Math.ceil(Math.sqrt(items.length));
+		const rows = Math.ceil(items.length / columns);
+
+		for (let i = 0; i < items.length; i++) {
+			const material = await getMaterial(items[i].id);
+			if (!material) {
+				continue;
+			}
+			const column = (i % columns) - (columns - 1) / 2;
+			const row = (rows - 1) / 2 - Math.floor(i / columns);
+			let item: THREE.Object3D;
+			if (material instanceof THREE.SpriteMaterial) {
+				item = new THREE.Sprite(material);
+				item.scale.set(itemSize, itemSize, 1);
+			} else {
+				item = new THREE.Mesh(plane, material);
+			}
+			if (onFace) {
+				const facing = new THREE.Vector3(...normal);
+				const right = new THREE.Vector3(0, 1, 0).cross(facing);
+				item.position
+					.copy(center)
+					.addScaledVector(facing, 0.51)
+					.addScaledVector(right, column * itemSize)
+					.setY(center.y + row * itemSize);
+				item.lookAt(item.position.clone().add(facing));
+			} else {
+				item.position.set(
+					center.x + column * itemSize,
+					center.y + 0.5 + heightOffset + row * itemSize,
+					center.z
+				);
+				if (item instanceof THREE.Mesh) {
+					item.lookAt(item.position.clone().add(new THREE.Vector3(0, 0, 1)));
+				}
+			}
+			item.userData.itemId = items[i].id;
+			item.userData.count = items[i].count;
+			item.userData.blockEntity = entity;
+			group.add(item);
+		}
+	}
+	return group;
+}
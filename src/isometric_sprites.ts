@@ -0,0 +1,233 @@
+import * as THREE from "three";
+import type { IsometricSpriteOptions, SpriteFrame } from "./types";
+
+const COS_30 = Math.cos(Math.PI / 6);
+const SIN_30 = 0.5;
+
+// the view looks down from the +x +y +z corner, faces pointing away are never drawn
+const FACE_SHADES: { [face: string]: number } = {
+	up: 1,
+	south: 0.8,
+	east: 0.65,
+};
+
+type PlacedComponent = [any, number[]];
+
+function createCanvas(width: number, height: number) {
+	const canvas = document.createElement("canvas");
+	canvas.width = width;
+	canvas.height = height;
+	return canvas;
+}
+
+// bakes the material tint and face shade into a copy of the texture, keeping its alpha
+const shadedTextureCache = new WeakMap<THREE.Material, Map<number, any>>();
+function getShadedTexture(material: THREE.Material, shade: number) {
+	let shades = shadedTextureCache.get(material);
+	if (!shades) {
+		shades = new Map();
+		shadedTextureCache.set(material, shades);
+	}
+	if (shades.has(shade)) {
+		return shades.get(shade);
+	}
+	const standard = material as THREE.MeshStandardMaterial;
+	const image: any = standard.map?.image;
+	const color = (standard.color?.clone() ?? new THREE.Color(1, 1, 1))
+		.convertLinearToSRGB()
+		.multiplyScalar(shade);
+	const width = image?.width || 1;
+	const height = image?.height || 1;
+	const canvas = createCanvas(width, height);
+	const context = canvas.getContext("2d")!;
+	if (image?.width) {
+		context.drawImage(image, 0, 0);
+		context.globalCompositeOperation = "multiply";
+	}
+	context.fillStyle = `#${color.getHexString()}`;
+	context.fillRect(0, 0, width, height);
+	if (image?.width) {
+		context.globalCompositeOperation = "destination-in";
+		context.drawImage(image, 0, 0);
+	}
+	shades.set(shade, canvas);
+	return canvas;
+}
+
+function project(x: number, y: number, z: number, scale: number) {
+	return [(x - z) * COS_30 * scale, ((x + z) * SIN_30 - y) * scale];
+}
+
+// painter's algorithm over every visible quad, each one drawn as the texture under the
+// affine transform taking its first three uv corners onto their projected positions
+export function drawIsometricComponents(
+	context: CanvasRenderingContext2D,
+	components: PlacedComponent[],
+	materialMap: Map<string, THREE.Material>,
+	origin: [number, number],
+	scale: number
+) {
+	const quads: { depth: number; draw: () => void }[] = [];
+	for (const [component, [bx, by, bz]] of components) {
+		const shade = FACE_SHADES[component.face];
+		const material = materialMap.get(component.materialId);
+		if (shade === undefined || !material || !component.uvs) {
+			continue;
+		}
+		const { positions, uvs } = component;
+		for (let q = 0; q < positions.length / 12; q++) {
+			const points: number[][] = [];
+			const corners: number[][] = [];
+			let depth = 0;
+			for (let v = 0; v < 4; v++) {
+				const i = (q * 4 + v) * 3;
+				const x = positions[i] + bx;
+				const y = positions[i + 1] + by;
+				const z = positions[i + 2] + bz;
+				const [sx, sy] = project(x, y, z, scale);
+				points.push([sx + origin[0], sy + origin[1]]);
+				corners.push([uvs[(q * 4 + v) * 2], uvs[(q * 4 + v) * 2 + 1]]);
+				depth += x + y + z;
+			}
+			quads.push({
+				depth,
+				draw: () => {
+					const texture = getShadedTexture(material, shade);
+					drawTexturedQuad(context, texture, points, corners);
+				},
+			});
+		}
+	}
+	quads.sort((a, b) => a.depth - b.depth);
+	for (const quad of quads) {
+		quad.draw();
+	}
+}
+
+function drawTexturedQuad(
+	context: CanvasRenderingContext2D,
+	texture: any,
+	points: number[][],
+	corners: number[][]
+) {
+	const w = texture.width;
+	const h = texture.height;
+	// uv v runs bottom to top while canvas rows run top to bottom
+	const [u0, v0] = [corners[0][0] * w, (1 - corners[0][1]) * h];
+	const [u1, v1] = [corners[1][0] * w, (1 - corners[1][1]) * h];
+	const [u2, v2] = [corners[2][0] * w, (1 - corners[2][1]) * h];
+	const det = (u1 - u0) * (v2 - v0) - (u2 - u0) * (v1 - v0);
+	if (Math.abs(det) < 1e-9) {
+		return;
+	}
+	const [x0, y0] = points[0];
+	const [x1, y1] = points[1];
+	const [x2, y2] = points[2];
+	const a = ((x1 - x0) * (v2 - v0) - (x2 - x0) * (v1 - v0)) / det;
+	const b = ((y1 - y0) * (v2 - v0) - (y2 - y0) * (v1 - v0)) / det;
+	const c = ((x2 - x0) * (u1 - u0) - (x1 - x0) * (u2 - u0)) / det;
+	const d = ((y2 - y0) * (u1 - u0) - (y1 - y0) * (u2 - u0)) / det;
+	context.save();
+	context.beginPath();
+	// quad vertices are ordered 0 1 3 2 around the outline
+	context.moveTo(points[0][0], points[0][1]);
+	context.lineTo(points[1][0], points[1][1]);
+	context.lineTo(points[3][0], points[3][1]);
+	context.lineTo(points[2][0], points[2][1]);
+	context.closePath();
+	context.clip();
+	context.transform(a, b, c, d, x0 - a * u0 - c * v0, y0 - b * u0 - d * v0);
+	context.drawImage(texture, 0, 0);
+	context.restore();
+}
+
+function getIsometricBounds(size: { x: number; y: number; z: number }) {
+	return {
+		width: (size.x + size.z) * COS_30,
+		height: (size.x + size.z) * SIN_30 + size.y,
+		// screen position of the x = 0, y = 0, z = 0 corner relative to the top left
+		originX: size.z * COS_30,
+		originY: size.y,
+	};
+}
+
+function drawIntoTile(
+	context: CanvasRenderingContext2D,
+	components: PlacedComponent[],
+	size: { x: number; y: number; z: number },
+	materialMap: Map<string, THREE.Material>,
+	tile: SpriteFrame,
+	padding: number
+) {
+	const bounds = getIsometricBounds(size);
+	const scale = Math.min(
+		(tile.width - padding * 2) / bounds.width,
+		(tile.height - padding * 2) / bounds.height
+	);
+	const left = tile.x + (tile.width - bounds.width * scale) / 2;
+	const top = tile.y + (tile.height - bounds.height * scale) / 2;
+	drawIsometricComponents(
+		context,
+		components,
+		materialMap,
+		[left + bounds.originX * scale, top + bounds.originY * scale],
+		scale
+	);
+}
+
+// one tile per palette entry laid out row by row, frames are keyed by palette key
+export function renderPaletteSpriteSheet(
+	blockMeshCache: Map<string, any>,
+	materialMap: Map<string, THREE.Material>,
+	options: IsometricSpriteOptions = {}
+) {
+	const tileSize = options.tileSize ?? 64;
+	const padding = options.padding ?? 2;
+	const keys = [...blockMeshCache.keys()];
+	const columns =
+		options.columns ?? Math.max(1, Math.ceil(Math.sqrt(keys.length)));
+	const rows = Math.max(1, Math.ceil(keys.length / columns));
+	const canvas = createCanvas(columns * tileSize, rows * tileSize);
+	const context = canvas.getContext("2d")!;
+	const frames: { [key: string]: SpriteFrame } = {};
+	keys.forEach((key, i) => {
+		const frame = {
+			x: (i % columns) * tileSize,
+			y: Math.floor(i / columns) * tileSize,
+			width: tileSize,
+			height: tileSize,
+		};
+		const components: PlacedComponent[] = Object.values(
+			blockMeshCache.get(key)
+		).map((component) => [component, [0, 0, 0]]);
+		drawIntoTile(
+			context,
+			components,
+			{ x: 1, y: 1, z: 1 },
+			materialMap,
+			frame,
+			padding
+		);
+		frames[key] = frame;
+	});
+	return { canvas, frames };
+}
+
+export function renderIsometricSprite(
+	components: PlacedComponent[],
+	size: { x: number; y: number; z: number },
+	materialMap: Map<string, THREE.Material>,
+	options: IsometricSpriteOptions = {}
+) {
+	const tileSize = options.tileSize ?? 256;
+	const canvas = createCanvas(tileSize, tileSize);
+	drawIntoTile(
+		canvas.getContext("2d")!,
+		components,
+		size,
+		materialMap,
+		{ x: 0, y: 0, width: tileSize, height: tileSize },
+		options.padding ?? 2
+	);
+	return canvas;
+}
@@ -0,0 +1,400 @@
+import * as THREE from "three";
+import { TagMap } from "@enginehub/nbt-ts";
+
+interface LegacyBlock {
+	type: string;
+	properties: { [key: string]: string };
+}
+
+const COLORS = [
+	"white",
+	"orange",
+	"magenta",
+	"light_blue",
+	"yellow",
+	"lime",
+	"pink",
+	"gray",
+	"light_gray",
+	"cyan",
+	"purple",
+	"blue",
+	"brown",
+	"green",
+	"red",
+	"black",
+];
+const WOODS = ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+const STONES = [
+	"stone",
+	"granite",
+	"polished_granite",
+	"diorite",
+	"polished_diorite",
+	"andesite",
+	"polished_andesite",
+];
+
+// pre-flattening numeric ids, data values only matter for the variant tables below
+const LEGACY_BLOCK_IDS: { [id: number]: string } = {
+	0: "air",
+	1: "stone",
+	2: "grass_block",
+	3: "dirt",
+	4: "cobblestone",
+	5: "planks",
+	6: "sapling",
+	7: "bedrock",
+	8: "water",
+	9: "water",
+	10: "lava",
+	11: "lava",
+	12: "sand",
+	13: "gravel",
+	14: "gold_ore",
+	15: "iron_ore",
+	16: "coal_ore",
+	17: "log",
+	18: "leaves",
+	19: "sponge",
+	20: "glass",
+	21: "lapis_ore",
+	22: "lapis_block",
+	23: "dispenser",
+	24: "sandstone",
+	25: "note_block",
+	27: "powered_rail",
+	28: "detector_rail",
+	29: "sticky_piston",
+	30: "cobweb",
+	31: "grass",
+	32: "dead_bush",
+	33: "piston",
+	35: "wool",
+	37: "dandelion",
+	38: "poppy",
+	39: "brown_mushroom",
+	40: "red_mushroom",
+	41: "gold_block",
+	42: "iron_block",
+	44: "smooth_stone_slab",
+	45: "bricks",
+	46: "tnt",
+	47: "bookshelf",
+	48: "mossy_cobblestone",
+	49: "obsidian",
+	50: "torch",
+	52: "spawner",
+	53: "oak_stairs",
+	54: "chest",
+	55: "redstone_wire",
+	56: "diamond_ore",
+	57: "diamond_block",
+	58: "crafting_table",
+	59: "wheat",
+	60: "farmland",
+	61: "furnace",
+	62: "furnace",
+	64: "oak_door",
+	65: "ladder",
+	66: "rail",
+	67: "cobblestone_stairs",
+	69: "lever",
+	70: "stone_pressure_plate",
+	71: "iron_door",
+	72: "oak_pressure_plate",
+	73: "redstone_ore",
+	74: "redstone_ore",
+	75: "redstone_torch",
+	76: "redstone_torch",
+	77: "stone_button",
+	78: "snow",
+	79: "ice",
+	80: "snow_block",
+	81: "cactus",
+	82: "clay",
+	83: "sugar_cane",
+	84: "jukebox",
+	85: "oak_fence",
+	86: "carved_pumpkin",
+	87: "netherrack",
+	88: "soul_sand",
+	89: "glowstone",
+	91: "jack_o_lantern",
+	95: "stained_glass",
+	96: "oak_trapdoor",
+	98: "stone_bricks",
+	101: "iron_bars",
+	102: "glass_pane",
+	103: "melon",
+	106: "vine",
+	107: "oak_fence_gate",
+	108: "brick_stairs",
+	109: "stone_brick_stairs",
+	110: "mycelium",
+	111: "lily_pad",
+	112: "nether_bricks",
+	113: "nether_brick_fence",
+	114: "nether_brick_stairs",
+	116: "enchanting_table",
+	121: "end_stone",
+	123: "redstone_lamp",
+	124: "redstone_lamp",
+	128: "sandstone_stairs",
+	129: "emerald_ore",
+	133: "emerald_block",
+	138: "beacon",
+	139: "cobblestone_wall",
+	145: "anvil",
+	152: "redstone_block",
+	155: "quartz_block",
+	156: "quartz_stairs",
+	159: "terracotta",
+	160: "stained_glass_pane",
+	161: "leaves",
+	162: "log",
+	165: "slime_block",
+	169: "sea_lantern",
+	170: "hay_block",
+	171: "carpet",
+	172: "terracotta",
+	173: "coal_block",
+	174: "packed_ice",
+	179: "red_sandstone",
+	201: "purpur_block",
+	251: "concrete",
+	252: "concrete_powder",
+};
+
+const COLORED_BLOCK_IDS = new Set([35, 95, 159, 160, 171, 251, 252]);
+
+export function translateLegacyBlock(id: number, data: number = 0) {
+	let type = LEGACY_BLOCK_IDS[id] ?? "air";
+	const properties: { [key: string]: string } = {};
+	if (COLORED_BLOCK_IDS.has(id)) {
+		type = `${COLORS[data & 15]}_${type}`;
+	} else if (type === "stone") {
+		type = STONES[data] ?? "stone";
+	} else if (type === "planks" || type === "sapling") {
+		type = `${WOODS[data & 7] ?? "oak"}_${type}`;
+	} else if (type === "log" || type === "leaves") {
+		const wood =
+			id === 162 || id === 161 ? WOODS[4 + (data & 1)] : WOODS[data & 3];
+		type = `${wood}_${type}`;
+		if (type.endsWith("_log")) {
+			properties["axis"] = ["y", "x", "z", "y"][(data >> 2) & 3];
+		}
+	} else if (type === "snow") {
+		properties["layers"] = String((data & 7) + 1);
+	}
+	return { type, properties } as LegacyBlock;
+}
+
+const AIR: LegacyBlock = { type: "air", properties: {} };
+
+// without an explicit size the block set is normalised so its lowest corner sits at the origin
+export function createBlockSetSchematic(
+	blocks: Map<string, LegacyBlock>,
+	metadata: { [key: string]: any },
+	size?: number[]
+) {
+	const min = [Infinity, Infinity, Infinity];
+	const max = [-Infinity, -Infinity, -Infinity];
+	for (const key of blocks.keys()) {
+		const coords = key.split(",").map(Number);
+		for (let i = 0; i < 3; i++) {
+			min[i] = Math.min(min[i], coords[i]);
+			max[i] = Math.max(max[i], coords[i]);
+		}
+	}
+	if (size || blocks.size === 0) {
+		min.fill(0);
+		max.fill(-1);
+	}
+	const [width, height, length] = size ?? [
+		max[0] - min[0] + 1,
+		max[1] - min[1] + 1,
+		max[2] - min[2] + 1,
+	];
+	const blockTypes = [...new Set([...blocks.values()].map((b) => b.type))];
+
+	return {
+		width,
+		height,
+		length,
+		blockTypes,
+		metadata: { ...metadata, origin: min },
+		getBlock(pos: THREE.Vector3) {
+			if (
+				pos.x < 0 ||
+				pos.y < 0 ||
+				pos.z < 0 ||
+				pos.x >= width ||
+				pos.y >= height ||
+				pos.z >= length
+			) {
+				return undefined;
+			}
+			// cells the format left out are air, the mesher expects a block for every cell
+			return (
+				blocks.get(`${pos.x + min[0]},${pos.y + min[1]},${pos.z + min[2]}`) ??
+				AIR
+			);
+		},
+		*[Symbol.iterator]() {
+			for (let x = 0; x < width; x++) {
+				for (let y = 0; y < height; y++) {
+					for (let z = 0; z < length; z++) {
+						yield new THREE.Vector3(x, y, z);
+					}
+				}
+			}
+		},
+	};
+}
+
+export function parseBlockName(name: string) {
+	const match = name.trim().match(/^(?:minecraft:)?([a-z0-9_]+)(?:\[(.*)\])?/i);
+	if (!match) {
+		return undefined;
+	}
+	const properties: { [key: string]: string } = {};
+	for (const pair of match[2]?.split(",") ?? []) {
+		const [key, value] = pair.split("=");
+		if (key && value !== undefined) {
+			properties[key.trim()] = value.trim();
+		}
+	}
+	return { type: match[1].toLowerCase(), properties };
+}
+
+// bo3 references are either modern names or legacy "NAME:data" / "id:data" pairs
+function parseBlockReference(reference: string) {
+	const [name, data] = reference.trim().replace(/^minecraft:/i, "").split(":");
+	if (/^\d+$/.test(name)) {
+		return translateLegacyBlock(Number(name), Number(data ?? 0));
+	}
+	if (data !== undefined && /^\d+$/.test(data)) {
+		const legacyName = name.toLowerCase();
+		for (const id in LEGACY_BLOCK_IDS) {
+			if (LEGACY_BLOCK_IDS[id] === legacyName) {
+				return translateLegacyBlock(Number(id), Number(data));
+			}
+		}
+	}
+	return parseBlockName(name);
+}
+
+export function isBo2(text: string) {
+	return /^\s*\[(META|DATA)\]/im.test(text);
+}
+
+export function isBo3(text: string) {
+	return /^\s*Block\(/m.test(text);
+}
+
+// bo2 objects are ini-like, [META] holds settings and [DATA] lines read "x,z,y:id.data"
+// with the height last, as in the terrain generators that write them
+export function loadBo2(text: string) {
+	const blocks = new Map<string, LegacyBlock>();
+	const metadata: { [key: string]: any } = { format: "bo2" };
+	let section = "";
+	for (const rawLine of text.split(/\r?\n/)) {
+		const line = rawLine.trim();
+		if (!line || line.startsWith("#")) {
+			continue;
+		}
+		if (line.startsWith("[")) {
+			section = line.toUpperCase();
+			continue;
+		}
+		if (section === "[META]") {
+			const [key, ...value] = line.split("=");
+			metadata[key.trim()] = value.join("=").trim();
+		} else if (section === "[DATA]") {
+			const [coords, reference] = line.split(":");
+			if (!reference) {
+				continue;
+			}
+			const [x, z, y] = coords.split(",").map(Number);
+			const [id, data] = reference.split("#")[0].split(".").map(Number);
+			const block = translateLegacyBlock(id, data);
+			if (block.type !== "air") {
+				blocks.set(`${x},${y},${z}`, block);
+			}
+		}
+	}
+	return createBlockSetSchematic(blocks, metadata);
+}
+
+// bo3 objects are settings files where each block is a Block(x,y,z,name) call
+export function loadBo3(text: string) {
+	const blocks = new Map<string, LegacyBlock>();
+	const metadata: { [key: string]: any } = { format: "bo3" };
+	for (const rawLine of text.split(/\r?\n/)) {
+		const line = rawLine.trim();
+		if (!line || line.startsWith("#")) {
+			continue;
+		}
+		const call = line.match(/^(Block|RandomBlock)\((.*)\)$/);
+		if (call) {
+			// block states may contain commas, so only the coordinates are split off
+			const [x, y, z, ...rest] = call[2].split(",");
+			const block = parseBlockReference(rest.join(","));
+			if (block && block.type !== "air") {
+				blocks.set(`${Number(x)},${Number(y)},${Number(z)}`, block);
+			}
+			continue;
+		}
+		const setting = line.match(/^([A-Za-z]+)\s*:\s*(.*)$/);
+		if (setting) {
+			metadata[setting[1]] = setting[2];
+		}
+	}
+	return createBlockSetSchematic(blocks, metadata);
+}
+
+function toNumber(tag: any): number {
+	return typeof tag === "number" ? tag : Number(tag?.value ?? tag);
+}
+
+// old worldedit clipboards are mcedit schematics storing numeric ids in Blocks/AddBlocks
+export function isLegacyClipboard(nbt: TagMap) {
+	return nbt.has("Blocks") && nbt.has("Data") && !nbt.has("Palette");
+}
+
+export function loadLegacyClipboard(nbt: TagMap) {
+	const width = toNumber(nbt.get("Width"));
+	const height = toNumber(nbt.get("Height"));
+	const length = toNumber(nbt.get("Length"));
+	const ids = nbt.get("Blocks") as any;
+	const data = nbt.get("Data") as any;
+	const addBlocks = nbt.get("AddBlocks") as any;
+	const blocks = new Map<string, LegacyBlock>();
+	for (let index = 0; index < width * height * length; index++) {
+		let id = ids[index] & 0xff;
+		if (addBlocks) {
+			const add = addBlocks[index >> 1] & 0xff;
+			id |= (index & 1 ? add >> 4 : add & 0x0f) << 8;
+		}
+		const block = translateLegacyBlock(id, data[index] & 0x0f);
+		if (block.type === "air") {
+			continue;
+		}
+		const x = index % width;
+		const z = Math.floor(index / width) % length;
+		const y = Math.floor(index / (width * length));
+		blocks.set(`${x},${y},${z}`, block);
+	}
+	return createBlockSetSchematic(
+		blocks,
+		{
+			format: "legacy_clipboard",
+			materials: nbt.get("Materials"),
+			offset: ["WEOffsetX", "WEOffsetY", "WEOffsetZ"].map((key) =>
+				toNumber(nbt.get(key) ?? 0)
+			),
+		},
+		[width, height, length]
+	);
+}
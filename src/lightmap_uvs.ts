@@ -0,0 +1,66 @@
+import * as THREE from "three";
+import type { LightmapLayout } from "./types";
+
+export interface LightmapPacker {
+	cursorX: number;
+	cursorY: number;
+	shelfHeight: number;
+}
+
+export function createLightmapPacker(): LightmapPacker {
+	return { cursorX: 0, cursorY: 0, shelfHeight: 0 };
+}
+
+// packs every quad into its own lightmap region with simple shelf packing, regions are
+// proportional to the quad size so merged quads keep a uniform texel density, meshes
+// sharing one atlas must share the packer so their regions don't overlap
+export function generateLightmapUvs(
+	geometry: THREE.BufferGeometry,
+	layout: LightmapLayout,
+	packer: LightmapPacker = createLightmapPacker()
+) {
+	const { atlasWidth, atlasHeight, texelsPerBlock } = layout;
+	const padding = layout.padding ?? 1;
+	const positions = geometry.getAttribute("position");
+	const uv1 = new Float32Array(positions.count * 2);
+	const p0 = new THREE.Vector3();
+	const edgeS = new THREE.Vector3();
+	const edgeT = new THREE.Vector3();
+	const corner = new THREE.Vector3();
+	let { cursorX, cursorY, shelfHeight } = packer;
+	let overflow = false;
+
+	for (let vertex = 0; vertex + 3 < positions.count; vertex += 4) {
+		p0.fromBufferAttribute(positions, vertex);
+		edgeS.fromBufferAttribute(positions, vertex + 1).sub(p0);
+		edgeT.fromBufferAttribute(positions, vertex + 2).sub(p0);
+		const width = Math.max(1, Math.ceil(edgeS.length() * texelsPerBlock));
+		const height = Math.max(1, Math.ceil(edgeT.length() * texelsPerBlock));
+		const regionWidth = width + padding * 2;
+		const regionHeight = height + padding * 2;
+		if (cursorX + regionWidth > atlasWidth) {
+			cursorX = 0;
+			cursorY += shelfHeight;
+			shelfHeight = 0;
+		}
+		if (cursorY + regionHeight > atlasHeight) {
+			overflow = true;
+		}
+		for (let i = 0; i < 4; i++) {
+			corner.fromBufferAttribute(positions, vertex + i).sub(p0);
+			const s = corner.dot(edgeS) / Math.max(edgeS.lengthSq(), 1e-12);
+			const t = corner.dot(edgeT) / Math.max(edgeT.lengthSq(), 1e-12);
+			uv1[(vertex + i) * 2] = (cursorX + padding + s * width) / atlasWidth;
+			uv1[(vertex + i) * 2 + 1] =
+				(cursorY + padding + t * height) / atlasHeight;
+		}
+		cursorX += regionWidth;
+		shelfHeight = Math.max(shelfHeight, regionHeight);
+	}
+
+	packer.cursorX = cursorX;
+	packer.cursorY = cursorY;
+	packer.shelfHeight = shelfHeight;
+	geometry.setAttribute("uv1", new THREE.BufferAttribute(uv1, 2));
+	return { usedHeight: cursorY + shelfHeight, overflow };
+}
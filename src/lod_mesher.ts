@@ -0,0 +1,127 @@
+import * as THREE from "three";
+import { createCubeComponents, hashBlockForMap } from "./utils";
+
+const FACE_OFFSETS: { [face: string]: number[] } = {
+	east: [1, 0, 0],
+	west: [-1, 0, 0],
+	up: [0, 1, 0],
+	down: [0, -1, 0],
+	south: [0, 0, 1],
+	north: [0, 0, -1],
+};
+
+const UNIT_CUBE = createCubeComponents("lod");
+
+export interface LodCell {
+	cell: number[];
+	block: any;
+}
+
+// a coarse cell is filled once it holds factor² blocks, enough for a one block thick
+// wall to survive at every level, and takes the most common block inside it
+export function downsampleBlocks(
+	schematic: any,
+	positions: THREE.Vector3[],
+	factor: number,
+	minBlocks = factor * factor
+) {
+	const counts = new Map<
+		string,
+		{ cell: number[]; blocks: Map<string, any> }
+	>();
+	const blockCounts = new Map<string, number>();
+	for (const pos of positions) {
+		const block = schematic.getBlock(pos);
+		if (!block) {
+			continue;
+		}
+		const cell = [
+			Math.floor(pos.x / factor),
+			Math.floor(pos.y / factor),
+			Math.floor(pos.z / factor),
+		];
+		const cellKey = cell.join(",");
+		let entry = counts.get(cellKey);
+		if (!entry) {
+			entry = { cell, blocks: new Map() };
+			counts.set(cellKey, entry);
+		}
+		const blockKey = hashBlockForMap(block);
+		entry.blocks.set(blockKey, block);
+		const countKey = `${cellKey}|${blockKey}`;
+		blockCounts.set(countKey, (blockCounts.get(countKey) ?? 0) + 1);
+	}
+	const cells = new Map<string, LodCell>();
+	for (const [cellKey, { cell, blocks }] of counts) {
+		let total = 0;
+		let dominant: any;
+		let dominantCount = 0;
+		for (const [blockKey, block] of blocks) {
+			const count = blockCounts.get(`${cellKey}|${blockKey}`)!;
+			total += count;
+			if (count > dominantCount) {
+				dominant = block;
+				dominantCount = count;
+			}
+		}
+		if (total >= minBlocks) {
+			cells.set(cellKey, { cell, block: dominant });
+		}
+	}
+	return cells;
+}
+
+// each filled cell becomes a cube scaled by the factor with the dominant block's face
+// materials tiled across it, faces between two filled cells are dropped. getGroupId
+// picks the group a face goes into, e.g. a biome tinted copy of its material
+export async function buildLodComponents(
+	cells: Map<string, LodCell>,
+	factor: number,
+	offset: { x: number; y: number; z: number },
+	getBlockComponents: (block: any) => Promise<any>,
+	getGroupId: (materialId: string, block: any, pos: THREE.Vector3) => string
+) {
+	const components: { [materialId: string]: [any, number[]][] } = {};
+	for (const { cell, block } of cells.values()) {
+		const blockComponents = Object.values(
+			(await getBlockComponents(block)) ?? {}
+		) as any[];
+		for (const face in FACE_OFFSETS) {
+			const [dx, dy, dz] = FACE_OFFSETS[face];
+			if (cells.has(`${cell[0] + dx},${cell[1] + dy},${cell[2] + dz}`)) {
+				continue;
+			}
+			const materialId = blockComponents.find(
+				(component) => component.face === face
+			)?.materialId;
+			if (!materialId) {
+				continue;
+			}
+			const template = UNIT_CUBE[`lod-${face}`];
+			const groupId = getGroupId(
+				materialId,
+				block,
+				new THREE.Vector3(
+					cell[0] * factor,
+					cell[1] * factor,
+					cell[2] * factor
+				)
+			);
+			(components[groupId] ??= []).push([
+				{
+					materialId,
+					face,
+					positions: template.positions.map((p: number) => p * factor),
+					normals: template.normals.slice(),
+					uvs: template.uvs.map((uv: number) => uv * factor),
+				},
+				[
+					cell[0] * factor + offset.x,
+					cell[1] * factor + offset.y,
+					cell[2] * factor + offset.z,
+				],
+			]);
+		}
+	}
+	return components;
+}
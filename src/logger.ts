@@ -0,0 +1,76 @@
+export type LogLevel = "debug" | "info" | "warn" | "error" | "silent";
+
+export type LogSink = (level: LogLevel, ...args: any[]) => void;
+
+const LOG_LEVEL_PRIORITY: { [level in LogLevel]: number } = {
+	debug: 0,
+	info: 1,
+	warn: 2,
+	error: 3,
+	silent: 4,
+};
+
+const consoleSink: LogSink = (level, ...args) => {
+	switch (level) {
+		case "debug":
+			console.debug(...args);
+			break;
+		case "info":
+			console.info(...args);
+			break;
+		case "warn":
+			console.warn(...args);
+			break;
+		case "error":
+			console.error(...args);
+			break;
+	}
+};
+
+export class Logger {
+	level: LogLevel = "warn";
+	sink: LogSink = consoleSink;
+
+	public setLevel(level: LogLevel) {
+		if (!(level in LOG_LEVEL_PRIORITY)) {
+			throw new Error(`Unknown log level: ${level}`);
+		}
+		this.level = level;
+	}
+
+	// passing null restores the console sink
+	public setSink(sink: LogSink | null) {
+		this.sink = sink ?? consoleSink;
+	}
+
+	public isEnabled(level: LogLevel) {
+		return (
+			level !== "silent" &&
+			LOG_LEVEL_PRIORITY[level] >= LOG_LEVEL_PRIORITY[this.level]
+		);
+	}
+
+	public debug(...args: any[]) {
+		this.log("debug", ...args);
+	}
+
+	public info(...args: any[]) {
+		this.log("info", ...args);
+	}
+
+	public warn(...args: any[]) {
+		this.log("warn", ...args);
+	}
+
+	public error(...args: any[]) {
+		this.log("error", ...args);
+	}
+
+	private log(level: LogLevel, ...args: any[]) {
+		if (this.isEnabled(level)) {
+			this.sink(level, ...args);
+		}
+	}
+}
+
+export const logger = new Logger();
@@ -0,0 +1,39 @@
+import * as THREE from "three";
+import type { MaterialUsageReport } from "./types";
+
+export function auditMaterialUsage(
+	meshes: THREE.Mesh[],
+	materialMap: Map<string, THREE.Material>
+): MaterialUsageReport {
+	const materials: MaterialUsageReport["materials"] = {};
+	let drawCalls = 0;
+	let triangleCount = 0;
+	for (const mesh of meshes) {
+		const triangles = (mesh.geometry.getIndex()?.count ?? 0) / 3;
+		materials[mesh.name] ??= { drawCalls: 0, triangleCount: 0 };
+		materials[mesh.name].drawCalls++;
+		materials[mesh.name].triangleCount += triangles;
+		drawCalls++;
+		triangleCount += triangles;
+	}
+	const unusedMaterials = [...materialMap.keys()].filter(
+		(materialId) => !materials[materialId]
+	);
+	return { drawCalls, triangleCount, materials, unusedMaterials };
+}
+
+// frees materials no emitted group references, block meshes stay cached so this is meant
+// for a finished build: chunks rebuilt later won't recreate the pruned materials
+export function pruneUnusedMaterials(
+	meshes: THREE.Mesh[],
+	materialMap: Map<string, THREE.Material>
+) {
+	const { unusedMaterials } = auditMaterialUsage(meshes, materialMap);
+	for (const materialId of unusedMaterials) {
+		const material = materialMap.get(materialId) as THREE.MeshStandardMaterial;
+		material.map?.dispose();
+		material.dispose();
+		materialMap.delete(materialId);
+	}
+	return unusedMaterials;
+}
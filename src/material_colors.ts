@@ -0,0 +1,57 @@
+import * as THREE from "three";
+
+const averageColorCache = new WeakMap<THREE.Material, THREE.Color>();
+
+// averages the visible texels of the material's texture and tints them with its color,
+// falls back to the plain material color while the texture image isn't loaded yet
+export function getAverageMaterialColor(material: THREE.Material) {
+	const cached = averageColorCache.get(material);
+	if (cached) {
+		return cached;
+	}
+	const standard = material as THREE.MeshStandardMaterial;
+	const color = standard.color?.clone() ?? new THREE.Color(1, 1, 1);
+	const textureColors = material.userData.textureColors;
+	if (textureColors) {
+		const [r, g, b] = textureColors.average;
+		color.multiply(new THREE.Color().setRGB(r, g, b, THREE.SRGBColorSpace));
+		averageColorCache.set(material, color);
+		return color;
+	}
+	const image: any = standard.map?.image;
+	if (!image || !image.width || !image.height) {
+		return color;
+	}
+	const canvas = document.createElement("canvas");
+	canvas.width = image.width;
+	canvas.height = image.height;
+	const context = canvas.getContext("2d");
+	if (!context) {
+		return color;
+	}
+	context.drawImage(image, 0, 0);
+	const { data } = context.getImageData(0, 0, image.width, image.height);
+	let r = 0;
+	let g = 0;
+	let b = 0;
+	let weight = 0;
+	for (let i = 0; i < data.length; i += 4) {
+		const alpha = data[i + 3] / 255;
+		r += data[i] * alpha;
+		g += data[i + 1] * alpha;
+		b += data[i + 2] * alpha;
+		weight += alpha;
+	}
+	if (weight > 0) {
+		color.multiply(
+			new THREE.Color().setRGB(
+				r / weight / 255,
+				g / weight / 255,
+				b / weight / 255,
+				THREE.SRGBColorSpace
+			)
+		);
+	}
+	averageColorCache.set(material, color);
+	return color;
+}
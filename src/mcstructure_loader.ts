@@ -0,0 +1,362 @@
+import * as THREE from "three";
+
+// bedrock structures are little-endian NBT without compression, so they can't go
+// through parseNbt which expects the java big-endian layout
+class LittleEndianNbtReader {
+	private view: DataView;
+	private offset = 0;
+	private decoder = new TextDecoder();
+
+	constructor(private bytes: Uint8Array) {
+		this.view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+	}
+
+	readRoot() {
+		const type = this.view.getUint8(this.offset++);
+		if (type !== 10) {
+			throw new Error("Bedrock structure root tag must be a compound");
+		}
+		this.readString();
+		return this.readPayload(type);
+	}
+
+	private readString() {
+		const length = this.view.getUint16(this.offset, true);
+		this.offset += 2;
+		const value = this.decoder.decode(
+			this.bytes.subarray(this.offset, this.offset + length)
+		);
+		this.offset += length;
+		return value;
+	}
+
+	private readArray(size: number, read: (offset: number) => any) {
+		const length = this.view.getInt32(this.offset, true);
+		this.offset += 4;
+		const values = [];
+		for (let i = 0; i < length; i++) {
+			values.push(read(this.offset));
+			this.offset += size;
+		}
+		return values;
+	}
+
+	private readPayload(type: number): any {
+		const view = this.view;
+		let value: any;
+		switch (type) {
+			case 1:
+				return view.getInt8(this.offset++);
+			case 2:
+				value = view.getInt16(this.offset, true);
+				this.offset += 2;
+				return value;
+			case 3:
+				value = view.getInt32(this.offset, true);
+				this.offset += 4;
+				return value;
+			case 4:
+				value = view.getBigInt64(this.offset, true);
+				this.offset += 8;
+				return value;
+			case 5:
+				value = view.getFloat32(this.offset, true);
+				this.offset += 4;
+				return value;
+			case 6:
+				value = view.getFloat64(this.offset, true);
+				this.offset += 8;
+				return value;
+			case 7:
+				return this.readArray(1, (offset) => view.getInt8(offset));
+			case 8:
+				return this.readString();
+			case 9: {
+				const elementType = view.getUint8(this.offset++);
+				const length = view.getInt32(this.offset, true);
+				this.offset += 4;
+				const list = [];
+				for (let i = 0; i < length; i++) {
+					list.push(this.readPayload(elementType));
+				}
+				return list;
+			}
+			case 10: {
+				const compound: { [key: string]: any } = {};
+				while (true) {
+					const childType = view.getUint8(this.offset++);
+					if (childType === 0) {
+						return compound;
+					}
+					const name = this.readString();
+					compound[name] = this.readPayload(childType);
+				}
+			}
+			case 11:
+				return this.readArray(4, (offset) => view.getInt32(offset, true));
+			case 12:
+				return this.readArray(8, (offset) => view.getBigInt64(offset, true));
+			default:
+				throw new Error(`Unknown NBT tag type ${type}`);
+		}
+	}
+}
+
+const RENAMED_BLOCKS: { [bedrockName: string]: string } = {
+	grass: "grass_block",
+	tallgrass: "grass",
+	double_plant: "tall_grass",
+	snow: "snow_block",
+	snow_layer: "snow",
+	brick_block: "bricks",
+	hardened_clay: "terracotta",
+	stained_hardened_clay: "terracotta",
+	lit_pumpkin: "jack_o_lantern",
+	melon_block: "melon",
+	web: "cobweb",
+	waterlily: "lily_pad",
+	yellow_flower: "dandelion",
+	red_flower: "poppy",
+	reeds: "sugar_cane",
+	deadbush: "dead_bush",
+	nether_brick: "nether_bricks",
+	red_nether_brick: "red_nether_bricks",
+	end_bricks: "end_stone_bricks",
+	quartz_ore: "nether_quartz_ore",
+	magma: "magma_block",
+	slime: "slime_block",
+	noteblock: "note_block",
+	mob_spawner: "spawner",
+	invisible_bedrock: "barrier",
+	golden_rail: "powered_rail",
+	trapdoor: "oak_trapdoor",
+	wooden_door: "oak_door",
+	fence_gate: "oak_fence_gate",
+	wooden_button: "oak_button",
+	wooden_pressure_plate: "oak_pressure_plate",
+	standing_sign: "oak_sign",
+	wall_sign: "oak_wall_sign",
+	unpowered_repeater: "repeater",
+	powered_repeater: "repeater",
+	unpowered_comparator: "comparator",
+	powered_comparator: "comparator",
+	lit_redstone_lamp: "redstone_lamp",
+	lit_redstone_ore: "redstone_ore",
+	lit_furnace: "furnace",
+	unlit_redstone_torch: "redstone_torch",
+	stonebrick: "stone_bricks",
+	concretePowder: "concrete_powder",
+	frame: "item_frame",
+	seaLantern: "sea_lantern",
+	movingBlock: "moving_piston",
+	pistonArmCollision: "piston_head",
+	darkoak_standing_sign: "dark_oak_sign",
+	darkoak_wall_sign: "dark_oak_wall_sign",
+};
+
+// blocks that bedrock still keys by a color state instead of one id per color
+const COLORED_BLOCKS: { [bedrockName: string]: string } = {
+	wool: "wool",
+	carpet: "carpet",
+	concrete: "concrete",
+	concrete_powder: "concrete_powder",
+	stained_glass: "stained_glass",
+	stained_glass_pane: "stained_glass_pane",
+	terracotta: "terracotta",
+	shulker_box: "shulker_box",
+};
+
+const WOOD_BLOCKS: { [bedrockName: string]: string } = {
+	planks: "planks",
+	log: "log",
+	log2: "log",
+	leaves: "leaves",
+	leaves2: "leaves",
+	wood: "wood",
+	sapling: "sapling",
+	wooden_slab: "slab",
+	fence: "fence",
+};
+
+const FACING_DIRECTIONS = ["down", "up", "north", "south", "west", "east"];
+const CARDINAL_DIRECTIONS = ["south", "west", "north", "east"];
+const STAIR_DIRECTIONS = ["east", "west", "south", "north"];
+
+function translateStates(type: string, states: { [key: string]: any }) {
+	const properties: { [key: string]: string } = {};
+	for (const [key, value] of Object.entries(states)) {
+		switch (key) {
+			case "facing_direction":
+				properties["facing"] = FACING_DIRECTIONS[value] ?? "north";
+				break;
+			case "direction":
+				properties["facing"] = CARDINAL_DIRECTIONS[value & 3];
+				break;
+			case "weirdo_direction":
+				properties["facing"] = STAIR_DIRECTIONS[value & 3];
+				break;
+			case "minecraft:cardinal_direction":
+			case "minecraft:block_face":
+			case "minecraft:facing_direction":
+				properties["facing"] = String(value);
+				break;
+			case "upside_down_bit":
+				properties["half"] = value ? "top" : "bottom";
+				break;
+			case "top_slot_bit":
+				properties["type"] = value ? "top" : "bottom";
+				break;
+			case "minecraft:vertical_half":
+				properties[type.endsWith("slab") ? "type" : "half"] = String(value);
+				break;
+			case "upper_block_bit":
+				properties["half"] = value ? "upper" : "lower";
+				break;
+			case "pillar_axis":
+				properties["axis"] = String(value);
+				break;
+			case "open_bit":
+				properties["open"] = value ? "true" : "false";
+				break;
+			case "powered_bit":
+				properties["powered"] = value ? "true" : "false";
+				break;
+			case "head_piece_bit":
+				properties["part"] = value ? "head" : "foot";
+				break;
+			case "growth":
+				properties["age"] = String(value);
+				break;
+			case "height":
+				properties["layers"] = String(value + 1);
+				break;
+			default:
+				if (!key.includes(":") && !key.endsWith("_bit")) {
+					properties[key] = String(value);
+				}
+		}
+	}
+	return properties;
+}
+
+export function translateBedrockBlock(
+	name: string,
+	states: { [key: string]: any } = {}
+) {
+	let type = name.replace(/^minecraft:/, "");
+	type = RENAMED_BLOCKS[type] ?? type;
+	const color = states["color"];
+	if (COLORED_BLOCKS[type] && color) {
+		const javaColor = color === "silver" ? "light_gray" : color;
+		type = `${javaColor}_${COLORED_BLOCKS[type]}`;
+	}
+	const woodType =
+		states["wood_type"] ?? states["old_log_type"] ?? states["new_log_type"];
+	if (WOOD_BLOCKS[type] && woodType) {
+		type = `${woodType}_${WOOD_BLOCKS[type]}`;
+	}
+	const properties = translateStates(type, states);
+	for (const consumed of [
+		"color",
+		"wood_type",
+		"old_log_type",
+		"new_log_type",
+	]) {
+		delete properties[consumed];
+	}
+	return { type, properties };
+}
+
+const AIR = { type: "air", properties: {} };
+
+export function parseMcstructure(bytes: Uint8Array) {
+	return new LittleEndianNbtReader(bytes).readRoot();
+}
+
+// a bedrock structure is uncompressed little-endian NBT whose root holds size and structure
+const MCSTRUCTURE_KEYS = [
+	"format_version",
+	"size",
+	"structure",
+	"structure_world_origin",
+];
+
+// sniffs the little endian root header and first child tag name instead of
+// parsing the whole file, java nbt reads as an absurd length here
+export function isMcstructure(bytes: Uint8Array) {
+	if (bytes.length < 6 || bytes[0] !== 10) {
+		return false;
+	}
+	const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+	let offset = 3 + view.getUint16(1, true);
+	if (offset + 3 > bytes.length) {
+		return false;
+	}
+	const childType = bytes[offset];
+	const nameLength = view.getUint16(offset + 1, true);
+	offset += 3;
+	if (childType === 0 || offset + nameLength > bytes.length) {
+		return false;
+	}
+	const name = new TextDecoder().decode(
+		bytes.subarray(offset, offset + nameLength)
+	);
+	return MCSTRUCTURE_KEYS.includes(name);
+}
+
+export function loadMcstructure(bytes: Uint8Array) {
+	const root = parseMcstructure(bytes);
+	const [width, height, length] = root.size as number[];
+	const { block_indices, palette } = root.structure;
+	const blockPalette = palette?.default?.block_palette ?? [];
+	const blocks = blockPalette.map((entry: any) =>
+		translateBedrockBlock(entry.name, entry.states)
+	);
+	const primaryLayer: number[] = block_indices[0] ?? [];
+	const secondaryLayer: number[] = block_indices[1] ?? [];
+	const blockTypes = [...new Set(blocks.map((block: any) => block.type))];
+
+	// indices run z fastest, then y, then x; -1 marks a structure void
+	const getIndex = (x: number, y: number, z: number) =>
+		(x * height + y) * length + z;
+
+	return {
+		width,
+		height,
+		length,
+		blockTypes,
+		getBlock(pos: THREE.Vector3) {
+			if (
+				pos.x < 0 ||
+				pos.y < 0 ||
+				pos.z < 0 ||
+				pos.x >= width ||
+				pos.y >= height ||
+				pos.z >= length
+			) {
+				return undefined;
+			}
+			const index = getIndex(pos.x, pos.y, pos.z);
+			// structure voids read as air, the mesher expects a block for every cell
+			const block = blocks[primaryLayer[index]] ?? AIR;
+			// the second layer only holds liquids that waterlog the primary block
+			const liquid = blocks[secondaryLayer[index]];
+			if (liquid?.type === "water" && block.type !== "air") {
+				return {
+					type: block.type,
+					properties: { ...block.properties, waterlogged: "true" },
+				};
+			}
+			return block;
+		},
+		*[Symbol.iterator]() {
+			for (let x = 0; x < width; x++) {
+				for (let y = 0; y < height; y++) {
+					for (let z = 0; z < length; z++) {
+						yield new THREE.Vector3(x, y, z);
+					}
+				}
+			}
+		},
+	};
+}
@@ -0,0 +1,116 @@
+import { INVISIBLE_BLOCKS } from "./utils";
+import type { SchematicMeasurements } from "./types";
+
+const NEIGHBOR_OFFSETS = [
+	[1, 0, 0],
+	[-1, 0, 0],
+	[0, 1, 0],
+	[0, -1, 0],
+	[0, 0, 1],
+	[0, 0, -1],
+];
+
+// builds a 0/1 occupancy grid padded by one empty cell on every side
+export function createVoxelMap(schematic: any) {
+	const width = schematic.width + 2;
+	const height = schematic.height + 2;
+	const length = schematic.length + 2;
+	const voxels = new Uint8Array(width * height * length);
+	const index = (x: number, y: number, z: number) =>
+		x + z * width + y * width * length;
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (block && !INVISIBLE_BLOCKS.has(block.type)) {
+			voxels[index(pos.x + 1, pos.y + 1, pos.z + 1)] = 1;
+		}
+	}
+	return { voxels, width, height, length, index };
+}
+
+export function measureSchematic(
+	schematic: any,
+	blockSize: number = 1
+): SchematicMeasurements {
+	const { voxels, width, height, length, index } = createVoxelMap(schematic);
+	const min = [Infinity, Infinity, Infinity];
+	const max = [-Infinity, -Infinity, -Infinity];
+	let blockCount = 0;
+	let surfaceFaces = 0;
+	for (let y = 1; y < height - 1; y++) {
+		for (let z = 1; z < length - 1; z++) {
+			for (let x = 1; x < width - 1; x++) {
+				if (!voxels[index(x, y, z)]) {
+					continue;
+				}
+				blockCount++;
+				[x, y, z].forEach((value, axis) => {
+					min[axis] = Math.min(min[axis], value - 1);
+					max[axis] = Math.max(max[axis], value - 1);
+				});
+				for (const [dx, dy, dz] of NEIGHBOR_OFFSETS) {
+					if (!voxels[index(x + dx, y + dy, z + dz)]) {
+						surfaceFaces++;
+					}
+				}
+			}
+		}
+	}
+
+	// every empty cell the outside air can't reach through face-adjacent empty cells is enclosed
+	const outside = new Uint8Array(voxels.length);
+	const stack = [0];
+	outside[0] = 1;
+	while (stack.length > 0) {
+		const current = stack.pop()!;
+		const x = current % width;
+		const z = Math.floor(current / width) % length;
+		const y = Math.floor(current / (width * length));
+		for (const [dx, dy, dz] of NEIGHBOR_OFFSETS) {
+			const nx = x + dx;
+			const ny = y + dy;
+			const nz = z + dz;
+			if (
+				nx < 0 ||
+				ny < 0 ||
+				nz < 0 ||
+				nx >= width ||
+				ny >= height ||
+				nz >= length
+			) {
+				continue;
+			}
+			const neighbor = index(nx, ny, nz);
+			if (!voxels[neighbor] && !outside[neighbor]) {
+				outside[neighbor] = 1;
+				stack.push(neighbor);
+			}
+		}
+	}
+	let enclosedCells = 0;
+	for (let i = 0; i < voxels.length; i++) {
+		if (!voxels[i] && !outside[i]) {
+			enclosedCells++;
+		}
+	}
+
+	const dimensions =
+		blockCount > 0
+			? {
+					x: max[0] - min[0] + 1,
+					y: max[1] - min[1] + 1,
+					z: max[2] - min[2] + 1,
+			  }
+			: { x: 0, y: 0, z: 0 };
+	return {
+		blockCount,
+		dimensions,
+		dimensionsInMeters: {
+			x: dimensions.x * blockSize,
+			y: dimensions.y * blockSize,
+			z: dimensions.z * blockSize,
+		},
+		surfaceArea: surfaceFaces * blockSize * blockSize,
+		solidVolume: blockCount * blockSize ** 3,
+		enclosedVolume: enclosedCells * blockSize ** 3,
+	};
+}
@@ -0,0 +1,277 @@
+import { getMergeKeyValue } from "./greedy_mesher";
+import type { GreedyMergeKey, SimplifyOptions } from "./types";
+
+const PRECISION = 1e4;
+
+// the other two corners of a quad next to each one, 0 and 3 are the diagonal
+const ADJACENT = [
+	[1, 2],
+	[0, 3],
+	[0, 3],
+	[1, 2],
+];
+
+interface SimplifyQuad {
+	entry: [any, number[]];
+	corners: number[][];
+	alive: boolean;
+}
+
+function getCorners(blockComponent: any, worldPos: number[]) {
+	const corners: number[][] = [];
+	for (let i = 0; i < 4; i++) {
+		corners.push([
+			blockComponent.positions[i * 3] + worldPos[0],
+			blockComponent.positions[i * 3 + 1] + worldPos[1],
+			blockComponent.positions[i * 3 + 2] + worldPos[2],
+		]);
+	}
+	return corners;
+}
+
+function getPointKey(point: number[]) {
+	return point.map((value) => Math.round(value * PRECISION)).join(",");
+}
+
+function getEdgeKey(a: number[], b: number[]) {
+	return [getPointKey(a), getPointKey(b)].sort().join("|");
+}
+
+function getQuadArea(corners: number[][]) {
+	const u = corners[1].map((value, i) => value - corners[0][i]);
+	const v = corners[2].map((value, i) => value - corners[0][i]);
+	return Math.hypot(
+		u[1] * v[2] - u[2] * v[1],
+		u[2] * v[0] - u[0] * v[2],
+		u[0] * v[1] - u[1] * v[0]
+	);
+}
+
+function getLength(a: number[], b: number[]) {
+	return Math.hypot(a[0] - b[0], a[1] - b[1], a[2] - b[2]);
+}
+
+function isClose(a: number[], b: number[], tolerance: number) {
+	return a.every((value, i) => Math.abs(value - b[i]) <= tolerance);
+}
+
+function getUv(blockComponent: any, corner: number) {
+	return [blockComponent.uvs[corner * 2], blockComponent.uvs[corner * 2 + 1]];
+}
+
+// only plain textured quads, per vertex colors or ao would need interpolating
+function isSimplifiable(blockComponent: any) {
+	return (
+		blockComponent.positions.length === 12 &&
+		blockComponent.uvs &&
+		!blockComponent.colors &&
+		!blockComponent.ao
+	);
+}
+
+// like greedy meshing, a quad never straddles a grid cell of maxQuadSize
+function fitsQuadGrid(corners: number[][], maxQuadSize: number) {
+	if (!isFinite(maxQuadSize)) {
+		return true;
+	}
+	for (let axis = 0; axis < 3; axis++) {
+		const values = corners.map((corner) => corner[axis]);
+		const min = Math.floor((Math.min(...values) + 1e-4) / maxQuadSize);
+		const max = Math.floor((Math.max(...values) - 1e-4) / maxQuadSize);
+		if (max > min) {
+			return false;
+		}
+	}
+	return true;
+}
+
+// b extends a across their shared edge if both lie in the same plane, the union is
+// a rectangle and the texture continues linearly from one into the other
+function tryMerge(
+	a: SimplifyQuad,
+	b: SimplifyQuad,
+	tolerance: number,
+	maxQuadSize: number,
+	mergeKeys: GreedyMergeKey[]
+) {
+	const [componentA, worldPosA] = a.entry;
+	const [componentB, worldPosB] = b.entry;
+	const normalA = componentA.normals.slice(0, 3);
+	const normalB = componentB.normals.slice(0, 3);
+	if (
+		componentA.face !== componentB.face ||
+		componentA.cullable !== componentB.cullable ||
+		!isClose(normalA, normalB, 1e-4) ||
+		mergeKeys.some(
+			(key) =>
+				getMergeKeyValue(key, componentA, worldPosA) !==
+				getMergeKeyValue(key, componentB, worldPosB)
+		)
+	) {
+		return null;
+	}
+	const shared: [number, number][] = [];
+	for (let i = 0; i < 4; i++) {
+		for (let j = 0; j < 4; j++) {
+			if (getPointKey(a.corners[i]) === getPointKey(b.corners[j])) {
+				shared.push([i, j]);
+			}
+		}
+	}
+	if (shared.length !== 2) {
+		return null;
+	}
+	// the union starts at the lower of the two blocks so blockSpan stays positive
+	const worldPos = worldPosA.map((value, axis) =>
+		Math.min(value, worldPosB[axis])
+	);
+	const corners: number[][] = [];
+	const positions: number[] = [];
+	const uvs: number[] = [];
+	for (let i = 0; i < 4; i++) {
+		const match = shared.find(([sharedA]) => sharedA === i);
+		let corner = a.corners[i];
+		let uv = getUv(componentA, i);
+		if (match) {
+			const [, j] = match;
+			if (!isClose(uv, getUv(componentB, j), 1e-4)) {
+				return null;
+			}
+			// the corner of b across from the shared one takes its place
+			const far = ADJACENT[j].find(
+				(k) => !shared.some(([, sharedB]) => sharedB === k)
+			)!;
+			const farA = ADJACENT[i].find(
+				(k) => !shared.some(([sharedA]) => sharedA === k)
+			)!;
+			const lengthA = getLength(a.corners[farA], a.corners[i]);
+			const lengthB = getLength(b.corners[far], b.corners[j]);
+			const directionA = a.corners[i].map(
+				(value, axis) => (value - a.corners[farA][axis]) / lengthA
+			);
+			const directionB = b.corners[far].map(
+				(value, axis) => (value - b.corners[j][axis]) / lengthB
+			);
+			const uvStepA = uv.map(
+				(value, k) => (value - getUv(componentA, farA)[k]) / lengthA
+			);
+			const uvStepB = getUv(componentB, far).map(
+				(value, k) => (value - uv[k]) / lengthB
+			);
+			if (
+				!isClose(directionA, directionB, tolerance) ||
+				!isClose(uvStepA, uvStepB, tolerance)
+			) {
+				return null;
+			}
+			corner = b.corners[far];
+			uv = getUv(componentB, far);
+		}
+		corners.push(corner);
+		positions.push(
+			corner[0] - worldPos[0],
+			corner[1] - worldPos[1],
+			corner[2] - worldPos[2]
+		);
+		uvs.push(...uv);
+	}
+	if (!fitsQuadGrid(corners, maxQuadSize)) {
+		return null;
+	}
+	const spanA = componentA.blockSpan ?? [1, 1, 1];
+	const spanB = componentB.blockSpan ?? [1, 1, 1];
+	const blockSpan = worldPos.map((value, axis) => {
+		const end = Math.max(
+			worldPosA[axis] + spanA[axis],
+			worldPosB[axis] + spanB[axis]
+		);
+		return Math.round(end - value);
+	});
+	return {
+		component: { ...componentA, positions, uvs, merged: true, blockSpan },
+		worldPos,
+	};
+}
+
+// runs after greedy meshing on whatever it left behind, mostly custom model faces.
+// quads smaller than targetError² are dropped and neighbouring coplanar quads with
+// a continuous texture are joined until nothing changes, within the same limits and
+// merge keys greedy meshing was given
+export function simplifyComponents(
+	components: { [materialId: string]: [any, number[]][] },
+	options: SimplifyOptions = {},
+	maxQuadSize = Infinity,
+	mergeKeys: GreedyMergeKey[] = []
+) {
+	const targetError = options.targetError ?? 0.01;
+	const minArea = targetError * targetError;
+	const simplified: { [materialId: string]: [any, number[]][] } = {};
+	for (const materialId in components) {
+		const kept: [any, number[]][] = [];
+		const quads: SimplifyQuad[] = [];
+		for (const entry of components[materialId]) {
+			if (!isSimplifiable(entry[0])) {
+				kept.push(entry);
+				continue;
+			}
+			const corners = getCorners(entry[0], entry[1]);
+			if (getQuadArea(corners) < minArea) {
+				continue;
+			}
+			quads.push({ entry, corners, alive: true });
+		}
+		let changed = true;
+		while (changed) {
+			changed = false;
+			const edges = new Map<string, SimplifyQuad[]>();
+			for (const quad of quads) {
+				if (!quad.alive) {
+					continue;
+				}
+				for (const [i, j] of [
+					[0, 1],
+					[1, 3],
+					[3, 2],
+					[2, 0],
+				]) {
+					const key = getEdgeKey(quad.corners[i], quad.corners[j]);
+					if (!edges.has(key)) {
+						edges.set(key, []);
+					}
+					edges.get(key)!.push(quad);
+				}
+			}
+			// a quad's edges move once it grows, so it waits for the next pass
+			const touched = new Set<SimplifyQuad>();
+			for (const candidates of edges.values()) {
+				const [a, b] = candidates;
+				if (
+					candidates.length !== 2 ||
+					!a.alive ||
+					!b.alive ||
+					touched.has(a) ||
+					touched.has(b)
+				) {
+					continue;
+				}
+				const merged = tryMerge(a, b, targetError, maxQuadSize, mergeKeys);
+				if (!merged) {
+					continue;
+				}
+				a.entry = [merged.component, merged.worldPos];
+				a.corners = getCorners(merged.component, merged.worldPos);
+				b.alive = false;
+				touched.add(a);
+				touched.add(b);
+				changed = true;
+			}
+		}
+		for (const quad of quads) {
+			if (quad.alive) {
+				kept.push(quad.entry);
+			}
+		}
+		simplified[materialId] = kept;
+	}
+	return simplified;
+}
@@ -0,0 +1,258 @@
+import * as THREE from "three";
+import type {
+	MeshSnapshot,
+	MeshSnapshotDiff,
+	MeshSnapshotGroup,
+} from "./types";
+
+const SNAPSHOT_VERSION = 2;
+// rounding keeps snapshots stable against float noise from rotations
+const SNAPSHOT_PRECISION = 1e4;
+const QUAD_ATTRIBUTES = [
+	["position", 3],
+	["normal", 3],
+	["uv", 2],
+] as const;
+
+export function hashNumbers(values: ArrayLike<number>, seed = 0x811c9dc5) {
+	let hash = seed;
+	for (let i = 0; i < values.length; i++) {
+		hash ^= values[i] & 0xffff;
+		hash = Math.imul(hash, 0x01000193);
+		hash ^= (values[i] >>> 16) & 0xffff;
+		hash = Math.imul(hash, 0x01000193);
+	}
+	return hash >>> 0;
+}
+
+export function hashString(value: string, seed = 0x811c9dc5) {
+	let hash = seed;
+	for (let i = 0; i < value.length; i++) {
+		hash ^= value.charCodeAt(i);
+		hash = Math.imul(hash, 0x01000193);
+	}
+	return hash >>> 0;
+}
+
+function toHex(hash: number) {
+	return hash.toString(16).padStart(8, "0");
+}
+
+function quantize(value: number) {
+	return Math.round(value * SNAPSHOT_PRECISION) | 0;
+}
+
+// quads are sorted by their content so that iteration order changes in the builder don't change the hash,
+// positions include the mesh translation so recentered and plain builds hash alike
+function getCanonicalQuads(
+	geometry: THREE.BufferGeometry,
+	translation: THREE.Vector3
+) {
+	const offset = [translation.x, translation.y, translation.z];
+	const vertexCount = geometry.getAttribute("position").count;
+	const quads: { [attribute: string]: number[] }[] = [];
+	for (let vertex = 0; vertex < vertexCount; vertex += 4) {
+		const quad: { [attribute: string]: number[] } = {};
+		for (const [name, itemSize] of QUAD_ATTRIBUTES) {
+			const attribute = geometry.getAttribute(name);
+			const values: number[] = [];
+			if (attribute) {
+				for (let i = vertex * itemSize; i < (vertex + 4) * itemSize; i++) {
+					values.push(
+						quantize(
+							attribute.array[i] + (name === "position" ? offset[i % 3] : 0)
+						)
+					);
+				}
+			}
+			quad[name] = values;
+		}
+		quads.push(quad);
+	}
+	const compare = (a: number[], b: number[]) => {
+		for (let i = 0; i < Math.min(a.length, b.length); i++) {
+			if (a[i] !== b[i]) {
+				return a[i] - b[i];
+			}
+		}
+		return a.length - b.length;
+	};
+	return quads.sort(
+		(a, b) =>
+			compare(a.position, b.position) ||
+			compare(a.normal, b.normal) ||
+			compare(a.uv, b.uv)
+	);
+}
+
+function getQuadRegion(positions: number[], regionSize: number) {
+	const center = [0, 0, 0];
+	for (let i = 0; i < positions.length; i++) {
+		center[i % 3] += positions[i] / SNAPSHOT_PRECISION / 4;
+	}
+	return center.map((value) => Math.floor(value / regionSize)).join(",");
+}
+
+// with a region size the snapshot also hashes each region of space on its own, so a
+// diff can point at where two builds differ and not only which material
+export function createMeshSnapshot(
+	meshes: THREE.Mesh[],
+	options: { regionSize?: number } = {}
+): MeshSnapshot {
+	const groups: MeshSnapshotGroup[] = [];
+	const regionQuads = new Map<string, Map<string, number[]>>();
+	for (const mesh of meshes) {
+		const geometry = mesh.geometry;
+		if (!geometry.getAttribute("position")) {
+			continue;
+		}
+		const quads = getCanonicalQuads(geometry, mesh.position);
+		if (options.regionSize) {
+			if (!regionQuads.has(mesh.name)) {
+				regionQuads.set(mesh.name, new Map());
+			}
+			const regions = regionQuads.get(mesh.name)!;
+			for (const quad of quads) {
+				const region = getQuadRegion(quad.position, options.regionSize);
+				if (!regions.has(region)) {
+					regions.set(region, []);
+				}
+				regions
+					.get(region)!
+					.push(
+						hashNumbers([...quad.position, ...quad.normal, ...quad.uv])
+					);
+			}
+		}
+		const hashes: { [attribute: string]: string } = {};
+		for (const [name] of QUAD_ATTRIBUTES) {
+			let hash = 0x811c9dc5;
+			for (const quad of quads) {
+				hash = hashNumbers(quad[name], hash);
+			}
+			hashes[name] = toHex(hash);
+		}
+		groups.push({
+			materialId: mesh.name,
+			vertexCount: quads.length * 4,
+			quadCount: quads.length,
+			hashes,
+		});
+	}
+	groups.sort((a, b) =>
+		a.materialId === b.materialId
+			? a.hashes.position.localeCompare(b.hashes.position)
+			: a.materialId.localeCompare(b.materialId)
+	);
+
+	let hash = 0x811c9dc5;
+	for (const group of groups) {
+		hash = hashString(group.materialId, hash);
+		for (const [name] of QUAD_ATTRIBUTES) {
+			hash = hashString(group.hashes[name], hash);
+		}
+	}
+	const snapshot: MeshSnapshot = {
+		version: SNAPSHOT_VERSION,
+		hash: toHex(hash),
+		groups,
+	};
+	if (options.regionSize) {
+		snapshot.regionSize = options.regionSize;
+		snapshot.regions = {};
+		for (const [materialId, regions] of regionQuads) {
+			const hashes: { [region: string]: string } = {};
+			for (const [region, quadHashes] of regions) {
+				// sorted so the hash doesn't depend on which chunk a quad came from
+				hashes[region] = toHex(hashNumbers(quadHashes.sort((a, b) => a - b)));
+			}
+			snapshot.regions[materialId] = hashes;
+		}
+	}
+	return snapshot;
+}
+
+function groupByMaterial(snapshot: MeshSnapshot) {
+	const groups = new Map<string, MeshSnapshotGroup[]>();
+	for (const group of snapshot.groups) {
+		if (!groups.has(group.materialId)) {
+			groups.set(group.materialId, []);
+		}
+		groups.get(group.materialId)!.push(group);
+	}
+	return groups;
+}
+
+// compares two snapshots, regions are only compared when both were taken with the same
+// region size
+export function diffMeshSnapshots(
+	a: MeshSnapshot,
+	b: MeshSnapshot
+): MeshSnapshotDiff {
+	const diff: MeshSnapshotDiff = {
+		identical: a.hash === b.hash,
+		groups: [],
+		regions: [],
+	};
+	const groupsA = groupByMaterial(a);
+	const groupsB = groupByMaterial(b);
+	for (const materialId of new Set([...groupsA.keys(), ...groupsB.keys()])) {
+		const before = groupsA.get(materialId) ?? [];
+		const after = groupsB.get(materialId) ?? [];
+		const quadCount: [number, number] = [
+			before.reduce((count, group) => count + group.quadCount, 0),
+			after.reduce((count, group) => count + group.quadCount, 0),
+		];
+		if (before.length === 0 || after.length === 0) {
+			diff.groups.push({
+				materialId,
+				status: before.length === 0 ? "added" : "removed",
+				quadCount,
+				attributes: [],
+			});
+			continue;
+		}
+		const attributes = QUAD_ATTRIBUTES.map(([name]) => name).filter(
+			(name) =>
+				before.map((group) => group.hashes[name]).join() !==
+				after.map((group) => group.hashes[name]).join()
+		);
+		if (attributes.length > 0 || quadCount[0] !== quadCount[1]) {
+			diff.groups.push({
+				materialId,
+				status: "changed",
+				quadCount,
+				attributes,
+			});
+		}
+	}
+	if (!a.regions || !b.regions || a.regionSize !== b.regionSize) {
+		return diff;
+	}
+	for (const materialId of new Set([
+		...Object.keys(a.regions),
+		...Object.keys(b.regions),
+	])) {
+		const before = a.regions[materialId] ?? {};
+		const after = b.regions[materialId] ?? {};
+		for (const region of new Set([
+			...Object.keys(before),
+			...Object.keys(after),
+		])) {
+			if (before[region] === after[region]) {
+				continue;
+			}
+			diff.regions.push({
+				materialId,
+				region,
+				status:
+					before[region] === undefined
+						? "added"
+						: after[region] === undefined
+						? "removed"
+						: "changed",
+			});
+		}
+	}
+	return diff;
+}
@@ -0,0 +1,225 @@
+import * as THREE from "three";
+import JSZip from "jszip";
+import { getAverageMaterialColor } from "./material_colors";
+
+interface ExportTriangleMesh {
+	positions: number[];
+	normals: number[];
+	indices: number[];
+	color: THREE.Color;
+	name: string;
+}
+
+// bakes the mesh transforms into the vertices so every exporter works in world space
+function collectTriangleMeshes(meshes: THREE.Mesh[]) {
+	const result: ExportTriangleMesh[] = [];
+	const vertex = new THREE.Vector3();
+	const normal = new THREE.Vector3();
+	const normalMatrix = new THREE.Matrix3();
+	for (const mesh of meshes) {
+		const geometry = mesh.geometry;
+		const position = geometry.getAttribute("position");
+		const normalAttribute = geometry.getAttribute("normal");
+		if (!position) {
+			continue;
+		}
+		mesh.updateMatrixWorld();
+		normalMatrix.getNormalMatrix(mesh.matrixWorld);
+		const positions: number[] = [];
+		const normals: number[] = [];
+		for (let i = 0; i < position.count; i++) {
+			vertex.fromBufferAttribute(position, i).applyMatrix4(mesh.matrixWorld);
+			positions.push(vertex.x, vertex.y, vertex.z);
+			if (normalAttribute) {
+				normal
+					.fromBufferAttribute(normalAttribute, i)
+					.applyMatrix3(normalMatrix)
+					.normalize();
+				normals.push(normal.x, normal.y, normal.z);
+			} else {
+				normals.push(0, 1, 0);
+			}
+		}
+		const index = geometry.getIndex();
+		const indices = index
+			? Array.from(index.array as ArrayLike<number>)
+			: Array.from({ length: position.count }, (_, i) => i);
+		const material = Array.isArray(mesh.material)
+			? mesh.material[0]
+			: mesh.material;
+		result.push({
+			positions,
+			normals,
+			indices,
+			color: getAverageMaterialColor(material),
+			name: mesh.name,
+		});
+	}
+	return result;
+}
+
+// binary little endian ply with per vertex colors taken from the average material color
+export function exportPly(meshes: THREE.Mesh[]) {
+	const triangleMeshes = collectTriangleMeshes(meshes);
+	let vertexCount = 0;
+	let faceCount = 0;
+	for (const mesh of triangleMeshes) {
+		vertexCount += mesh.positions.length / 3;
+		faceCount += mesh.indices.length / 3;
+	}
+	const header = [
+		"ply",
+		"format binary_little_endian 1.0",
+		"comment exported by schematic-renderer",
+		`element vertex ${vertexCount}`,
+		"property float x",
+		"property float y",
+		"property float z",
+		"property float nx",
+		"property float ny",
+		"property float nz",
+		"property uchar red",
+		"property uchar green",
+		"property uchar blue",
+		`element face ${faceCount}`,
+		"property list uchar int vertex_indices",
+		"end_header",
+		"",
+	].join("\n");
+	const headerBytes = new TextEncoder().encode(header);
+	const buffer = new ArrayBuffer(
+		headerBytes.length + vertexCount * 27 + faceCount * 13
+	);
+	new Uint8Array(buffer).set(headerBytes);
+	const view = new DataView(buffer);
+	let offset = headerBytes.length;
+	for (const mesh of triangleMeshes) {
+		const rgb = [mesh.color.r, mesh.color.g, mesh.color.b].map((c) =>
+			Math.round(THREE.MathUtils.clamp(c, 0, 1) * 255)
+		);
+		for (let i = 0; i < mesh.positions.length; i += 3) {
+			for (let c = 0; c < 3; c++) {
+				view.setFloat32(offset, mesh.positions[i + c], true);
+				view.setFloat32(offset + 12, mesh.normals[i + c], true);
+				offset += 4;
+			}
+			offset += 12;
+			for (const value of rgb) {
+				view.setUint8(offset++, value);
+			}
+		}
+	}
+	let vertexOffset = 0;
+	for (const mesh of triangleMeshes) {
+		for (let i = 0; i < mesh.indices.length; i += 3) {
+			view.setUint8(offset++, 3);
+			for (let c = 0; c < 3; c++) {
+				view.setInt32(offset, mesh.indices[i + c] + vertexOffset, true);
+				offset += 4;
+			}
+		}
+		vertexOffset += mesh.positions.length / 3;
+	}
+	return buffer;
+}
+
+function toHexColor(color: THREE.Color) {
+	return `#${color.getHexString().toUpperCase()}`;
+}
+
+const XML_ENTITIES: { [char: string]: string } = {
+	"&": "&amp;",
+	"<": "&lt;",
+	">": "&gt;",
+	'"': "&quot;",
+	"'": "&apos;",
+};
+
+function escapeXml(value: string) {
+	return value.replace(/[&<>"']/g, (char) => XML_ENTITIES[char]);
+}
+
+// 3mf is z up and measured in millimetres, the build is moved onto the positive octant
+// since most slicers expect the model to sit on the plate
+export async function export3mf(
+	meshes: THREE.Mesh[],
+	options: { blockSize?: number } = {}
+) {
+	const blockSize = options.blockSize ?? 10;
+	const triangleMeshes = collectTriangleMeshes(meshes);
+	const min = [Infinity, Infinity, Infinity];
+	for (const mesh of triangleMeshes) {
+		for (let i = 0; i < mesh.positions.length; i += 3) {
+			const [x, y, z] = mesh.positions.slice(i, i + 3);
+			min[0] = Math.min(min[0], x);
+			min[1] = Math.min(min[1], -z);
+			min[2] = Math.min(min[2], y);
+		}
+	}
+	const format = (value: number) => Number(value.toFixed(4));
+	const materials = triangleMeshes
+		.map(
+			(mesh) =>
+				`<base name="${escapeXml(mesh.name)}" displaycolor="${toHexColor(
+					mesh.color
+				)}" />`
+		)
+		.join("");
+	const objects: string[] = [];
+	const items: string[] = [];
+	triangleMeshes.forEach((mesh, meshIndex) => {
+		const id = meshIndex + 2;
+		const vertices: string[] = [];
+		for (let i = 0; i < mesh.positions.length; i += 3) {
+			const [x, y, z] = mesh.positions.slice(i, i + 3);
+			vertices.push(
+				`<vertex x="${format((x - min[0]) * blockSize)}" y="${format(
+					(-z - min[1]) * blockSize
+				)}" z="${format((y - min[2]) * blockSize)}" />`
+			);
+		}
+		const triangles: string[] = [];
+		for (let i = 0; i < mesh.indices.length; i += 3) {
+			triangles.push(
+				`<triangle v1="${mesh.indices[i]}" v2="${mesh.indices[i + 1]}" v3="${
+					mesh.indices[i + 2]
+				}" />`
+			);
+		}
+		objects.push(
+			`<object id="${id}" type="model" pid="1" pindex="${meshIndex}"><mesh>` +
+				`<vertices>${vertices.join("")}</vertices>` +
+				`<triangles>${triangles.join("")}</triangles></mesh></object>`
+		);
+		items.push(`<item objectid="${id}" />`);
+	});
+	const model =
+		`<?xml version="1.0" encoding="UTF-8"?>` +
+		`<model unit="millimeter" xml:lang="en-US" ` +
+		`xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">` +
+		`<resources><basematerials id="1">${materials}</basematerials>` +
+		`${objects.join("")}</resources>` +
+		`<build>${items.join("")}</build></model>`;
+
+	const zip = new JSZip();
+	zip.file(
+		"[Content_Types].xml",
+		`<?xml version="1.0" encoding="UTF-8"?>` +
+			`<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">` +
+			`<Default Extension="rels" ` +
+			`ContentType="application/vnd.openxmlformats-package.relationships+xml" />` +
+			`<Default Extension="model" ` +
+			`ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml" />` +
+			`</Types>`
+	);
+	zip.file(
+		"_rels/.rels",
+		`<?xml version="1.0" encoding="UTF-8"?>` +
+			`<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">` +
+			`<Relationship Target="/3D/3dmodel.model" Id="rel0" ` +
+			`Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel" />` +
+			`</Relationships>`
+	);
+	zip.file("3D/3dmodel.model", model);
+	return zip.generateAsync({ type: "blob" });
+}
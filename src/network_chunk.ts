@@ -0,0 +1,144 @@
+import { createBlockSetSchematic } from "./legacy_formats";
+import type { NetworkChunkOptions } from "./types";
+
+const SECTION_SIZE = 16;
+const BLOCKS_PER_SECTION = SECTION_SIZE * SECTION_SIZE * SECTION_SIZE;
+const BIOMES_PER_SECTION = 4 * 4 * 4;
+
+class PacketReader {
+	offset = 0;
+	private view: DataView;
+
+	constructor(private bytes: Uint8Array) {
+		this.view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+	}
+
+	get remaining() {
+		return this.bytes.byteLength - this.offset;
+	}
+
+	readUint8() {
+		return this.view.getUint8(this.offset++);
+	}
+
+	readInt16() {
+		const value = this.view.getInt16(this.offset);
+		this.offset += 2;
+		return value;
+	}
+
+	readBigUint64() {
+		const value = this.view.getBigUint64(this.offset);
+		this.offset += 8;
+		return value;
+	}
+
+	readVarInt() {
+		let value = 0;
+		let shift = 0;
+		let byte;
+		do {
+			byte = this.readUint8();
+			value |= (byte & 0x7f) << shift;
+			shift += 7;
+		} while (byte & 0x80 && shift < 35);
+		return value;
+	}
+}
+
+// single value, indirect and direct palettes share one layout since 1.18, and entries
+// never straddle two longs since 1.16
+function readPalettedContainer(
+	reader: PacketReader,
+	entryCount: number,
+	maxIndirectBits: number,
+	lengthPrefix: boolean
+) {
+	const bitsPerEntry = reader.readUint8();
+	const values = new Int32Array(entryCount);
+	if (bitsPerEntry === 0) {
+		values.fill(reader.readVarInt());
+		if (lengthPrefix) {
+			reader.readVarInt();
+		}
+		return values;
+	}
+	let palette: number[] | undefined;
+	if (bitsPerEntry <= maxIndirectBits) {
+		const paletteLength = reader.readVarInt();
+		palette = [];
+		for (let i = 0; i < paletteLength; i++) {
+			palette.push(reader.readVarInt());
+		}
+	}
+	const entriesPerLong = Math.floor(64 / bitsPerEntry);
+	const longCount = lengthPrefix
+		? reader.readVarInt()
+		: Math.ceil(entryCount / entriesPerLong);
+	const mask = (1n << BigInt(bitsPerEntry)) - 1n;
+	let entry = 0;
+	for (let i = 0; i < longCount; i++) {
+		const long = reader.readBigUint64();
+		for (let j = 0; j < entriesPerLong && entry < entryCount; j++) {
+			const value = Number((long >> BigInt(j * bitsPerEntry)) & mask);
+			values[entry++] = palette ? palette[value] ?? 0 : value;
+		}
+	}
+	return values;
+}
+
+function resolveBlockState(
+	state: NetworkChunkOptions["blockStates"][number] | undefined
+) {
+	if (state === undefined) {
+		return undefined;
+	}
+	if (typeof state === "string") {
+		return { type: state.replace(/^minecraft:/, ""), properties: {} };
+	}
+	return {
+		type: state.type.replace(/^minecraft:/, ""),
+		properties: state.properties ?? {},
+	};
+}
+
+// takes the chunk data array of a chunk data packet, the sections follow each other until
+// the buffer runs out unless a section count is given
+export function loadNetworkChunk(
+	bytes: Uint8Array,
+	options: NetworkChunkOptions
+) {
+	const reader = new PacketReader(bytes);
+	const lengthPrefix = options.dataArrayLengthPrefix ?? true;
+	const blocks = new Map<string, { type: string; properties: any }>();
+	let section = 0;
+	while (
+		reader.remaining > 0 &&
+		(options.sectionCount === undefined || section < options.sectionCount)
+	) {
+		reader.readInt16();
+		const states = readPalettedContainer(
+			reader,
+			BLOCKS_PER_SECTION,
+			8,
+			lengthPrefix
+		);
+		readPalettedContainer(reader, BIOMES_PER_SECTION, 3, lengthPrefix);
+		for (let i = 0; i < BLOCKS_PER_SECTION; i++) {
+			const block = resolveBlockState(options.blockStates[states[i]]);
+			if (!block || block.type === "air" || block.type.endsWith("_air")) {
+				continue;
+			}
+			const x = i & 15;
+			const z = (i >> 4) & 15;
+			const y = (i >> 8) + section * SECTION_SIZE;
+			blocks.set(`${x},${y},${z}`, block);
+		}
+		section++;
+	}
+	return createBlockSetSchematic(
+		blocks,
+		{ format: "network_chunk", minY: options.minY ?? 0, sections: section },
+		[SECTION_SIZE, section * SECTION_SIZE, SECTION_SIZE]
+	);
+}
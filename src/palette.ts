@@ -0,0 +1,123 @@
+import * as THREE from "three";
+import type { BlockMeshBuilder } from "./block_mesh_builder";
+import type { PaletteEntrySummary } from "./types";
+import {
+	getBlockCategory,
+	hashBlockForMap,
+	NON_OCCLUDING_BLOCKS,
+} from "./utils";
+
+const PALETTE_BLOB_VERSION = 2;
+
+// distinct block states in the order the schematic first uses them, available
+// before anything is built
+export function getSchematicPalette(schematic: any) {
+	const palette = new Map<string, any>();
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (!block) {
+			continue;
+		}
+		const key = hashBlockForMap(block);
+		if (!palette.has(key)) {
+			palette.set(key, block);
+		}
+	}
+	return [...palette.values()];
+}
+
+export function getPaletteSummary(
+	blockMeshBuilder: BlockMeshBuilder
+): PaletteEntrySummary[] {
+	const summary: PaletteEntrySummary[] = [];
+	let index = 0;
+	for (const [key, blockComponents] of blockMeshBuilder.blockMeshCache) {
+		const block = blockMeshBuilder.paletteBlocks.get(key);
+		const type = block?.type ?? key;
+		let vertexCount = 0;
+		let floatCount = 0;
+		for (const component of Object.values(blockComponents) as any[]) {
+			vertexCount += component.positions.length / 3;
+			floatCount +=
+				component.positions.length +
+				component.normals.length +
+				component.uvs.length;
+		}
+		const category = getBlockCategory(type);
+		summary.push({
+			index: index++,
+			key,
+			block,
+			category,
+			occludesNeighbors: !NON_OCCLUDING_BLOCKS.has(type),
+			transparent: category === "transparent",
+			geometryCount: Object.keys(blockComponents).length,
+			vertexCount,
+			memoryBytes: floatCount * Float32Array.BYTES_PER_ELEMENT,
+		});
+	}
+	return summary;
+}
+
+// the baked block meshes only reference materials by id, so the blob also carries enough
+// to recreate each material from the resource packs when it is imported
+export function exportPaletteBlob(blockMeshBuilder: BlockMeshBuilder) {
+	const materialIds = new Set<string>();
+	const entries = [];
+	for (const [key, blockComponents] of blockMeshBuilder.blockMeshCache) {
+		for (const component of Object.values(blockComponents) as any[]) {
+			materialIds.add(component.materialId);
+		}
+		entries.push({
+			key,
+			block: blockMeshBuilder.paletteBlocks.get(key),
+			components: blockComponents,
+		});
+	}
+	const materials: { [materialId: string]: any } = {};
+	for (const materialId of materialIds) {
+		materials[materialId] =
+			blockMeshBuilder.materialDescriptors.get(materialId) ?? null;
+	}
+	return new Blob(
+		[JSON.stringify({ version: PALETTE_BLOB_VERSION, entries, materials })],
+		{ type: "application/json" }
+	);
+}
+
+export async function importPaletteBlob(
+	blockMeshBuilder: BlockMeshBuilder,
+	blob: Blob
+) {
+	const data = JSON.parse(await blob.text());
+	if (data.version !== PALETTE_BLOB_VERSION) {
+		throw new Error(`Unsupported palette blob version ${data.version}`);
+	}
+	for (const [materialId, descriptor] of Object.entries(
+		data.materials
+	) as any[]) {
+		if (!descriptor || blockMeshBuilder.materialMap.has(materialId)) {
+			continue;
+		}
+		const material = await blockMeshBuilder.ressourceLoader.getTextureMaterial(
+			{} as any,
+			{ texture: descriptor.texture },
+			descriptor.transparent,
+			descriptor.color
+				? new THREE.Color().fromArray(descriptor.color)
+				: undefined
+		);
+		blockMeshBuilder.materialMap.set(
+			materialId,
+			material ?? new THREE.MeshBasicMaterial()
+		);
+		blockMeshBuilder.materialDescriptors.set(materialId, descriptor);
+	}
+	for (const { key, block, components } of data.entries) {
+		blockMeshBuilder.blockMeshCache.set(key, components);
+		if (block) {
+			blockMeshBuilder.paletteBlocks.set(key, block);
+		}
+	}
+	return data.entries.length as number;
+}
@@ -0,0 +1,56 @@
+import type { BlockMeshBuilder } from "./block_mesh_builder";
+import type { BlockRotation, PaletteBakeJob } from "./types";
+import { yieldToMain } from "./utils";
+
+let nextJobId = 1;
+
+// bakes the palette in slices of frameBudget milliseconds so a large resource pack
+// doesn't block the page, the job settles with the number of blocks baked
+export function startPaletteBake(
+	blockMeshBuilder: BlockMeshBuilder,
+	blocks: any[],
+	options: { frameBudget?: number; rotations?: BlockRotation[] } = {}
+): PaletteBakeJob {
+	const frameBudget = options.frameBudget ?? 8;
+	const callbacks: ((job: PaletteBakeJob) => void)[] = [];
+	const job: PaletteBakeJob = {
+		id: nextJobId++,
+		total: blocks.length,
+		completed: 0,
+		done: false,
+		cancelled: false,
+		promise: Promise.resolve(0),
+		cancel() {
+			job.cancelled = true;
+		},
+		onComplete(callback) {
+			if (job.done) {
+				callback(job);
+			} else {
+				callbacks.push(callback);
+			}
+		},
+	};
+	job.promise = (async () => {
+		let sliceStart = performance.now();
+		// a block that fails to bake still settles the job, the promise rejects with it
+		try {
+			for (const block of blocks) {
+				if (job.cancelled) {
+					break;
+				}
+				await blockMeshBuilder.prepareBlocks([block], options.rotations);
+				job.completed++;
+				if (performance.now() - sliceStart > frameBudget) {
+					await yieldToMain();
+					sliceStart = performance.now();
+				}
+			}
+		} finally {
+			job.done = true;
+			callbacks.forEach((callback) => callback(job));
+		}
+		return job.completed;
+	})();
+	return job;
+}
@@ -0,0 +1,43 @@
+import * as THREE from "three";
+
+// six ints per quad, the inclusive min and max schematic cell it was built from. merged
+// quads carry their blockSpan, everything else covers just its own block
+export function createQuadRanges(
+	blockList: [any, number[]][],
+	offset: { x: number; y: number; z: number }
+) {
+	let quadCount = 0;
+	for (const [blockComponent] of blockList) {
+		quadCount += blockComponent.positions.length / 12;
+	}
+	const ranges = new Int32Array(quadCount * 6);
+	let quad = 0;
+	for (const [blockComponent, worldPos] of blockList) {
+		const span = blockComponent.blockSpan ?? [1, 1, 1];
+		const min = [
+			Math.round(worldPos[0] - offset.x),
+			Math.round(worldPos[1] - offset.y),
+			Math.round(worldPos[2] - offset.z),
+		];
+		for (let i = 0; i < blockComponent.positions.length / 12; i++) {
+			const max = min.map((value, axis) => value + span[axis] - 1);
+			ranges.set([...min, ...max], quad++ * 6);
+		}
+	}
+	return ranges;
+}
+
+// takes any vertex of the hit triangle, e.g. face.a of a raycast hit, since every quad
+// owns 4 consecutive vertices no matter how the index buffer was reordered
+export function getQuadBlockRange(mesh: THREE.Mesh, vertexIndex: number) {
+	const ranges: Int32Array | undefined = mesh.userData.quadRanges;
+	const quad = Math.floor(vertexIndex / 4);
+	if (!ranges || quad * 6 >= ranges.length) {
+		return undefined;
+	}
+	const range = ranges.subarray(quad * 6, quad * 6 + 6);
+	return {
+		min: new THREE.Vector3(range[0], range[1], range[2]),
+		max: new THREE.Vector3(range[3], range[4], range[5]),
+	};
+}
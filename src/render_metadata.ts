@@ -0,0 +1,33 @@
+import * as THREE from "three";
+import type { CategoryRenderSettings, RenderMetadata } from "./types";
+
+function getAlphaMode(material: THREE.Material | undefined) {
+	if (material?.transparent) {
+		return "blend";
+	}
+	return material && material.alphaTest > 0 ? "mask" : "opaque";
+}
+
+// defaults follow the material, the first of the mesh's categories with settings
+// overrides them, so modded categories only need an entry in the settings
+export function getRenderMetadata(
+	mesh: THREE.Mesh,
+	settings: { [category: string]: CategoryRenderSettings } = {}
+): RenderMetadata {
+	const categories = [
+		mesh.userData.category,
+		...(mesh.userData.blockCategories ?? []),
+	].filter((category): category is string => category !== undefined);
+	const category = categories.find((candidate) => settings[candidate]);
+	const override = category ? settings[category] : {};
+	const alphaMode =
+		override.alphaMode ?? getAlphaMode(mesh.material as THREE.Material);
+	const blended = alphaMode === "blend";
+	return {
+		category,
+		alphaMode,
+		renderOrder: override.renderOrder ?? (blended ? 1 : 0),
+		needsSorting: override.needsSorting ?? blended,
+		depthWrite: override.depthWrite ?? !blended,
+	};
+}
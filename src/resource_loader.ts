@@ -2,6 +2,7 @@ import * as THREE from "three";
 import deepmerge from "deepmerge";
 import type { Block } from "@enginehub/schematicjs";
 import {
+	emitWarning,
 	hashBlockForMap,
 	occludedFacesIntToList,
 	REDSTONE_COLORS,
@@ -13,7 +14,11 @@ import type {
 	BlockStateDefinition,
 	BlockStateDefinitionVariant,
 	BlockStateModelHolder,
+	TextureAnimation,
+	TextureColors,
 } from "./types";
+import { getPngSize, parseTextureAnimation } from "./texture_animation";
+import { computeTextureColors, getImagePixels } from "./texture_colors";
 export class ResourceLoader {
 	schematic: any;
 	textureCache: Map<string, THREE.Texture>;
@@ -24,6 +29,8 @@ export class ResourceLoader {
 	blockModelCache: Map<string, BlockModel>;
 	faceDataCache: Map<string, any>;
 	blockStateDefinitionCache: Map<string, BlockStateDefinition>;
+	textureColors: Map<string, TextureColors>;
+	itemTextures: Map<string, THREE.Texture | undefined>;
 
 	materialMap: Map<string, THREE.Material>;
 	base64MaterialMap: Map<string, string>;
@@ -39,9 +46,11 @@ export class ResourceLoader {
 	DEG2RAD = Math.PI / 180;
 
 	DEBUG = true;
+	options: any;
 	constructor(
 		resourcePackBlobs: any,
-		materialMap?: Map<string, THREE.Material>
+		materialMap?: Map<string, THREE.Material>,
+		options: any = {}
 	) {
 		this.textureCache = new Map();
 		this.blobCache = new Map();
@@ -50,12 +59,15 @@ export class ResourceLoader {
 		this.blockMetaCache = new Map();
 		this.blockModelCache = new Map();
 		this.faceDataCache = new Map();
+		this.itemTextures = new Map();
 		this.blockStateDefinitionCache = new Map();
+		this.textureColors = new Map();
 		this.materialMap = materialMap ?? new Map();
 		this.base64MaterialMap = new Map();
 		this.resourcePackBlobs = resourcePackBlobs;
 		this.textureLoader = new THREE.TextureLoader();
 		this.schematic = undefined;
+		this.options = options ?? {};
 	}
 
 	public setSchematic(schematic: any) {
@@ -122,6 +134,55 @@ export class ResourceLoader {
 		}
 	}
 
+	// host supplied descriptors in options.textureAnimations win over the pack's .mcmeta files
+	public async getTextureAnimation(
+		textureName: string
+	): Promise<TextureAnimation | undefined> {
+		textureName = textureName.replace(/^minecraft:/, "");
+		let mcmeta = this.options.textureAnimations?.[textureName];
+		if (!mcmeta) {
+			const mcmetaString = await this.getResourceString(
+				`textures/${textureName}.png.mcmeta`
+			);
+			if (!mcmetaString) {
+				return undefined;
+			}
+			try {
+				mcmeta = JSON.parse(mcmetaString);
+			} catch (e) {
+				return undefined;
+			}
+		}
+		const base64Png = await this.getResourceBase64(
+			`textures/${textureName}.png`
+		);
+		const imageSize = base64Png
+			? getPngSize(base64Png)
+			: { width: 16, height: 16 };
+		return parseTextureAnimation(textureName, mcmeta, imageSize);
+	}
+
+	// block items without a flat sprite fall back to the block's own texture
+	public async getItemTexture(itemId: string) {
+		const name = itemId.replace(/^minecraft:/, "");
+		if (this.itemTextures.has(name)) {
+			return this.itemTextures.get(name);
+		}
+		const base64Resource =
+			(await this.getResourceBase64(`textures/item/${name}.png`)) ??
+			(await this.getResourceBase64(`textures/block/${name}.png`));
+		let texture: THREE.Texture | undefined;
+		if (base64Resource !== undefined) {
+			texture = this.textureLoader.load(
+				"data:image/png;base64," + base64Resource
+			);
+			texture.minFilter = THREE.NearestFilter;
+			texture.magFilter = THREE.NearestFilter;
+		}
+		this.itemTextures.set(name, texture);
+		return texture;
+	}
+
 	public async getBase64Image(model: BlockModel, faceData: any) {
 		const textureName = this.resolveTextureName(faceData.texture, model);
 		const base64Resource = await this.getResourceBase64(
@@ -162,6 +223,11 @@ export class ResourceLoader {
 		);
 
 		if (base64Resource === undefined) {
+			emitWarning(this.options, {
+				type: "missing_texture",
+				message: `Texture ${textureName} was not found in any resource pack`,
+				resource: `textures/${textureName}.png`,
+			});
 			return undefined;
 		}
 		const base64Png = "data:image/png;base64," + base64Resource;
@@ -170,15 +236,15 @@ export class ResourceLoader {
 			texture.minFilter = THREE.NearestFilter;
 			texture.magFilter = THREE.NearestFilter;
 			texture.needsUpdate = true;
+			material.userData.textureColors = this.getTextureColors(
+				textureName,
+				texture.image
+			);
 		});
-
-		//check if the faceData is rotated if so rotate the texture
-		const rotation = faceData.rotation;
-		if (rotation) {
-			texture.center = new THREE.Vector2(0.5, 0.5);
-			texture.rotation = rotation * Math.PI * 0.25;
-		}
-		return new THREE.MeshStandardMaterial({
+		// greedy merged quads tile the texture once per block
+		texture.wrapS = THREE.RepeatWrapping;
+		texture.wrapT = THREE.RepeatWrapping;
+		const material = new THREE.MeshStandardMaterial({
 			map: texture,
 			//side: transparent ? THREE.DoubleSide : THREE.FrontSide,
 			side: THREE.FrontSide,
@@ -186,6 +252,24 @@ export class ResourceLoader {
 			transparent: transparent,
 			color: color ?? 0xffffff,
 		});
+		return material;
+	}
+
+	// read back once per texture, host supplied colors in options.textureColors win
+	public getTextureColors(textureName: string, image?: any) {
+		const cached =
+			this.options.textureColors?.[textureName] ??
+			this.textureColors.get(textureName);
+		if (cached || !image?.width) {
+			return cached as TextureColors | undefined;
+		}
+		const pixels = getImagePixels(image);
+		if (!pixels) {
+			return undefined;
+		}
+		const colors = computeTextureColors(pixels, image.width, image.height);
+		this.textureColors.set(textureName, colors);
+		return colors;
 	}
 
 	public getModelOption(data: BlockModelData) {
@@ -355,7 +439,16 @@ export class ResourceLoader {
 			const geometry = new THREE.BufferGeometry();
 			const positions = new Float32Array(totalVertices * 3);
 			const normals = new Float32Array(totalVertices * 3);
-			const uvs = new Float32Array(totalVertices * 2);
+			// color only groups carry rgba per vertex and no uvs at all
+			const hasColors = (blockList as any).some(
+				(block: any) => block[0].colors
+			);
+			const uvs = hasColors ? null : new Float32Array(totalVertices * 2);
+			const colors = hasColors ? new Float32Array(totalVertices * 4) : null;
+			// corner occlusion from options.voxelAo, faces without it stay fully open
+			const ao = (blockList as any).some((block: any) => block[0].ao)
+				? new Uint8Array(totalVertices).fill(3)
+				: null;
 			chunkTimes.chunkMeshCreation.arrayCreation += performance.now() - start;
 
 			// const indices: Uint16Array = new Uint16Array(totalIndices);
@@ -382,7 +475,11 @@ export class ResourceLoader {
 				start = performance.now();
 				positions.set(newPositions, indexOffset * 3);
 				normals.set(blockComponent.normals, indexOffset * 3);
-				uvs.set(blockComponent.uvs, indexOffset * 2);
+				uvs?.set(blockComponent.uvs, indexOffset * 2);
+				colors?.set(blockComponent.colors, indexOffset * 4);
+				if (ao && blockComponent.ao) {
+					ao.set(blockComponent.ao, indexOffset);
+				}
 				chunkTimes.chunkMeshCreation.arrayAllocation +=
 					performance.now() - start;
 
@@ -413,12 +510,23 @@ export class ResourceLoader {
 				new THREE.BufferAttribute(positions, 3)
 			);
 			geometry.setAttribute("normal", new THREE.BufferAttribute(normals, 3));
-			geometry.setAttribute("uv", new THREE.BufferAttribute(uvs, 2));
+			if (uvs) {
+				geometry.setAttribute("uv", new THREE.BufferAttribute(uvs, 2));
+			}
+			if (colors) {
+				geometry.setAttribute("color", new THREE.BufferAttribute(colors, 4));
+			}
+			if (ao) {
+				geometry.setAttribute("ao", new THREE.BufferAttribute(ao, 1));
+			}
 			geometry.setIndex(indices);
+			geometry.computeBoundingBox();
+			geometry.computeBoundingSphere();
 			// geometry.setIndex(new THREE.BufferAttribute(indices, 1));
 			chunkTimes.chunkMeshCreation.geometryCreation +=
 				performance.now() - start;
 			const mesh = new THREE.Mesh(geometry, material);
+			mesh.name = materialId;
 			mesh.castShadow = true;
 			mesh.receiveShadow = true;
 			meshes.push(mesh);
@@ -546,9 +654,15 @@ export class ResourceLoader {
 		if (modelRef.startsWith("minecraft:")) {
 			modelRef = modelRef.substring("minecraft:".length);
 		}
-		let model = JSON.parse(
-			(await this.getResourceString(`models/${modelRef}.json`)) ?? "{}"
-		) as BlockModel;
+		const modelString = await this.getResourceString(`models/${modelRef}.json`);
+		if (modelString === undefined) {
+			emitWarning(this.options, {
+				type: "missing_model",
+				message: `Model ${modelRef} was not found in any resource pack`,
+				resource: `models/${modelRef}.json`,
+			});
+		}
+		let model = JSON.parse(modelString ?? "{}") as BlockModel;
 
 		if (model.parent) {
 			const parent = await this.loadModel(model.parent);
@@ -0,0 +1,91 @@
+import { loadSchematic } from "@enginehub/schematicjs";
+import { Buffer } from "buffer/";
+import { parseNbt } from "./utils";
+import { readBlockEntities } from "./inventory_preview";
+import { isMcstructure, loadMcstructure } from "./mcstructure_loader";
+import { despeckleSchematic } from "./despeckle";
+import { isVox, loadVox } from "./vox_loader";
+import {
+	isBo2,
+	isBo3,
+	isLegacyClipboard,
+	loadBo2,
+	loadBo3,
+	loadLegacyClipboard,
+} from "./legacy_formats";
+
+export type SchematicFormat =
+	| "auto"
+	| "java"
+	| "mcstructure"
+	| "bo2"
+	| "bo3"
+	| "vox";
+
+export function detectSchematicFormat(bytes: Uint8Array): SchematicFormat {
+	if (isVox(bytes)) {
+		return "vox";
+	}
+	if (isMcstructure(bytes)) {
+		return "mcstructure";
+	}
+	// nbt always starts with a binary tag id, the object formats are plain text
+	if (bytes[0] !== 10 && bytes[0] !== 0x1f) {
+		const text = new TextDecoder().decode(bytes.subarray(0, 4096));
+		if (isBo2(text)) {
+			return "bo2";
+		}
+		if (isBo3(text)) {
+			return "bo3";
+		}
+	}
+	return "java";
+}
+
+export function loadSchematicFromBase64(
+	schematicData: string,
+	options: any = {}
+) {
+	const schematic = loadSchematicData(
+		schematicData,
+		options.schematicFormat,
+		options.voxPalette
+	);
+	if (options.despeckle) {
+		return despeckleSchematic(
+			schematic,
+			options.despeckle === true ? {} : options.despeckle
+		);
+	}
+	return schematic;
+}
+
+function loadSchematicData(
+	schematicData: string,
+	format: SchematicFormat = "auto",
+	voxPalette?: { [colorOrIndex: string]: string }
+) {
+	const buff = Buffer.from(schematicData, "base64");
+	const bytes = new Uint8Array(buff.buffer, buff.byteOffset, buff.byteLength);
+	const resolvedFormat =
+		format === "auto" ? detectSchematicFormat(bytes) : format;
+	switch (resolvedFormat) {
+		case "mcstructure":
+			return loadMcstructure(bytes);
+		case "bo2":
+			return loadBo2(new TextDecoder().decode(bytes));
+		case "bo3":
+			return loadBo3(new TextDecoder().decode(bytes));
+		case "vox":
+			return loadVox(bytes, voxPalette);
+		default: {
+			// legacy worldedit clipboards share the java nbt container
+			const nbt = parseNbt(buff);
+			const blockEntities = readBlockEntities(nbt);
+			if (isLegacyClipboard(nbt)) {
+				return Object.assign(loadLegacyClipboard(nbt), { blockEntities });
+			}
+			return Object.assign(loadSchematic(nbt), { blockEntities });
+		}
+	}
+}
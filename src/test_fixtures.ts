@@ -0,0 +1,81 @@
+import { createBlockSetSchematic, parseBlockName } from "./legacy_formats";
+import type { FixtureKind, FixtureOptions } from "./types";
+
+const DEFAULT_FIXTURE_BLOCKS = ["stone", "oak_planks"];
+
+function parseBlocks(names: string[]) {
+	return names
+		.map(parseBlockName)
+		.filter(
+			(block): block is { type: string; properties: any } =>
+				block !== undefined
+		);
+}
+
+// deterministic block sets with known geometry, for benchmarks and for checking a
+// material pipeline against a reference render
+export function createFixtureSchematic(
+	kind: FixtureKind,
+	options: FixtureOptions = {}
+) {
+	const size = options.size ?? 16;
+	const blocks = parseBlocks(options.blocks ?? DEFAULT_FIXTURE_BLOCKS);
+	if (blocks.length === 0) {
+		throw new Error("Fixtures need at least one block");
+	}
+	const cells = new Map<string, { type: string; properties: any }>();
+	const set = (x: number, y: number, z: number, index: number) =>
+		cells.set(`${x},${y},${z}`, blocks[index % blocks.length]);
+
+	switch (kind) {
+		// a solid cube alternating materials, everything inside is culled
+		case "checkerboard":
+			for (let x = 0; x < size; x++) {
+				for (let y = 0; y < size; y++) {
+					for (let z = 0; z < size; z++) {
+						set(x, y, z, x + y + z);
+					}
+				}
+			}
+			break;
+		case "sphere": {
+			const radius = size / 2;
+			for (let x = 0; x < size; x++) {
+				for (let y = 0; y < size; y++) {
+					for (let z = 0; z < size; z++) {
+						const dx = x + 0.5 - radius;
+						const dy = y + 0.5 - radius;
+						const dz = z + 0.5 - radius;
+						if (dx * dx + dy * dy + dz * dz <= radius * radius) {
+							set(x, y, z, y);
+						}
+					}
+				}
+			}
+			break;
+		}
+		// every block on its own with a gap around it, so no face is culled by a neighbour
+		case "palette_grid": {
+			const columns = Math.ceil(Math.sqrt(blocks.length));
+			blocks.forEach((_block, i) => {
+				set((i % columns) * 2, 0, Math.floor(i / columns) * 2, i);
+			});
+			break;
+		}
+		// blocks touching only along edges, nothing can be culled or merged
+		case "greedy_worst_case":
+			for (let x = 0; x < size; x++) {
+				for (let y = 0; y < size; y++) {
+					for (let z = 0; z < size; z++) {
+						if ((x + y + z) % 2 === 0) {
+							set(x, y, z, 0);
+						}
+					}
+				}
+			}
+			break;
+		default:
+			throw new Error(`Unknown fixture ${kind}`);
+	}
+	return createBlockSetSchematic(cells, { format: "fixture", fixture: kind });
+}
@@ -0,0 +1,52 @@
+import type { TextureAnimation } from "./types";
+
+// width and height live in the IHDR chunk right after the 8 byte png signature
+export function getPngSize(base64Png: string) {
+	const header = atob(base64Png.slice(0, 44));
+	const readUint32 = (offset: number) =>
+		((header.charCodeAt(offset) << 24) |
+			(header.charCodeAt(offset + 1) << 16) |
+			(header.charCodeAt(offset + 2) << 8) |
+			header.charCodeAt(offset + 3)) >>>
+		0;
+	return { width: readUint32(16), height: readUint32(20) };
+}
+
+// follows the vanilla .mcmeta rules: frames default to every square frame of the strip in
+// order, and frame entries may be bare indices or { index, time } objects
+export function parseTextureAnimation(
+	texture: string,
+	mcmeta: any,
+	imageSize: { width: number; height: number }
+): TextureAnimation | undefined {
+	const animation = mcmeta?.animation;
+	if (!animation) {
+		return undefined;
+	}
+	const frameWidth =
+		animation.width ?? Math.min(imageSize.width, imageSize.height);
+	const frameHeight = animation.height ?? frameWidth;
+	const framesInImage =
+		Math.floor(imageSize.width / frameWidth) *
+		Math.floor(imageSize.height / frameHeight);
+	const frameTime = animation.frametime ?? 1;
+	const frames = Array.isArray(animation.frames)
+		? animation.frames.map((frame: any) =>
+				typeof frame === "number"
+					? { index: frame, time: frameTime }
+					: { index: frame.index, time: frame.time ?? frameTime }
+		  )
+		: Array.from({ length: framesInImage }, (_, index) => ({
+				index,
+				time: frameTime,
+		  }));
+	return {
+		texture,
+		frameCount: frames.length,
+		frameTime,
+		interpolate: animation.interpolate ?? false,
+		frames,
+		frameWidth,
+		frameHeight,
+	};
+}
@@ -0,0 +1,134 @@
+import type { BlockMeshBuilder } from "./block_mesh_builder";
+import type { TextureColors } from "./types";
+
+// works on raw rgba texels so hosts that already decoded the pack can skip the browser
+// entirely, animated strips only count their first square frame
+export function computeTextureColors(
+	pixels: ArrayLike<number>,
+	width: number,
+	height: number
+): TextureColors {
+	const frameHeight = Math.min(width, height);
+	let r = 0;
+	let g = 0;
+	let b = 0;
+	let alpha = 0;
+	// 4 bits per channel is coarse enough for similar shades to land in one bucket
+	const buckets = new Map<number, number[]>();
+	for (let i = 0; i < width * frameHeight * 4; i += 4) {
+		const weight = pixels[i + 3] / 255;
+		if (weight === 0) {
+			continue;
+		}
+		r += pixels[i] * weight;
+		g += pixels[i + 1] * weight;
+		b += pixels[i + 2] * weight;
+		alpha += weight;
+		const bucketKey =
+			((pixels[i] >> 4) << 8) |
+			((pixels[i + 1] >> 4) << 4) |
+			(pixels[i + 2] >> 4);
+		const bucket = buckets.get(bucketKey) ?? [0, 0, 0, 0];
+		bucket[0] += pixels[i] * weight;
+		bucket[1] += pixels[i + 1] * weight;
+		bucket[2] += pixels[i + 2] * weight;
+		bucket[3] += weight;
+		buckets.set(bucketKey, bucket);
+	}
+	if (alpha === 0) {
+		return { average: [0, 0, 0, 0], dominant: [0, 0, 0] };
+	}
+	let dominant = [0, 0, 0, 0];
+	for (const bucket of buckets.values()) {
+		if (bucket[3] > dominant[3]) {
+			dominant = bucket;
+		}
+	}
+	return {
+		average: [
+			r / alpha / 255,
+			g / alpha / 255,
+			b / alpha / 255,
+			alpha / (width * frameHeight),
+		],
+		dominant: [
+			dominant[0] / dominant[3] / 255,
+			dominant[1] / dominant[3] / 255,
+			dominant[2] / dominant[3] / 255,
+		],
+	};
+}
+
+export function getImagePixels(image: any) {
+	const canvas =
+		typeof OffscreenCanvas !== "undefined"
+			? new OffscreenCanvas(image.width, image.height)
+			: Object.assign(document.createElement("canvas"), {
+					width: image.width,
+					height: image.height,
+			  });
+	const context = canvas.getContext("2d") as
+		| CanvasRenderingContext2D
+		| OffscreenCanvasRenderingContext2D
+		| null;
+	if (!context) {
+		return undefined;
+	}
+	context.drawImage(image, 0, 0);
+	return context.getImageData(0, 0, image.width, image.height).data;
+}
+
+function tintColors(colors: TextureColors, tint?: number[]): TextureColors {
+	if (!tint) {
+		return colors;
+	}
+	return {
+		average: [
+			colors.average[0] * tint[0],
+			colors.average[1] * tint[1],
+			colors.average[2] * tint[2],
+			colors.average[3],
+		],
+		dominant: colors.dominant.map((c, i) => c * tint[i]),
+	};
+}
+
+// per palette key and face, tinted the same way the face's material is
+export function getPaletteFaceColors(
+	blockMeshBuilder: BlockMeshBuilder,
+	textureColors: Map<string, TextureColors>
+) {
+	const faceColors: { [key: string]: { [face: string]: TextureColors } } = {};
+	for (const [key, blockComponents] of blockMeshBuilder.blockMeshCache) {
+		faceColors[key] = {};
+		for (const component of Object.values(blockComponents) as any[]) {
+			const descriptor = blockMeshBuilder.materialDescriptors.get(
+				component.materialId
+			);
+			const colors =
+				descriptor &&
+				textureColors.get(descriptor.texture.replace(/^minecraft:/, ""));
+			if (colors && !faceColors[key][component.face]) {
+				faceColors[key][component.face] = tintColors(colors, descriptor.color);
+			}
+		}
+	}
+	return faceColors;
+}
+
+// top face first since that is what a map or a far away chunk mostly shows
+export function createTextureColorMapping(faceColors: {
+	[key: string]: { [face: string]: TextureColors };
+}) {
+	const mapping: { [key: string]: number[] } = {};
+	for (const [key, faces] of Object.entries(faceColors)) {
+		const colors = faces.up ?? Object.values(faces)[0];
+		if (colors) {
+			mapping[key] = [
+				...colors.dominant.map((c) => Math.round(c * 255)),
+				Math.round(colors.average[3] * 255),
+			];
+		}
+	}
+	return mapping;
+}
@@ -0,0 +1,104 @@
+import * as THREE from "three";
+import type { TurntableCamera, TurntableOptions } from "./types";
+
+function getTurntableAngles(angles: number | number[]) {
+	if (Array.isArray(angles)) {
+		return angles;
+	}
+	return Array.from({ length: angles }, (_, i) => (i * 360) / angles);
+}
+
+// places a perspective camera on a circle around the box so the bounding sphere always fits
+// the frame, angles are in degrees around the vertical axis starting from +z
+export function computeTurntableCameras(
+	box: THREE.Box3,
+	angles: number | number[],
+	options: TurntableOptions = {}
+): TurntableCamera[] {
+	const fov = options.fov ?? 45;
+	const aspect = (options.width ?? 512) / (options.height ?? 512);
+	const elevation = THREE.MathUtils.degToRad(options.elevation ?? 30);
+	const sphere = box.getBoundingSphere(new THREE.Sphere());
+	const verticalHalfFov = THREE.MathUtils.degToRad(fov) / 2;
+	const horizontalHalfFov = Math.atan(Math.tan(verticalHalfFov) * aspect);
+	const distance =
+		(sphere.radius / Math.sin(Math.min(verticalHalfFov, horizontalHalfFov))) *
+		(options.margin ?? 1.05);
+	return getTurntableAngles(angles).map((angle) => {
+		const radians = THREE.MathUtils.degToRad(angle);
+		const position = new THREE.Vector3(
+			Math.sin(radians) * Math.cos(elevation),
+			Math.sin(elevation),
+			Math.cos(radians) * Math.cos(elevation)
+		)
+			.multiplyScalar(distance)
+			.add(sphere.center);
+		return {
+			angle,
+			position: position.toArray(),
+			target: sphere.center.toArray(),
+			up: [0, 1, 0],
+			fov,
+			aspect,
+			near: Math.max(distance - sphere.radius, 0.01) * 0.5,
+			far: (distance + sphere.radius) * 2,
+		};
+	});
+}
+
+export function createTurntableCamera(parameters: TurntableCamera) {
+	const camera = new THREE.PerspectiveCamera(
+		parameters.fov,
+		parameters.aspect,
+		parameters.near,
+		parameters.far
+	);
+	camera.position.fromArray(parameters.position);
+	camera.up.fromArray(parameters.up);
+	camera.lookAt(new THREE.Vector3().fromArray(parameters.target));
+	camera.updateProjectionMatrix();
+	return camera;
+}
+
+// gl reads bottom to top, the returned rows run top to bottom
+export function readTargetRows(
+	renderer: THREE.WebGLRenderer,
+	target: THREE.WebGLRenderTarget,
+	width: number,
+	height: number,
+	pixels = new Uint8Array(width * height * 4)
+) {
+	renderer.readRenderTargetPixels(target, 0, 0, width, height, pixels);
+	const rowSize = width * 4;
+	const rows = new Uint8ClampedArray(pixels.length);
+	for (let row = 0; row < height; row++) {
+		const start = (height - 1 - row) * rowSize;
+		rows.set(pixels.subarray(start, start + rowSize), row * rowSize);
+	}
+	return rows;
+}
+
+// renders into an offscreen target so it also runs in a worker with an OffscreenCanvas
+// backed renderer, frames are rgba rows top to bottom
+export function renderTurntableFrames(
+	renderer: THREE.WebGLRenderer,
+	scene: THREE.Scene,
+	cameras: TurntableCamera[],
+	options: TurntableOptions = {}
+) {
+	const width = options.width ?? 512;
+	const height = options.height ?? 512;
+	const target = new THREE.WebGLRenderTarget(width, height, {
+		samples: 4,
+	});
+	const previousTarget = renderer.getRenderTarget();
+	const pixels = new Uint8Array(width * height * 4);
+	const frames = cameras.map((parameters) => {
+		renderer.setRenderTarget(target);
+		renderer.render(scene, createTurntableCamera(parameters));
+		return readTargetRows(renderer, target, width, height, pixels);
+	});
+	renderer.setRenderTarget(previousTarget);
+	target.dispose();
+	return frames;
+}
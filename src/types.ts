@@ -64,6 +64,8 @@ export interface BlockModel {
 			rescale?: boolean;
 		};
 		shade?: boolean;
+		// non-vanilla extension, elements sharing a group get averaged normals
+		smoothing_group?: string;
 		faces?: {
 			[face in Faces]: {
 				uv?: [number, number, number, number];
@@ -87,3 +89,561 @@ export interface BlockModelOption {
 	name: string;
 	holders: BlockStateModelHolder[];
 }
+
+export type GeometryIssue =
+	| "nan"
+	| "inverted_normal"
+	| "out_of_bounds"
+	| "attribute_mismatch";
+
+export interface GeometryDiagnostic {
+	paletteKey: string;
+	blockName: string;
+	component: string;
+	issue: GeometryIssue;
+	message: string;
+}
+
+export type SchematicWarningType =
+	| "unknown_block"
+	| "missing_model"
+	| "missing_texture"
+	| "face_data_error"
+	| "slow_block"
+	| "invalid_geometry"
+	| "lightmap_overflow";
+
+export interface SchematicWarning {
+	type: SchematicWarningType;
+	message: string;
+	block?: string;
+	resource?: string;
+	details?: any;
+}
+
+export interface GroupProvenance {
+	blockCount: number;
+	// index follows the palette summary order
+	palette: { key: string; index: number; blockCount: number }[];
+}
+
+export interface MergeStats {
+	facesConsidered: number;
+	facesCulled: number;
+	quadsEmitted: number;
+	naiveVertexCount: number;
+	vertexCount: number;
+	vertexReduction: number;
+}
+
+// a component field name such as "colors", or a callback returning a comparable value
+export type GreedyMergeKey =
+	| string
+	| ((blockComponent: any, worldPos: number[]) => unknown);
+
+export interface MergeReport {
+	total: MergeStats;
+	byFace: { [face: string]: MergeStats };
+	byMaterial: { [materialId: string]: MergeStats };
+}
+
+export interface PhaseTimings {
+	min: number;
+	median: number;
+	max: number;
+}
+
+export interface BenchmarkResult {
+	mode: string;
+	iterations: number;
+	phases: { [phase: string]: PhaseTimings };
+}
+
+export interface MeshSnapshotGroup {
+	materialId: string;
+	vertexCount: number;
+	quadCount: number;
+	hashes: { [attribute: string]: string };
+}
+
+export interface MeshSnapshot {
+	version: number;
+	hash: string;
+	groups: MeshSnapshotGroup[];
+	// per material, hashes of the quads whose center falls in each "x,y,z" region
+	regions?: { [materialId: string]: { [region: string]: string } };
+	regionSize?: number;
+}
+
+export interface MeshSnapshotDiff {
+	identical: boolean;
+	groups: {
+		materialId: string;
+		status: "added" | "removed" | "changed";
+		quadCount: [number, number];
+		attributes: string[];
+	}[];
+	regions: {
+		materialId: string;
+		region: string;
+		status: "added" | "removed" | "changed";
+	}[];
+}
+
+export interface SchematicChunk {
+	key: bigint;
+	x: number;
+	y: number;
+	z: number;
+	// edge length in chunk dimensions for chunks merged into a larger region
+	scale?: number;
+	positions: any[];
+}
+
+export interface SchematicMeasurements {
+	blockCount: number;
+	dimensions: { x: number; y: number; z: number };
+	dimensionsInMeters: { x: number; y: number; z: number };
+	surfaceArea: number;
+	solidVolume: number;
+	enclosedVolume: number;
+}
+
+export interface MaterialUsageReport {
+	drawCalls: number;
+	triangleCount: number;
+	materials: {
+		[materialId: string]: { drawCalls: number; triangleCount: number };
+	};
+	unusedMaterials: string[];
+}
+
+export interface LightmapLayout {
+	atlasWidth: number;
+	atlasHeight: number;
+	texelsPerBlock: number;
+	padding?: number;
+}
+
+export type BlockCategory =
+	| "invisible"
+	| "transparent"
+	| "non_occluding"
+	| "opaque";
+
+export interface MaterialDescriptor {
+	texture: string;
+	transparent: boolean;
+	color?: number[];
+}
+
+export interface PaletteEntrySummary {
+	index: number;
+	key: string;
+	block: any;
+	category: BlockCategory;
+	occludesNeighbors: boolean;
+	transparent: boolean;
+	geometryCount: number;
+	vertexCount: number;
+	memoryBytes: number;
+}
+
+export interface TextureAnimation {
+	texture: string;
+	frameCount: number;
+	frameTime: number;
+	interpolate: boolean;
+	frames: { index: number; time: number }[];
+	frameWidth: number;
+	frameHeight: number;
+}
+
+export interface TextureColors {
+	// 0..1 srgb as stored in the texture, average alpha is the share of visible texels
+	average: number[];
+	dominant: number[];
+}
+
+export interface DespeckleOptions {
+	kernel?: "face" | "full";
+	removeIsolated?: boolean;
+	fillHoles?: boolean;
+	minNeighbors?: number;
+}
+
+export interface HighlightPredicate {
+	paletteIndices?: number[];
+	types?: string[];
+	properties?: { [key: string]: string };
+	color?: string | number;
+	opacity?: number;
+}
+
+export interface LightOverlayOptions {
+	spawnThreshold?: number;
+	showAllLevels?: boolean;
+	heightOffset?: number;
+	subBlockLight?: boolean;
+	// light level of cells outside the schematic, in schematic coordinates
+	neighborLight?: (x: number, y: number, z: number) => number;
+}
+
+export type FixtureKind =
+	| "checkerboard"
+	| "sphere"
+	| "palette_grid"
+	| "greedy_worst_case";
+
+export interface FixtureOptions {
+	size?: number;
+	// block state strings like "oak_stairs[facing=north]"
+	blocks?: string[];
+}
+
+export interface DebugMeshOptions {
+	occupancy?: boolean;
+	occlusion?: boolean;
+	dirtyRegions?: boolean;
+}
+
+export interface ContactShadowOptions {
+	texelsPerBlock?: number;
+	padding?: number;
+	blurRadius?: number;
+	heightFalloff?: number;
+	heightOffset?: number;
+	opacity?: number;
+	color?: string | number;
+}
+
+export interface TurntableOptions {
+	width?: number;
+	height?: number;
+	fov?: number;
+	elevation?: number;
+	margin?: number;
+}
+
+export interface TurntableCamera {
+	angle: number;
+	position: number[];
+	target: number[];
+	up: number[];
+	fov: number;
+	aspect: number;
+	near: number;
+	far: number;
+}
+
+export type ElevationView = "north" | "south" | "east" | "west" | "top";
+
+export interface ElevationOptions {
+	views?: ElevationView[];
+	pixelsPerBlock?: number;
+	// extra blocks around the bounding box
+	margin?: number;
+}
+
+export interface ElevationImage {
+	view: ElevationView;
+	width: number;
+	height: number;
+	color: Uint8ClampedArray;
+	depth: Float32Array;
+}
+
+export interface IsometricSpriteOptions {
+	tileSize?: number;
+	padding?: number;
+	columns?: number;
+}
+
+export interface SpriteFrame {
+	x: number;
+	y: number;
+	width: number;
+	height: number;
+}
+
+export interface BlockRotation {
+	x?: number;
+	y?: number;
+}
+
+export interface InstanceBufferOptions {
+	format?: "mat4" | "packed";
+	blockRotation?: (block: any, pos: any) => BlockRotation | undefined;
+}
+
+export interface PaletteInstanceBuffer {
+	paletteKey: string;
+	block: any;
+	format: "mat4" | "packed";
+	stride: number;
+	count: number;
+	// column major matrices, or x y z orientation per instance
+	data: Float32Array;
+}
+
+export type MaterialSortPreset =
+	| "opaque-first"
+	| "emissive-last"
+	| "opaque-first-emissive-last";
+
+export interface HeightTintOptions {
+	stops: { y: number; color: string | number | number[] }[];
+}
+
+export interface HeatmapOptions {
+	// keyed by "x,y,z" schematic coordinates, or looked up per block
+	field:
+		| { [position: string]: number }
+		| ((x: number, y: number, z: number) => number | undefined);
+	stops: { value: number; color: string | number | number[] }[];
+}
+
+export interface WaterVolume {
+	id: number;
+	blockCount: number;
+	boundingBox: import("three").Box3;
+	surfaceY: number;
+	containsPoint(point: { x: number; y: number; z: number }): boolean;
+}
+
+export interface BlockEntity {
+	id: string;
+	position: number[];
+	data: any;
+}
+
+export interface InventoryItem {
+	id: string;
+	count: number;
+}
+
+export interface InventoryPreviewOptions {
+	placement?: "above" | "front";
+	maxItems?: number;
+	itemSize?: number;
+	heightOffset?: number;
+}
+
+// offset in blocks, rotation in degrees around the pivot
+export interface BlockAnimationTransform {
+	offset?: number[];
+	rotation?: number[];
+	scale?: number[];
+}
+
+export interface BlockAnimation {
+	position: number[];
+	// defaults to the block at position, e.g. pass the open door state to swing it shut
+	block?: any;
+	// relative to the block's minimum corner
+	pivot?: number[];
+	start?: BlockAnimationTransform;
+	end: BlockAnimationTransform;
+}
+
+export interface ExclusionBox {
+	min: number[];
+	max: number[];
+}
+
+// a box or an explicit list of cells, both in schematic coordinates
+export type CutVolume = ExclusionBox | { cells: number[][] };
+
+export interface CutCapOptions {
+	color?: string | number;
+	opacity?: number;
+}
+
+export type AlphaMode = "opaque" | "mask" | "blend";
+
+export interface CategoryRenderSettings {
+	renderOrder?: number;
+	alphaMode?: AlphaMode;
+	needsSorting?: boolean;
+	depthWrite?: boolean;
+}
+
+export type PaletteCategory =
+	| "solid"
+	| "cutout"
+	| "translucent"
+	| "fluid"
+	| "decoration";
+
+// keyed by block type, or a callback for anything that depends on properties
+export type CategoryInferenceOverrides =
+	| { [type: string]: PaletteCategory }
+	| ((block: any) => PaletteCategory | undefined);
+
+export interface RenderMetadata {
+	category?: string;
+	renderOrder: number;
+	alphaMode: AlphaMode;
+	needsSorting: boolean;
+	depthWrite: boolean;
+}
+
+export interface PaletteBakeJob {
+	id: number;
+	total: number;
+	completed: number;
+	done: boolean;
+	cancelled: boolean;
+	promise: Promise<number>;
+	cancel(): void;
+	onComplete(callback: (job: PaletteBakeJob) => void): void;
+}
+
+export interface CategoryDistance {
+	fadeStart?: number;
+	fadeEnd?: number;
+	cullDistance?: number;
+	// blocks of the category are left out of builds above this lod level
+	maxLodLevel?: number;
+}
+
+export interface ChunkVisibility {
+	chunkKey: bigint;
+	// bit (a * 6 + b) is set when face a can see face b through the chunk
+	connectivity: bigint;
+	connections: [Faces, Faces][];
+}
+
+export interface NetworkChunkOptions {
+	// global block state registry of the server's protocol version, indexed by state id
+	blockStates: (
+		| { type: string; properties?: { [key: string]: string } }
+		| string
+	)[];
+	sectionCount?: number;
+	minY?: number;
+	// 1.21.5 dropped the length prefix in front of paletted container data arrays
+	dataArrayLengthPrefix?: boolean;
+}
+
+export interface VertexAttributeDescriptor {
+	name: string;
+	format: string;
+	offset: number;
+	shaderLocation: number;
+	components: number;
+	paddedComponents: number;
+}
+
+export interface VertexLayoutDescriptor {
+	arrayStride: number;
+	stepMode: "vertex";
+	attributes: VertexAttributeDescriptor[];
+	vertexCount: number;
+	indexFormat: "uint16" | "uint32";
+}
+
+export interface UploadSegmentOptions {
+	maxSegmentBytes?: number;
+	alignment?: number;
+	// matches the mode passed to getIndexBuffers
+	indexMode?: "triangles" | "strip" | "quads" | "none";
+}
+
+export interface UploadSegment {
+	buffer: string;
+	byteOffset: number;
+	byteLength: number;
+	// element offset and count, as bufferSubData's srcOffset and length take them
+	srcOffset: number;
+	length: number;
+}
+
+export interface VertexFormatRecommendation {
+	attribute: string;
+	format: string;
+	// multiply by scale before truncating to the integer format
+	scale?: number;
+	note: string;
+}
+
+export interface VertexFormatReport {
+	vertexCount: number;
+	indexFormat: "uint16" | "uint32";
+	attributes: VertexFormatRecommendation[];
+}
+
+export interface BatchedGeometryRange {
+	chunkKey: bigint;
+	vertexStart: number;
+	vertexCount: number;
+	indexStart: number;
+	indexCount: number;
+	matrix: number[];
+}
+
+export interface BatchedMaterialGroup {
+	materialId: string;
+	attributes: { [name: string]: { array: Float32Array; itemSize: number } };
+	// indices are local to each range, add vertexStart to address the shared pool
+	indices: Uint32Array;
+	ranges: BatchedGeometryRange[];
+}
+
+export type VoxelColorMapping =
+	| { [blockTypeOrKey: string]: number[] }
+	| ((block: any) => number[] | undefined);
+
+export interface CoordinateFrame {
+	offset: { x: number; y: number; z: number };
+	chunkDimensions: {
+		chunkWidth: number;
+		chunkHeight: number;
+		chunkLength: number;
+	};
+	// region key to edge length in chunks, for chunks merged by auto chunking
+	chunkScales?: Map<bigint, number>;
+	scale?: number;
+}
+
+export interface AutoChunkOptions {
+	sizes?: number[];
+	// how many remeshed blocks one extra draw call is worth
+	blocksPerDrawCall?: number;
+}
+
+export interface ChunkPartitionCandidate {
+	size: number;
+	chunkCount: number;
+	meanBlocks: number;
+	maxBlocks: number;
+	cost: number;
+}
+
+// a cube of whole chunks meshed as one, min and size are in blocks
+export interface ChunkRegion {
+	key: bigint;
+	min: number[];
+	size: number;
+	blockCount: number;
+}
+
+export interface ChunkPartition {
+	// the smallest chunk size, regions are multiples of it
+	chunkDimensions: {
+		chunkWidth: number;
+		chunkHeight: number;
+		chunkLength: number;
+	};
+	chunkCount: number;
+	meanBlocks: number;
+	maxBlocks: number;
+	candidates: ChunkPartitionCandidate[];
+	regions: ChunkRegion[];
+}
+
+export interface SimplifyOptions {
+	// in blocks, bounds both the size of dropped quads and the texture drift allowed
+	// when joining two
+	targetError?: number;
+}
@@ -0,0 +1,91 @@
+import * as THREE from "three";
+import { createIndexBuffer, IndexMode } from "./index_formats";
+import type { UploadSegment, UploadSegmentOptions } from "./types";
+
+const DEFAULT_MAX_SEGMENT_BYTES = 4 * 1024 * 1024;
+const DEFAULT_ALIGNMENT = 256;
+
+// strips carry their restart index after the 4 corners of every quad
+const INDEX_PRIMITIVE_SIZES: { [mode in IndexMode]: number } = {
+	triangles: 3,
+	strip: 5,
+	quads: 4,
+	none: 1,
+};
+
+function gcd(a: number, b: number): number {
+	return b === 0 ? a : gcd(b, a % b);
+}
+
+// boundaries land on whole vertices (whole primitives for indices) and on the
+// alignment, so every segment can be handed to bufferSubData as is
+export function splitUploadRanges(
+	buffer: string,
+	array: THREE.TypedArray,
+	itemSize: number,
+	options: UploadSegmentOptions = {}
+): UploadSegment[] {
+	const maxSegmentBytes = options.maxSegmentBytes ?? DEFAULT_MAX_SEGMENT_BYTES;
+	const alignment = options.alignment ?? DEFAULT_ALIGNMENT;
+	const elementBytes = array.BYTES_PER_ELEMENT;
+	const itemBytes = itemSize * elementBytes;
+	const unit = (itemBytes * alignment) / gcd(itemBytes, alignment);
+	const segmentBytes = Math.max(
+		unit,
+		Math.floor(maxSegmentBytes / unit) * unit
+	);
+
+	const segments: UploadSegment[] = [];
+	for (
+		let byteOffset = 0;
+		byteOffset < array.byteLength;
+		byteOffset += segmentBytes
+	) {
+		const byteLength = Math.min(segmentBytes, array.byteLength - byteOffset);
+		segments.push({
+			buffer,
+			byteOffset,
+			byteLength,
+			srcOffset: byteOffset / elementBytes,
+			length: byteLength / elementBytes,
+		});
+	}
+	return segments;
+}
+
+export function createUploadSegments(
+	geometry: THREE.BufferGeometry,
+	options: UploadSegmentOptions = {}
+) {
+	const segments: UploadSegment[] = [];
+	for (const name of Object.keys(geometry.attributes)) {
+		const attribute = geometry.getAttribute(name) as THREE.BufferAttribute;
+		segments.push(
+			...splitUploadRanges(name, attribute.array, attribute.itemSize, options)
+		);
+	}
+	// other index modes are uploaded in place of the triangle index the geometry holds
+	const indexMode = options.indexMode ?? "triangles";
+	if (indexMode !== "triangles") {
+		const position = geometry.getAttribute("position");
+		const { indices } = createIndexBuffer(
+			position ? position.count : 0,
+			indexMode
+		);
+		if (indices.length > 0) {
+			segments.push(
+				...splitUploadRanges(
+					"index",
+					indices,
+					INDEX_PRIMITIVE_SIZES[indexMode],
+					options
+				)
+			);
+		}
+	} else if (geometry.index) {
+		segments.push(
+			...splitUploadRanges("index", geometry.index.array, 3, options)
+		);
+	}
+	return segments;
+}
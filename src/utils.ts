@@ -1,6 +1,11 @@
 import { unzip } from "gzip-js";
 import { decode, TagMap } from "@enginehub/nbt-ts";
-import type { Faces, Vector } from "./types";
+import type {
+	BlockCategory,
+	Faces,
+	SchematicWarning,
+	Vector,
+} from "./types";
 import NonOccludingBlocks from "./nonOccluding.json";
 import TransparentBlocks from "./transparent.json";
 import { Buffer } from "buffer/";
@@ -289,6 +294,55 @@ export function normalize(input: number): number {
 	return input / 16;
 }
 
+const FACE_PLANES: { [face: string]: [number, number] } = {
+	east: [0, 16],
+	west: [0, 0],
+	up: [1, 16],
+	down: [1, 0],
+	south: [2, 16],
+	north: [2, 0],
+};
+
+// the plane a coordinate sits on in 1/16ths, undefined when it is off the model grid
+export function getFacePlaneKey(value: number) {
+	const key = value * 16;
+	return Number.isInteger(key) ? key : undefined;
+}
+
+// compares integer plane keys instead of floats, so models at half block offsets neither
+// get culled by accident nor keep faces that really are on the block boundary
+export function isFlushFace(face: string, corners: number[][]) {
+	const plane = FACE_PLANES[face];
+	if (!plane || corners.length === 0) {
+		return false;
+	}
+	return corners.every(
+		(corner) => getFacePlaneKey(corner[plane[0]]) === plane[1]
+	);
+}
+
+// gives the browser a chance to render and handle input, scheduler.yield keeps the
+// continuation ahead of other queued tasks where it is supported
+export function yieldToMain(): Promise<void> {
+	const scheduler = (globalThis as any).scheduler;
+	if (typeof scheduler?.yield === "function") {
+		return scheduler.yield();
+	}
+	if (typeof MessageChannel !== "undefined") {
+		return new Promise((resolve) => {
+			const channel = new MessageChannel();
+			channel.port1.onmessage = () => resolve();
+			channel.port2.postMessage(undefined);
+		});
+	}
+	return new Promise((resolve) => setTimeout(resolve, 0));
+}
+
+export function isQuarterTurn(angle: number) {
+	const turns = angle / (Math.PI / 2);
+	return Math.abs(turns - Math.round(turns)) < 1e-9;
+}
+
 export function faceToRotation(face: string) {
 	switch (face) {
 		case "north":
@@ -386,6 +440,138 @@ export function isExtendedPiston(block: Block) {
 	);
 }
 
+const CLOCKWISE_FACES: { [face: string]: string } = {
+	north: "east",
+	east: "south",
+	south: "west",
+	west: "north",
+};
+
+const COUNTER_CLOCKWISE_FACES: { [face: string]: string } = {
+	north: "west",
+	west: "south",
+	south: "east",
+	east: "north",
+};
+
+// face of the block pointing at the other half of a two-block structure
+// (doors, tall plants, beds and double chests), undefined for single blocks
+export function getPairedBlockFace(block: Block): string | undefined {
+	const properties = block.properties ?? {};
+	const facing = properties["facing"];
+	if (properties["half"] === "lower") {
+		return "up";
+	}
+	if (properties["half"] === "upper") {
+		return "down";
+	}
+	if (block.type.endsWith("_bed") && facing) {
+		return properties["part"] === "foot" ? facing : getOppositeFace(facing);
+	}
+	if (block.type.endsWith("chest") && facing) {
+		if (properties["type"] === "left") {
+			return CLOCKWISE_FACES[facing];
+		}
+		if (properties["type"] === "right") {
+			return COUNTER_CLOCKWISE_FACES[facing];
+		}
+	}
+	return undefined;
+}
+
+const PARTIAL_HEIGHT_BLOCKS: { [type: string]: number } = {
+	farmland: 15 / 16,
+	dirt_path: 15 / 16,
+	grass_path: 15 / 16,
+};
+
+// height of blocks that fill the bottom of their cell with a full footprint, undefined otherwise
+export function getPartialBlockHeight(block: Block): number | undefined {
+	if (block.type === "snow") {
+		return (Number(block.properties?.["layers"] ?? 1) * 2) / 16;
+	}
+	return PARTIAL_HEIGHT_BLOCKS[block.type];
+}
+
+// blocks whose side texture switches to the snowy variant when snow lies on top of them
+export const SNOWY_BLOCKS = new Set(["grass_block", "mycelium", "podzol"]);
+
+export const SNOW_COVER_BLOCKS = new Set(["snow", "snow_block", "powder_snow"]);
+
+export const HORIZONTAL_FACES = ["north", "south", "west", "east"] as const;
+
+// only the plain rail can curve, the others stay straight
+export const RAIL_BLOCKS = new Set([
+	"rail",
+	"powered_rail",
+	"detector_rail",
+	"activator_rail",
+]);
+
+// follows the vanilla placement rules: a straight line wins over a curve, and a straight
+// rail ascends toward a connected rail one block higher
+export function getRailShape(
+	type: string,
+	connections: { [face: string]: "flat" | "up" | undefined }
+) {
+	const has = (face: string) => connections[face] !== undefined;
+	let axis: [string, string] | undefined;
+	if (has("north") && has("south")) {
+		axis = ["north", "south"];
+	} else if (has("east") && has("west")) {
+		axis = ["east", "west"];
+	} else if (type === "rail") {
+		const ns = ["south", "north"].find(has);
+		const ew = ["east", "west"].find(has);
+		if (ns && ew) {
+			return `${ns}_${ew}`;
+		}
+	}
+	if (!axis) {
+		const single = HORIZONTAL_FACES.find(has);
+		if (!single) {
+			return undefined;
+		}
+		axis =
+			single === "north" || single === "south"
+				? ["north", "south"]
+				: ["east", "west"];
+	}
+	const ascending = axis.find((face) => connections[face] === "up");
+	return ascending ? `ascending_${ascending}` : `${axis[0]}_${axis[1]}`;
+}
+
+export function isWallAttachedBlock(type: string) {
+	return (
+		type === "ladder" ||
+		type === "tripwire_hook" ||
+		type.endsWith("wall_torch") ||
+		type.endsWith("_wall_sign") ||
+		type.endsWith("_wall_hanging_sign") ||
+		type.endsWith("_wall_banner")
+	);
+}
+
+// the vanilla chest shape is inset a pixel from every side, the halves of a pair are
+// stretched over that gap so they close into one box once the shared face is culled
+const PAIR_JOIN_DISTANCE = 1 / 16 + 1e-6;
+
+// expects normalized coordinates and the shared face in model space
+export function joinPairedElement(
+	element: { from?: number[]; to?: number[]; rotation?: unknown },
+	face: string
+) {
+	if (!element.from || !element.to || element.rotation) {
+		return;
+	}
+	const [axis, plane] = FACE_PLANES[face];
+	const boundary = plane / 16;
+	const coords = boundary > 0 ? element.to : element.from;
+	if (Math.abs(coords[axis] - boundary) <= PAIR_JOIN_DISTANCE) {
+		coords[axis] = boundary;
+	}
+}
+
 export const INVISIBLE_BLOCKS = new Set([
 	"air",
 	"cave_air",
@@ -415,6 +601,110 @@ export const NON_OCCLUDING_BLOCKS = new Set([
 //	return data.value as TagMap;
 //}
 
+export function getBlockCategory(type: string): BlockCategory {
+	if (INVISIBLE_BLOCKS.has(type)) {
+		return "invisible";
+	}
+	if (TRANSPARENT_BLOCKS.has(type)) {
+		return "transparent";
+	}
+	if (NON_OCCLUDING_BLOCKS.has(type)) {
+		return "non_occluding";
+	}
+	return "opaque";
+}
+
+const FOLIAGE_BLOCKS = new Set([
+	"grass",
+	"short_grass",
+	"tall_grass",
+	"fern",
+	"large_fern",
+	"dead_bush",
+	"seagrass",
+	"tall_seagrass",
+	"kelp",
+	"kelp_plant",
+	"vine",
+	"glow_lichen",
+	"hanging_roots",
+	"small_dripleaf",
+	"sweet_berry_bush",
+	"nether_sprouts",
+	"crimson_roots",
+	"warped_roots",
+	"twisting_vines",
+	"twisting_vines_plant",
+	"weeping_vines",
+	"weeping_vines_plant",
+	"dandelion",
+	"poppy",
+	"blue_orchid",
+	"allium",
+	"azure_bluet",
+	"red_tulip",
+	"orange_tulip",
+	"white_tulip",
+	"pink_tulip",
+	"oxeye_daisy",
+	"cornflower",
+	"lily_of_the_valley",
+	"wither_rose",
+	"sunflower",
+	"lilac",
+	"rose_bush",
+	"peony",
+	"pink_petals",
+	"lily_pad",
+]);
+
+const DECORATION_BLOCKS = new Set([
+	"torch",
+	"wall_torch",
+	"soul_torch",
+	"soul_wall_torch",
+	"redstone_torch",
+	"redstone_wall_torch",
+	"redstone_wire",
+	"lever",
+	"tripwire",
+	"tripwire_hook",
+	"flower_pot",
+	"candle",
+	"sea_pickle",
+	"cobweb",
+	"snow",
+	"rail",
+	"powered_rail",
+	"detector_rail",
+	"activator_rail",
+	"ladder",
+]);
+
+// coarse groups for distance based fading and culling, host overrides win
+export function getRenderCategory(
+	type: string,
+	overrides: { [type: string]: string } = {}
+): string | undefined {
+	if (overrides[type]) {
+		return overrides[type];
+	}
+	if (FOLIAGE_BLOCKS.has(type)) {
+		return "foliage";
+	}
+	if (
+		DECORATION_BLOCKS.has(type) ||
+		type.endsWith("_button") ||
+		type.endsWith("_carpet") ||
+		type.endsWith("_pressure_plate") ||
+		type.endsWith("_candle") ||
+		type.endsWith("_banner")
+	) {
+		return "decoration";
+	}
+	return undefined;
+}
+
 export function parseNbt(nbt: Buffer): TagMap {
 	let uncompressed;
 	try {
@@ -456,6 +746,32 @@ export function occludedFacesIntToList(occludedFaces: number) {
 	return result;
 }
 
+export function isOpaqueBlock(block: Block | undefined) {
+	return (
+		block !== undefined &&
+		!NON_OCCLUDING_BLOCKS.has(block.type) &&
+		!TRANSPARENT_BLOCKS.has(block.type)
+	);
+}
+
+// true when every face-adjacent neighbour is opaque, so nothing of the block can ever be seen
+export function isBuriedBlock(
+	schematic: any,
+	pos: { x: number; y: number; z: number }
+) {
+	const { x, y, z } = pos;
+	return [
+		[x + 1, y, z],
+		[x - 1, y, z],
+		[x, y + 1, z],
+		[x, y - 1, z],
+		[x, y, z + 1],
+		[x, y, z - 1],
+	].every(([nx, ny, nz]) =>
+		isOpaqueBlock(schematic.getBlock(new THREE.Vector3(nx, ny, nz)))
+	);
+}
+
 export function getOppositeFace(face: string): string {
 	switch (face) {
 		case "north":
@@ -474,3 +790,84 @@ export function getOppositeFace(face: string): string {
 			return "north";
 	}
 }
+
+// returns false when no callback is registered so callers can fall back to their old behaviour
+export function emitWarning(options: any, warning: SchematicWarning) {
+	if (typeof options?.onWarning !== "function") {
+		return false;
+	}
+	options.onWarning(warning);
+	return true;
+}
+
+// shrinks every quad's uv rectangle towards its center so samples never reach the neighbouring texels
+export function insetComponentUvs(blockComponent: any, inset: number) {
+	const uvs = blockComponent.uvs.slice();
+	for (let i = 0; i < uvs.length; i += 8) {
+		for (let axis = 0; axis < 2; axis++) {
+			let min = Infinity;
+			let max = -Infinity;
+			for (let j = i + axis; j < i + 8; j += 2) {
+				min = Math.min(min, uvs[j]);
+				max = Math.max(max, uvs[j]);
+			}
+			const amount = Math.min(inset, (max - min) / 2);
+			for (let j = i + axis; j < i + 8; j += 2) {
+				if (uvs[j] === min) {
+					uvs[j] += amount;
+				} else if (uvs[j] === max) {
+					uvs[j] -= amount;
+				}
+			}
+		}
+	}
+	return { ...blockComponent, uvs };
+}
+
+const CHUNK_KEY_BITS = 21n;
+const CHUNK_KEY_MASK = (1n << CHUNK_KEY_BITS) - 1n;
+const CHUNK_KEY_SIGN = 1n << (CHUNK_KEY_BITS - 1n);
+
+// packs signed chunk coordinates (±2^20 chunks per axis) into a single 64-bit key
+export function getChunkKey(chunkX: number, chunkY: number, chunkZ: number) {
+	return (
+		(BigInt(chunkX) & CHUNK_KEY_MASK) |
+		((BigInt(chunkY) & CHUNK_KEY_MASK) << CHUNK_KEY_BITS) |
+		((BigInt(chunkZ) & CHUNK_KEY_MASK) << (CHUNK_KEY_BITS * 2n))
+	);
+}
+
+export function unpackChunkKey(key: bigint) {
+	const unpack = (shift: bigint) => {
+		const value = (key >> shift) & CHUNK_KEY_MASK;
+		return Number(
+			value & CHUNK_KEY_SIGN ? value - (1n << CHUNK_KEY_BITS) : value
+		);
+	};
+	return {
+		x: unpack(0n),
+		y: unpack(CHUNK_KEY_BITS),
+		z: unpack(CHUNK_KEY_BITS * 2n),
+	};
+}
+
+// a full block with every face using the whole texture, for blocks that have no model
+export function createCubeComponents(materialId: string) {
+	const faceUvs: { [face: string]: number[] } = {};
+	for (const face of POSSIBLE_FACES) {
+		faceUvs[face] = [0, 0, 1, 1];
+	}
+	const directionData = getDirectionData(faceUvs);
+	const components: { [key: string]: any } = {};
+	for (const face of POSSIBLE_FACES) {
+		const { normal, corners } = directionData[face];
+		components[`${materialId}-${face}`] = {
+			materialId,
+			face,
+			positions: corners.flatMap(({ pos }: any) => pos),
+			normals: corners.flatMap(() => normal),
+			uvs: corners.flatMap(({ uv }: any) => [1 - uv[0], 1 - uv[1]]),
+		};
+	}
+	return components;
+}
@@ -0,0 +1,284 @@
+import * as THREE from "three";
+import type { HeatmapOptions, HeightTintOptions } from "./types";
+
+export type VoxelOccupancy = (x: number, y: number, z: number) => boolean;
+
+// amanatides & woo voxel traversal, returns the distance to the first solid voxel hit
+export function traceVoxelRay(
+	isSolid: VoxelOccupancy,
+	origin: number[],
+	direction: number[],
+	maxDistance: number
+) {
+	const voxel = origin.map(Math.floor);
+	const step = direction.map(Math.sign);
+	const tDelta = direction.map((d) => (d === 0 ? Infinity : Math.abs(1 / d)));
+	const tMax = direction.map((d, i) => {
+		if (d === 0) {
+			return Infinity;
+		}
+		const boundary = d > 0 ? voxel[i] + 1 : voxel[i];
+		return (boundary - origin[i]) / d;
+	});
+	let distance = 0;
+	while (distance <= maxDistance) {
+		let axis = tMax[0] < tMax[1] ? 0 : 1;
+		if (tMax[2] < tMax[axis]) {
+			axis = 2;
+		}
+		distance = tMax[axis];
+		voxel[axis] += step[axis];
+		tMax[axis] += tDelta[axis];
+		if (distance <= maxDistance && isSolid(voxel[0], voxel[1], voxel[2])) {
+			return distance;
+		}
+	}
+	return Infinity;
+}
+
+// fixed spiral so repeated builds bake identical values
+function getCosineSamples(count: number) {
+	const samples: THREE.Vector3[] = [];
+	const goldenAngle = Math.PI * (3 - Math.sqrt(5));
+	for (let i = 0; i < count; i++) {
+		const r = Math.sqrt((i + 0.5) / count);
+		const phi = i * goldenAngle;
+		samples.push(
+			new THREE.Vector3(
+				r * Math.cos(phi),
+				r * Math.sin(phi),
+				Math.sqrt(1 - r * r)
+			)
+		);
+	}
+	return samples;
+}
+
+// multiplies into an existing color attribute so bakes stack with each other and with
+// color only meshes, factors hold either one value or rgb per vertex
+export function multiplyVertexColors(
+	geometry: THREE.BufferGeometry,
+	factors: Float32Array
+) {
+	const count = geometry.getAttribute("position").count;
+	let colors = geometry.getAttribute("color") as THREE.BufferAttribute;
+	if (!colors) {
+		colors = new THREE.BufferAttribute(new Float32Array(count * 3).fill(1), 3);
+		geometry.setAttribute("color", colors);
+	}
+	const rgb = factors.length === count * 3;
+	for (let i = 0; i < count; i++) {
+		const r = rgb ? factors[i * 3] : factors[i];
+		const g = rgb ? factors[i * 3 + 1] : factors[i];
+		const b = rgb ? factors[i * 3 + 2] : factors[i];
+		colors.setXYZ(
+			i,
+			colors.getX(i) * r,
+			colors.getY(i) * g,
+			colors.getZ(i) * b
+		);
+	}
+	colors.needsUpdate = true;
+}
+
+// vertex positions are shifted by `origin` to land in voxel coordinates
+export function bakeRayTracedAo(
+	geometry: THREE.BufferGeometry,
+	isSolid: VoxelOccupancy,
+	origin: number[],
+	options: { samples?: number; maxDistance?: number; strength?: number } = {}
+) {
+	const maxDistance = options.maxDistance ?? 4;
+	const strength = options.strength ?? 0.8;
+	const samples = getCosineSamples(options.samples ?? 16);
+	const positions = geometry.getAttribute("position");
+	const normals = geometry.getAttribute("normal");
+	const factors = new Float32Array(positions.count);
+	const normal = new THREE.Vector3();
+	const tangent = new THREE.Vector3();
+	const bitangent = new THREE.Vector3();
+	const direction = new THREE.Vector3();
+	// vertices sharing a position and normal always get the same value
+	const cache = new Map<string, number>();
+	for (let i = 0; i < positions.count; i++) {
+		normal.fromBufferAttribute(normals, i).normalize();
+		const start = [
+			positions.getX(i) - origin[0] + normal.x * 1e-3,
+			positions.getY(i) - origin[1] + normal.y * 1e-3,
+			positions.getZ(i) - origin[2] + normal.z * 1e-3,
+		];
+		const key = `${start.join(",")}|${normal.toArray().join(",")}`;
+		if (cache.has(key)) {
+			factors[i] = cache.get(key)!;
+			continue;
+		}
+		const alongX = Math.abs(normal.x) > 0.9;
+		tangent
+			.set(alongX ? 0 : 1, alongX ? 1 : 0, 0)
+			.cross(normal)
+			.normalize();
+		bitangent.crossVectors(normal, tangent);
+		let occlusion = 0;
+		for (const sample of samples) {
+			direction
+				.copy(tangent)
+				.multiplyScalar(sample.x)
+				.addScaledVector(bitangent, sample.y)
+				.addScaledVector(normal, sample.z);
+			const hit = traceVoxelRay(
+				isSolid,
+				start,
+				direction.toArray(),
+				maxDistance
+			);
+			if (hit !== Infinity) {
+				occlusion += 1 - hit / maxDistance;
+			}
+		}
+		factors[i] = 1 - strength * (occlusion / samples.length);
+		cache.set(key, factors[i]);
+	}
+	multiplyVertexColors(geometry, factors);
+	return factors;
+}
+
+// a single ray per vertex toward the sun, faces turned away from it count as shadowed too
+export function bakeSunShadows(
+	geometry: THREE.BufferGeometry,
+	isSolid: VoxelOccupancy,
+	origin: number[],
+	options: {
+		direction?: number[];
+		maxDistance?: number;
+		shadowStrength?: number;
+	} = {}
+) {
+	const sun = new THREE.Vector3(...(options.direction ?? [0.4, 1, 0.3]))
+		.normalize()
+		.toArray();
+	const maxDistance = options.maxDistance ?? 64;
+	const shadow = 1 - (options.shadowStrength ?? 0.5);
+	const positions = geometry.getAttribute("position");
+	const normals = geometry.getAttribute("normal");
+	const factors = new Float32Array(positions.count);
+	const normal = new THREE.Vector3();
+	for (let i = 0; i < positions.count; i++) {
+		normal.fromBufferAttribute(normals, i).normalize();
+		if (normal.x * sun[0] + normal.y * sun[1] + normal.z * sun[2] <= 0) {
+			factors[i] = shadow;
+			continue;
+		}
+		const start = [
+			positions.getX(i) - origin[0] + normal.x * 1e-3,
+			positions.getY(i) - origin[1] + normal.y * 1e-3,
+			positions.getZ(i) - origin[2] + normal.z * 1e-3,
+		];
+		const hit = traceVoxelRay(isSolid, start, sun, maxDistance);
+		factors[i] = hit === Infinity ? 1 : shadow;
+	}
+	multiplyVertexColors(geometry, factors);
+	return factors;
+}
+
+function sampleGradient(stops: { y: number; color: THREE.Color }[], y: number) {
+	if (y <= stops[0].y) {
+		return stops[0].color;
+	}
+	for (let i = 1; i < stops.length; i++) {
+		if (y <= stops[i].y) {
+			const t = (y - stops[i - 1].y) / (stops[i].y - stops[i - 1].y);
+			return stops[i - 1].color.clone().lerp(stops[i].color, t);
+		}
+	}
+	return stops[stops.length - 1].color;
+}
+
+function parseGradientStops(
+	stops: { color: string | number | number[] }[],
+	getKey: (stop: any) => number
+) {
+	return stops
+		.map((stop) => ({
+			y: getKey(stop),
+			color: Array.isArray(stop.color)
+				? new THREE.Color(stop.color[0], stop.color[1], stop.color[2])
+				: new THREE.Color(stop.color),
+		}))
+		.sort((a, b) => a.y - b.y);
+}
+
+// y is measured in schematic blocks, stops are sorted and colors clamp past either end
+export function bakeHeightTint(
+	geometry: THREE.BufferGeometry,
+	origin: number[],
+	options: HeightTintOptions
+) {
+	const stops = parseGradientStops(options.stops, (stop) => stop.y);
+	if (stops.length === 0) {
+		return undefined;
+	}
+	const positions = geometry.getAttribute("position");
+	const factors = new Float32Array(positions.count * 3);
+	for (let i = 0; i < positions.count; i++) {
+		const color = sampleGradient(stops, positions.getY(i) - origin[1]);
+		factors[i * 3] = color.r;
+		factors[i * 3 + 1] = color.g;
+		factors[i * 3 + 2] = color.b;
+	}
+	multiplyVertexColors(geometry, factors);
+	return factors;
+}
+
+function getHeatmapValue(field: HeatmapOptions["field"], cell: number[]) {
+	if (typeof field === "function") {
+		return field(cell[0], cell[1], cell[2]);
+	}
+	return field[cell.join(",")];
+}
+
+// every vertex samples the block it belongs to, found by stepping from the corner
+// into the face and back against the normal, blocks without a value stay untinted
+export function bakeHeatmap(
+	geometry: THREE.BufferGeometry,
+	origin: number[],
+	options: HeatmapOptions
+) {
+	const stops = parseGradientStops(options.stops, (stop) => stop.value);
+	if (stops.length === 0) {
+		return undefined;
+	}
+	const positions = geometry.getAttribute("position");
+	const normals = geometry.getAttribute("normal");
+	const factors = new Float32Array(positions.count * 3).fill(1);
+	const center = new THREE.Vector3();
+	const point = new THREE.Vector3();
+	const normal = new THREE.Vector3();
+	for (let quad = 0; quad + 3 < positions.count; quad += 4) {
+		center.set(0, 0, 0);
+		for (let i = quad; i < quad + 4; i++) {
+			center.add(point.fromBufferAttribute(positions, i));
+		}
+		center.multiplyScalar(0.25);
+		for (let i = quad; i < quad + 4; i++) {
+			normal.fromBufferAttribute(normals, i);
+			point
+				.fromBufferAttribute(positions, i)
+				.lerp(center, 1e-3 / Math.max(point.distanceTo(center), 1e-3))
+				.addScaledVector(normal, -1e-3);
+			const value = getHeatmapValue(options.field, [
+				Math.floor(point.x - origin[0]),
+				Math.floor(point.y - origin[1]),
+				Math.floor(point.z - origin[2]),
+			]);
+			if (value === undefined) {
+				continue;
+			}
+			const color = sampleGradient(stops, value);
+			factors[i * 3] = color.r;
+			factors[i * 3 + 1] = color.g;
+			factors[i * 3 + 2] = color.b;
+		}
+	}
+	multiplyVertexColors(geometry, factors);
+	return factors;
+}
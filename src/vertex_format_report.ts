@@ -0,0 +1,175 @@
+import * as THREE from "three";
+import type {
+	VertexFormatRecommendation,
+	VertexFormatReport,
+} from "./types";
+
+const EPSILON = 1e-5;
+const MAX_FIXED_POINT_SCALE = 4096;
+
+function collectAttribute(meshes: THREE.Mesh[], name: string) {
+	const arrays: ArrayLike<number>[] = [];
+	for (const mesh of meshes) {
+		const attribute = mesh.geometry.getAttribute(name);
+		if (attribute) {
+			arrays.push(attribute.array);
+		}
+	}
+	return arrays;
+}
+
+function isExactAt(arrays: ArrayLike<number>[], scale: number) {
+	for (const array of arrays) {
+		for (let i = 0; i < array.length; i++) {
+			const scaled = array[i] * scale;
+			if (Math.abs(scaled - Math.round(scaled)) > EPSILON * scale) {
+				return false;
+			}
+		}
+	}
+	return true;
+}
+
+function getMaxMagnitude(arrays: ArrayLike<number>[]) {
+	let max = 0;
+	for (const array of arrays) {
+		for (let i = 0; i < array.length; i++) {
+			max = Math.max(max, Math.abs(array[i]));
+		}
+	}
+	return max;
+}
+
+// the smallest power of two that turns every value into an integer
+function findExactScale(arrays: ArrayLike<number>[]) {
+	for (let scale = 1; scale <= MAX_FIXED_POINT_SCALE; scale *= 2) {
+		if (isExactAt(arrays, scale)) {
+			return scale;
+		}
+	}
+	return undefined;
+}
+
+function recommendFixedPoint(
+	attribute: string,
+	arrays: ArrayLike<number>[]
+): VertexFormatRecommendation {
+	const scale = findExactScale(arrays);
+	const max = getMaxMagnitude(arrays);
+	if (scale !== undefined && max * scale <= 0x7fff) {
+		return {
+			attribute,
+			format: "sint16",
+			scale,
+			note: `${attribute} fits in i16 at scale ${scale}`,
+		};
+	}
+	return {
+		attribute,
+		format: "float32",
+		note:
+			scale === undefined
+				? `${attribute} has values off a 1/${MAX_FIXED_POINT_SCALE} grid`
+				: `${attribute} exceeds the i16 range at scale ${scale}`,
+	};
+}
+
+function recommendNormal(
+	arrays: ArrayLike<number>[]
+): VertexFormatRecommendation {
+	let axisAligned = true;
+	for (const array of arrays) {
+		for (let i = 0; i < array.length; i += 3) {
+			const nonZero =
+				Number(Math.abs(array[i]) > EPSILON) +
+				Number(Math.abs(array[i + 1]) > EPSILON) +
+				Number(Math.abs(array[i + 2]) > EPSILON);
+			if (nonZero !== 1) {
+				axisAligned = false;
+				break;
+			}
+		}
+	}
+	if (axisAligned) {
+		return {
+			attribute: "normal",
+			format: "faceId",
+			note: "normals are axis-aligned only, a face id can replace them",
+		};
+	}
+	if (isExactAt(arrays, 127)) {
+		return {
+			attribute: "normal",
+			format: "snorm8x4",
+			scale: 127,
+			note: "normals are exact in snorm8",
+		};
+	}
+	return {
+		attribute: "normal",
+		format: "float32x3",
+		note: "normals need full precision",
+	};
+}
+
+function recommendColor(
+	arrays: ArrayLike<number>[]
+): VertexFormatRecommendation {
+	// tints brighter than white don't fit a normalized format at all
+	const normalized = getMaxMagnitude(arrays) <= 1;
+	if (normalized && isExactAt(arrays, 255)) {
+		return {
+			attribute: "color",
+			format: "unorm8x4",
+			scale: 255,
+			note: "colors are exact in unorm8",
+		};
+	}
+	if (normalized && isExactAt(arrays, 65535)) {
+		return {
+			attribute: "color",
+			format: "unorm16x4",
+			scale: 65535,
+			note: "colors need more than 8 bits per channel",
+		};
+	}
+	return {
+		attribute: "color",
+		format: "float32x4",
+		note: "colors need full precision",
+	};
+}
+
+// only recommends formats that reproduce the current buffers exactly
+export function analyzeVertexFormats(meshes: THREE.Mesh[]): VertexFormatReport {
+	const counts = meshes.map(
+		(mesh) => mesh.geometry.getAttribute("position")?.count ?? 0
+	);
+	const vertexCount = counts.reduce((sum, count) => sum + count, 0);
+	const largestMesh = Math.max(0, ...counts);
+	const attributes: VertexFormatRecommendation[] = [];
+	const names = new Set(
+		meshes.flatMap((mesh) => Object.keys(mesh.geometry.attributes))
+	);
+	for (const name of names) {
+		const arrays = collectAttribute(meshes, name);
+		if (name === "normal") {
+			attributes.push(recommendNormal(arrays));
+		} else if (name === "color") {
+			attributes.push(recommendColor(arrays));
+		} else if (name === "faceId") {
+			attributes.push({
+				attribute: name,
+				format: "uint8",
+				note: "face ids already use a single byte",
+			});
+		} else {
+			attributes.push(recommendFixedPoint(name, arrays));
+		}
+	}
+	return {
+		vertexCount,
+		indexFormat: largestMesh < 0xffff ? "uint16" : "uint32",
+		attributes,
+	};
+}
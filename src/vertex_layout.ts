@@ -0,0 +1,116 @@
+import * as THREE from "three";
+import type {
+	VertexAttributeDescriptor,
+	VertexLayoutDescriptor,
+} from "./types";
+
+const ATTRIBUTE_ORDER = ["position", "normal", "uv", "uv1", "color"];
+
+function getScalarFormat(array: THREE.TypedArray, normalized: boolean) {
+	if (array instanceof Float32Array) {
+		return { format: "float32", bytes: 4, setter: "setFloat32" };
+	}
+	if (array instanceof Int16Array) {
+		const format = normalized ? "snorm16" : "sint16";
+		return { format, bytes: 2, setter: "setInt16" };
+	}
+	if (array instanceof Uint16Array) {
+		const format = normalized ? "unorm16" : "uint16";
+		return { format, bytes: 2, setter: "setUint16" };
+	}
+	if (array instanceof Int8Array) {
+		const format = normalized ? "snorm8" : "sint8";
+		return { format, bytes: 1, setter: "setInt8" };
+	}
+	if (array instanceof Uint8Array) {
+		const format = normalized ? "unorm8" : "uint8";
+		return { format, bytes: 1, setter: "setUint8" };
+	}
+	if (array instanceof Int32Array) {
+		return { format: "sint32", bytes: 4, setter: "setInt32" };
+	}
+	return { format: "uint32", bytes: 4, setter: "setUint32" };
+}
+
+// webgpu has no 3 component formats below 32 bits and wants every attribute offset and
+// the stride on a 4 byte boundary, so small attributes are widened to x2/x4
+function getPaddedComponents(components: number, bytes: number, pad: boolean) {
+	if (bytes === 4) {
+		return components;
+	}
+	if (components === 3) {
+		return 4;
+	}
+	if (pad && components * bytes < 4) {
+		return 4 / bytes;
+	}
+	return components;
+}
+
+export function describeVertexLayout(
+	geometry: THREE.BufferGeometry,
+	options: { padAttributes?: boolean } = {}
+): VertexLayoutDescriptor {
+	const pad = options.padAttributes ?? true;
+	const attributes: VertexAttributeDescriptor[] = [];
+	let offset = 0;
+	const names = Object.keys(geometry.attributes).sort(
+		(a, b) =>
+			(ATTRIBUTE_ORDER.indexOf(a) + 1 || 99) -
+			(ATTRIBUTE_ORDER.indexOf(b) + 1 || 99)
+	);
+	for (const name of names) {
+		const attribute = geometry.getAttribute(name) as THREE.BufferAttribute;
+		const { format, bytes } = getScalarFormat(
+			attribute.array,
+			attribute.normalized
+		);
+		const components = attribute.itemSize;
+		const paddedComponents = getPaddedComponents(components, bytes, pad);
+		attributes.push({
+			name,
+			format: paddedComponents > 1 ? `${format}x${paddedComponents}` : format,
+			offset,
+			shaderLocation: attributes.length,
+			components,
+			paddedComponents,
+		});
+		offset += paddedComponents * bytes;
+		if (pad) {
+			offset = Math.ceil(offset / 4) * 4;
+		}
+	}
+	const vertexCount = geometry.getAttribute("position")?.count ?? 0;
+	return {
+		arrayStride: offset,
+		stepMode: "vertex",
+		attributes,
+		vertexCount,
+		indexFormat: vertexCount >= 0xffff ? "uint32" : "uint16",
+	};
+}
+
+// interleaves the geometry exactly as described, padding components are left at zero
+export function packInterleavedVertices(
+	geometry: THREE.BufferGeometry,
+	layout: VertexLayoutDescriptor
+) {
+	const buffer = new ArrayBuffer(layout.arrayStride * layout.vertexCount);
+	const view = new DataView(buffer);
+	for (const descriptor of layout.attributes) {
+		const attribute = geometry.getAttribute(
+			descriptor.name
+		) as THREE.BufferAttribute;
+		const array = attribute.array;
+		const { bytes, setter } = getScalarFormat(array, attribute.normalized);
+		const write = (offset: number, value: number) =>
+			(view as any)[setter](offset, value, true);
+		for (let vertex = 0; vertex < layout.vertexCount; vertex++) {
+			const base = vertex * layout.arrayStride + descriptor.offset;
+			for (let c = 0; c < descriptor.components; c++) {
+				write(base + c * bytes, array[vertex * descriptor.components + c]);
+			}
+		}
+	}
+	return buffer;
+}
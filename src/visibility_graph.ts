@@ -0,0 +1,108 @@
+import * as THREE from "three";
+import { isOpaqueBlock } from "./utils";
+import type { ChunkVisibility, Faces, SchematicChunk } from "./types";
+
+const CHUNK_FACES: Faces[] = ["west", "east", "down", "up", "north", "south"];
+
+const STEPS = [
+	[-1, 0, 0],
+	[1, 0, 0],
+	[0, -1, 0],
+	[0, 1, 0],
+	[0, 0, -1],
+	[0, 0, 1],
+];
+
+// flood fills every region of non opaque cells inside the chunk, a region touching two
+// chunk faces means a camera looking through one face may see out of the other
+export function computeChunkVisibility(
+	schematic: any,
+	chunk: SchematicChunk,
+	dimensions: { chunkWidth: number; chunkHeight: number; chunkLength: number }
+): ChunkVisibility {
+	const scale = chunk.scale ?? 1;
+	const origin = [
+		chunk.x * dimensions.chunkWidth,
+		chunk.y * dimensions.chunkHeight,
+		chunk.z * dimensions.chunkLength,
+	];
+	const size = [
+		dimensions.chunkWidth * scale,
+		dimensions.chunkHeight * scale,
+		dimensions.chunkLength * scale,
+	];
+	const index = (x: number, y: number, z: number) =>
+		x + z * size[0] + y * size[0] * size[2];
+	const cellCount = size[0] * size[1] * size[2];
+	const blocked = new Uint8Array(cellCount);
+	const visited = new Uint8Array(cellCount);
+	const position = new THREE.Vector3();
+	for (let y = 0; y < size[1]; y++) {
+		for (let z = 0; z < size[2]; z++) {
+			for (let x = 0; x < size[0]; x++) {
+				position.set(origin[0] + x, origin[1] + y, origin[2] + z);
+				blocked[index(x, y, z)] = isOpaqueBlock(schematic.getBlock(position))
+					? 1
+					: 0;
+			}
+		}
+	}
+
+	let connectivity = 0n;
+	const queue = new Int32Array(cellCount * 3);
+	for (let start = 0; start < cellCount; start++) {
+		if (blocked[start] || visited[start]) {
+			continue;
+		}
+		const sx = start % size[0];
+		const sz = Math.floor(start / size[0]) % size[2];
+		const sy = Math.floor(start / (size[0] * size[2]));
+		let head = 0;
+		let tail = 0;
+		queue[tail++] = sx;
+		queue[tail++] = sy;
+		queue[tail++] = sz;
+		visited[start] = 1;
+		let touchedFaces = 0;
+		while (head < tail) {
+			const cell = [queue[head++], queue[head++], queue[head++]];
+			for (let face = 0; face < 6; face++) {
+				const next = [
+					cell[0] + STEPS[face][0],
+					cell[1] + STEPS[face][1],
+					cell[2] + STEPS[face][2],
+				];
+				const axis = face >> 1;
+				if (next[axis] < 0 || next[axis] >= size[axis]) {
+					touchedFaces |= 1 << face;
+					continue;
+				}
+				const i = index(next[0], next[1], next[2]);
+				if (blocked[i] || visited[i]) {
+					continue;
+				}
+				visited[i] = 1;
+				queue[tail++] = next[0];
+				queue[tail++] = next[1];
+				queue[tail++] = next[2];
+			}
+		}
+		for (let a = 0; a < 6; a++) {
+			for (let b = 0; b < 6; b++) {
+				if (a !== b && touchedFaces & (1 << a) && touchedFaces & (1 << b)) {
+					connectivity |= 1n << BigInt(a * 6 + b);
+				}
+			}
+		}
+	}
+
+	const connections: [Faces, Faces][] = [];
+	for (let a = 0; a < 6; a++) {
+		for (let b = a + 1; b < 6; b++) {
+			if (connectivity & (1n << BigInt(a * 6 + b))) {
+				connections.push([CHUNK_FACES[a], CHUNK_FACES[b]]);
+			}
+		}
+	}
+	return { chunkKey: chunk.key, connectivity, connections };
+}
@@ -0,0 +1,100 @@
+import { createBlockSetSchematic } from "./legacy_formats";
+
+export const VOX_COLOR_BLOCK = "vox_color";
+
+// magicavoxel's palette used when a file has no RGBA chunk
+const DEFAULT_VOX_PALETTE = (() => {
+	const palette: number[][] = [];
+	const steps = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+	// blue changes fastest, then green, then red
+	for (const r of steps) {
+		for (const g of steps) {
+			for (const b of steps) {
+				palette.push([r, g, b, 255]);
+			}
+		}
+	}
+	palette.pop();
+	const ramps = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+	for (const channel of [2, 1, 0]) {
+		for (const value of ramps) {
+			const color = [0, 0, 0, 255];
+			color[channel] = value;
+			palette.push(color);
+		}
+	}
+	for (const value of ramps) {
+		palette.push([value, value, value, 255]);
+	}
+	palette.push([0, 0, 0, 0]);
+	return palette;
+})();
+
+export function isVox(bytes: Uint8Array) {
+	return String.fromCharCode(...bytes.subarray(0, 4)) === "VOX ";
+}
+
+function toHex(color: number[]) {
+	return `#${color
+		.slice(0, 3)
+		.map((channel) => channel.toString(16).padStart(2, "0"))
+		.join("")}`;
+}
+
+// blocks come from the caller's palette, keyed by "#rrggbb" or the 1 based color index,
+// colors it doesn't map become synthetic colored cubes
+export function loadVox(
+	bytes: Uint8Array,
+	palette: { [colorOrIndex: string]: string } = {}
+) {
+	const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+	const readId = (offset: number) =>
+		String.fromCharCode(...bytes.subarray(offset, offset + 4));
+	const models: { size: number[]; voxels: number[][] }[] = [];
+	let colors = DEFAULT_VOX_PALETTE;
+	let size: number[] = [0, 0, 0];
+	// MAIN only wraps the other chunks, so everything after its header is walked flat
+	let offset = 8 + 12;
+	while (offset + 12 <= bytes.byteLength) {
+		const id = readId(offset);
+		const contentLength = view.getUint32(offset + 4, true);
+		const content = offset + 12;
+		if (id === "SIZE") {
+			size = [0, 4, 8].map((i) => view.getUint32(content + i, true));
+		} else if (id === "XYZI") {
+			const count = view.getUint32(content, true);
+			const voxels: number[][] = [];
+			for (let i = 0; i < count; i++) {
+				const voxel = content + 4 + i * 4;
+				voxels.push(Array.from(bytes.subarray(voxel, voxel + 4)));
+			}
+			models.push({ size, voxels });
+		} else if (id === "RGBA") {
+			colors = [];
+			for (let i = 0; i < 256; i++) {
+				const color = content + i * 4;
+				colors.push(Array.from(bytes.subarray(color, color + 4)));
+			}
+		}
+		offset = content + contentLength;
+	}
+
+	const blocks = new Map<string, { type: string; properties: any }>();
+	const model = models[0];
+	const [sizeX, sizeY, sizeZ] = model?.size ?? [0, 0, 0];
+	for (const [x, y, z, colorIndex] of model?.voxels ?? []) {
+		const color = colors[colorIndex - 1] ?? [255, 255, 255, 255];
+		const hex = toHex(color);
+		const mapped = palette[hex] ?? palette[colorIndex];
+		const block = mapped
+			? { type: mapped.replace(/^minecraft:/, ""), properties: {} }
+			: { type: VOX_COLOR_BLOCK, properties: { color: hex } };
+		// vox is z up and its y axis runs opposite to minecraft's z
+		blocks.set(`${x},${z},${sizeY - 1 - y}`, block);
+	}
+	return createBlockSetSchematic(
+		blocks,
+		{ format: "vox", models: models.length },
+		[sizeX, sizeZ, sizeY]
+	);
+}
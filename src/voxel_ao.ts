@@ -0,0 +1,101 @@
+import * as THREE from "three";
+import { FACE_AXES } from "./greedy_mesher";
+import type { VoxelOccupancy } from "./vertex_baking";
+
+const EPSILON = 1e-5;
+
+// the classic corner darkening, 3 is fully open and 0 is a corner boxed in by both sides.
+// only faces lying on their block boundary get values, anything inset or partial
+// returns undefined and renders unoccluded
+export function computeFaceAo(
+	blockComponent: any,
+	cell: number[],
+	isSolid: VoxelOccupancy
+) {
+	const { positions, face } = blockComponent;
+	const axes = FACE_AXES[face];
+	if (!axes || positions.length !== 12) {
+		return undefined;
+	}
+	const front = cell.slice();
+	front[axes.normalAxis] += axes.plane === 1 ? 1 : -1;
+	const ao: number[] = [];
+	for (let i = 0; i < 4; i++) {
+		const pos = positions.slice(i * 3, i * 3 + 3);
+		const u = pos[axes.uAxis];
+		const v = pos[axes.vAxis];
+		if (
+			Math.abs(pos[axes.normalAxis] - axes.plane) > EPSILON ||
+			Math.abs(u - Math.round(u)) > EPSILON ||
+			Math.abs(v - Math.round(v)) > EPSILON
+		) {
+			return undefined;
+		}
+		const side1 = front.slice();
+		side1[axes.uAxis] += Math.round(u) === 1 ? 1 : -1;
+		const side2 = front.slice();
+		side2[axes.vAxis] += Math.round(v) === 1 ? 1 : -1;
+		const corner = side1.slice();
+		corner[axes.vAxis] = side2[axes.vAxis];
+		const a = isSolid(side1[0], side1[1], side1[2]) ? 1 : 0;
+		const b = isSolid(side2[0], side2[1], side2[2]) ? 1 : 0;
+		const c = isSolid(corner[0], corner[1], corner[2]) ? 1 : 0;
+		ao.push(a && b ? 0 : 3 - (a + b + c));
+	}
+	return ao;
+}
+
+// block components are shared through the mesh cache, so the ao goes on copies
+export function applyVoxelAo(
+	components: { [materialId: string]: [any, number[]][] },
+	offset: { x: number; y: number; z: number },
+	isSolid: VoxelOccupancy
+) {
+	const result: { [materialId: string]: [any, number[]][] } = {};
+	for (const materialId in components) {
+		result[materialId] = components[materialId].map(
+			([blockComponent, worldPos]) => {
+				const ao = computeFaceAo(
+					blockComponent,
+					[
+						worldPos[0] - offset.x,
+						worldPos[1] - offset.y,
+						worldPos[2] - offset.z,
+					],
+					isSolid
+				);
+				return [ao ? { ...blockComponent, ao } : blockComponent, worldPos];
+			}
+		);
+	}
+	return result;
+}
+
+// a copy of the material that darkens by the ao attribute, minLight is what a fully
+// boxed in corner keeps. like the face id material the base material's hooks are chained
+export function createVoxelAoMaterial(
+	material: THREE.Material,
+	minLight = 0.5
+) {
+	const aoMaterial = material.clone();
+	const min = minLight.toFixed(3);
+	aoMaterial.onBeforeCompile = (shader, renderer) => {
+		material.onBeforeCompile(shader, renderer);
+		shader.vertexShader = shader.vertexShader
+			.replace(
+				"#include <common>",
+				"#include <common>\nattribute float ao;\nvarying float vAo;"
+			)
+			.replace("#include <begin_vertex>", "#include <begin_vertex>\nvAo = ao;");
+		shader.fragmentShader = shader.fragmentShader
+			.replace("#include <common>", "#include <common>\nvarying float vAo;")
+			.replace(
+				"#include <color_fragment>",
+				"#include <color_fragment>\n" +
+					`diffuseColor.rgb *= mix( ${min}, 1.0, vAo / 3.0 );`
+			);
+	};
+	aoMaterial.customProgramCacheKey = () =>
+		`${material.customProgramCacheKey()}|ao${min}`;
+	return aoMaterial;
+}
@@ -0,0 +1,180 @@
+import { hashBlockForMap, INVISIBLE_BLOCKS } from "./utils";
+import type { VoxelColorMapping } from "./types";
+
+const DEFAULT_COLOR = [128, 128, 128, 255];
+
+function resolveColor(block: any, mapping: VoxelColorMapping) {
+	const color =
+		typeof mapping === "function"
+			? mapping(block)
+			: mapping[hashBlockForMap(block)] ?? mapping[block.type];
+	const [r, g, b, a = 255] = color ?? DEFAULT_COLOR;
+	return [r, g, b, a];
+}
+
+// the host decides the colors, blocks it doesn't know about export as neutral grey
+function collectVoxels(schematic: any, mapping: VoxelColorMapping) {
+	const voxels: { x: number; y: number; z: number; color: number[] }[] = [];
+	for (const pos of schematic) {
+		const block = schematic.getBlock(pos);
+		if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+			continue;
+		}
+		const color = resolveColor(block, mapping);
+		voxels.push({ x: pos.x, y: pos.y, z: pos.z, color });
+	}
+	return voxels;
+}
+
+class ByteWriter {
+	bytes: number[] = [];
+
+	uint8(value: number) {
+		this.bytes.push(value & 0xff);
+	}
+
+	uint32(value: number) {
+		for (let i = 0; i < 4; i++) {
+			this.bytes.push((value >>> (i * 8)) & 0xff);
+		}
+	}
+
+	ascii(text: string) {
+		for (let i = 0; i < text.length; i++) {
+			this.bytes.push(text.charCodeAt(i));
+		}
+	}
+
+	chunk(id: string, content: number[], children: number[] = []) {
+		this.ascii(id);
+		this.uint32(content.length);
+		this.uint32(children.length);
+		// appended one by one, spreading large voxel lists overflows the call stack
+		for (const byte of content) {
+			this.bytes.push(byte);
+		}
+		for (const byte of children) {
+			this.bytes.push(byte);
+		}
+	}
+
+	toArrayBuffer() {
+		return new Uint8Array(this.bytes).buffer;
+	}
+}
+
+// magicavoxel models are z up and capped at 256 per axis and 255 colors, larger builds
+// are cropped and colors past the palette limit reuse the closest entry
+export function exportVox(schematic: any, mapping: VoxelColorMapping) {
+	const palette: number[][] = [];
+	const paletteIndex = new Map<string, number>();
+	const getPaletteIndex = (color: number[]) => {
+		const key = color.join(",");
+		let index = paletteIndex.get(key);
+		if (index === undefined) {
+			if (palette.length < 255) {
+				palette.push(color);
+				index = palette.length;
+			} else {
+				index = findClosestColor(palette, color) + 1;
+			}
+			paletteIndex.set(key, index);
+		}
+		return index;
+	};
+	const sizeX = Math.min(schematic.width, 256);
+	const sizeY = Math.min(schematic.length, 256);
+	const sizeZ = Math.min(schematic.height, 256);
+	const voxels = collectVoxels(schematic, mapping).filter(
+		(voxel) => voxel.x < sizeX && voxel.z < sizeY && voxel.y < sizeZ
+	);
+
+	const size = new ByteWriter();
+	size.uint32(sizeX);
+	size.uint32(sizeY);
+	size.uint32(sizeZ);
+	const xyzi = new ByteWriter();
+	xyzi.uint32(voxels.length);
+	// minecraft z runs towards the viewer, vox y away from it, so it is flipped
+	for (const voxel of voxels) {
+		xyzi.uint8(voxel.x);
+		xyzi.uint8(sizeY - 1 - voxel.z);
+		xyzi.uint8(voxel.y);
+		xyzi.uint8(getPaletteIndex(voxel.color));
+	}
+	const rgba = new ByteWriter();
+	for (let i = 0; i < 256; i++) {
+		const [r, g, b, a] = palette[i] ?? [0, 0, 0, 0];
+		rgba.uint8(r);
+		rgba.uint8(g);
+		rgba.uint8(b);
+		rgba.uint8(a);
+	}
+	const children = new ByteWriter();
+	children.chunk("SIZE", size.bytes);
+	children.chunk("XYZI", xyzi.bytes);
+	children.chunk("RGBA", rgba.bytes);
+
+	const file = new ByteWriter();
+	file.ascii("VOX ");
+	file.uint32(150);
+	file.chunk("MAIN", [], children.bytes);
+	return file.toArrayBuffer();
+}
+
+function findClosestColor(palette: number[][], color: number[]) {
+	let best = 0;
+	let bestDistance = Infinity;
+	palette.forEach((entry, i) => {
+		const distance =
+			(entry[0] - color[0]) ** 2 +
+			(entry[1] - color[1]) ** 2 +
+			(entry[2] - color[2]) ** 2;
+		if (distance < bestDistance) {
+			bestDistance = distance;
+			best = i;
+		}
+	});
+	return best;
+}
+
+// uncompressed single matrix qubicle file, the alpha byte doubles as visibility
+export function exportQb(
+	schematic: any,
+	mapping: VoxelColorMapping,
+	name: string = "schematic"
+) {
+	const { width, height, length } = schematic;
+	const colors = new Uint32Array(width * height * length);
+	const view = new DataView(colors.buffer);
+	for (const voxel of collectVoxels(schematic, mapping)) {
+		// the header declares a left handed z axis, the opposite of minecraft
+		const z = length - 1 - voxel.z;
+		const index = voxel.x + voxel.y * width + z * width * height;
+		const [r, g, b] = voxel.color;
+		view.setUint8(index * 4, r);
+		view.setUint8(index * 4 + 1, g);
+		view.setUint8(index * 4 + 2, b);
+		view.setUint8(index * 4 + 3, 255);
+	}
+	const header = new ByteWriter();
+	[1, 1, 0, 0].forEach((part) => header.uint8(part));
+	header.uint32(0);
+	header.uint32(0);
+	header.uint32(0);
+	header.uint32(0);
+	header.uint32(1);
+	const matrixName = name.slice(0, 255);
+	header.uint8(matrixName.length);
+	header.ascii(matrixName);
+	header.uint32(width);
+	header.uint32(height);
+	header.uint32(length);
+	header.uint32(0);
+	header.uint32(0);
+	header.uint32(0);
+	const output = new Uint8Array(header.bytes.length + colors.byteLength);
+	output.set(header.bytes);
+	output.set(new Uint8Array(colors.buffer), header.bytes.length);
+	return output.buffer;
+}
@@ -0,0 +1,119 @@
+import * as THREE from "three";
+import type { WaterVolume } from "./types";
+
+// always submerged even without a waterlogged property
+const UNDERWATER_BLOCKS = new Set([
+	"water",
+	"bubble_column",
+	"kelp",
+	"kelp_plant",
+	"seagrass",
+	"tall_seagrass",
+]);
+
+export function isWaterBlock(block: any) {
+	if (!block) {
+		return false;
+	}
+	return (
+		UNDERWATER_BLOCKS.has(block.type) ||
+		block.properties?.waterlogged === "true" ||
+		block.properties?.waterlogged === true
+	);
+}
+
+// 6-connected flood fill over the water cells, each connected body becomes one volume
+export function extractWaterVolumes(
+	schematic: any,
+	offset: { x: number; y: number; z: number }
+): WaterVolume[] {
+	const { width, height, length } = schematic;
+	const index = (x: number, y: number, z: number) =>
+		x + z * width + y * width * length;
+	const water = new Uint8Array(width * height * length);
+	for (const pos of schematic) {
+		if (isWaterBlock(schematic.getBlock(pos))) {
+			water[index(pos.x, pos.y, pos.z)] = 1;
+		}
+	}
+	const visited = new Uint8Array(water.length);
+	const volumes: WaterVolume[] = [];
+	const neighbors = [
+		[1, 0, 0],
+		[-1, 0, 0],
+		[0, 1, 0],
+		[0, -1, 0],
+		[0, 0, 1],
+		[0, 0, -1],
+	];
+	for (let start = 0; start < water.length; start++) {
+		if (!water[start] || visited[start]) {
+			continue;
+		}
+		const cells: number[] = [];
+		const min = [Infinity, Infinity, Infinity];
+		const max = [-Infinity, -Infinity, -Infinity];
+		const queue = [start];
+		visited[start] = 1;
+		while (queue.length > 0) {
+			const cell = queue.pop()!;
+			cells.push(cell);
+			const x = cell % width;
+			const z = Math.floor(cell / width) % length;
+			const y = Math.floor(cell / (width * length));
+			min[0] = Math.min(min[0], x);
+			min[1] = Math.min(min[1], y);
+			min[2] = Math.min(min[2], z);
+			max[0] = Math.max(max[0], x + 1);
+			max[1] = Math.max(max[1], y + 1);
+			max[2] = Math.max(max[2], z + 1);
+			for (const [dx, dy, dz] of neighbors) {
+				const nx = x + dx;
+				const ny = y + dy;
+				const nz = z + dz;
+				if (
+					nx < 0 ||
+					ny < 0 ||
+					nz < 0 ||
+					nx >= width ||
+					ny >= height ||
+					nz >= length
+				) {
+					continue;
+				}
+				const neighbor = index(nx, ny, nz);
+				if (water[neighbor] && !visited[neighbor]) {
+					visited[neighbor] = 1;
+					queue.push(neighbor);
+				}
+			}
+		}
+		const cellSet = new Set(cells);
+		volumes.push({
+			id: volumes.length,
+			blockCount: cells.length,
+			boundingBox: new THREE.Box3(
+				new THREE.Vector3().fromArray(min),
+				new THREE.Vector3().fromArray(max)
+			).translate(new THREE.Vector3(offset.x, offset.y, offset.z)),
+			surfaceY: max[1] + offset.y,
+			containsPoint(point: { x: number; y: number; z: number }) {
+				const x = Math.floor(point.x - offset.x);
+				const y = Math.floor(point.y - offset.y);
+				const z = Math.floor(point.z - offset.z);
+				if (
+					x < 0 ||
+					y < 0 ||
+					z < 0 ||
+					x >= width ||
+					y >= height ||
+					z >= length
+				) {
+					return false;
+				}
+				return cellSet.has(index(x, y, z));
+			},
+		});
+	}
+	return volumes;
+}
@@ -1,64 +1,315 @@
 import * as THREE from "three";
 
 import { BlockMeshBuilder } from "./block_mesh_builder";
+import { logger } from "./logger";
+import {
+	createMergeReport,
+	greedyMergeComponents,
+	recordMergeStats,
+} from "./greedy_mesher";
+import { applyVoxelAo, createVoxelAoMaterial } from "./voxel_ao";
+import { createQuadRanges } from "./quad_provenance";
+import { buildLodComponents, downsampleBlocks } from "./lod_mesher";
+import { chooseChunkDimensions, findChunkRegionKey } from "./chunk_sizing";
+import { simplifyComponents } from "./mesh_simplify";
+import {
+	INFERRED_CATEGORY_SETTINGS,
+	inferPaletteCategory,
+} from "./category_inference";
+import type {
+	ChunkPartition,
+	ChunkVisibility,
+	ExclusionBox,
+	CutVolume,
+	PaletteBakeJob,
+	BlockRotation,
+	HeatmapOptions,
+	MaterialSortPreset,
+	MergeReport,
+	SchematicChunk,
+} from "./types";
+import { createLightmapPacker, generateLightmapUvs } from "./lightmap_uvs";
+import { generateChunkSkirts } from "./chunk_skirts";
+import { matchesHighlightPredicate } from "./highlight";
+import { computeChunkVisibility } from "./visibility_graph";
+import { excludeSchematicBoxes } from "./exclusion";
+import { hashSchematicChunks } from "./chunk_hash";
+import { getRenderMetadata } from "./render_metadata";
+import { startPaletteBake } from "./palette_baking";
+import { createFaceIdMaterial, packFaceIds } from "./face_ids";
+import {
+	copyGeometryAttributes,
+	diffGeometryAttributes,
+} from "./attribute_diff";
+import {
+	bakeHeatmap,
+	bakeHeightTint,
+	bakeRayTracedAo,
+	bakeSunShadows,
+} from "./vertex_baking";
+import {
+	collapseVertexColorGroups,
+	createVertexColorMaterials,
+	getBlockFaceColor,
+	getVertexColorGroupId,
+} from "./color_mode";
+import {
+	createXrayShellComponents,
+	createXrayShellMaterial,
+	XRAY_SHELL_MATERIAL_ID,
+	XrayFilter,
+} from "./xray";
 import {
 	INVISIBLE_BLOCKS,
 	TRANSPARENT_BLOCKS,
+	emitWarning,
+	getBlockCategory,
+	hashBlockForMap,
+	getRenderCategory,
+	insetComponentUvs,
+	isBuriedBlock,
 	occludedFacesIntToList,
+	unpackChunkKey,
+	yieldToMain,
 } from "./utils";
 
+// past this distance from the origin float32 vertex positions start to visibly jitter
+const RECENTER_THRESHOLD = 4096;
+
+const isEmissive = (material?: THREE.Material) => {
+	const emissive = (material as THREE.MeshStandardMaterial)?.emissive;
+	return !!emissive && emissive.getHex() !== 0;
+};
+
+const MATERIAL_SORT_PRESETS: {
+	[preset in MaterialSortPreset]: (
+		materialId: string,
+		material?: THREE.Material
+	) => number;
+} = {
+	"opaque-first": (_materialId, material) => (material?.transparent ? 1 : 0),
+	"emissive-last": (_materialId, material) => (isEmissive(material) ? 1 : 0),
+	"opaque-first-emissive-last": (_materialId, material) =>
+		(material?.transparent ? 2 : 0) + (isEmissive(material) ? 1 : 0),
+};
+
 export class WorldMeshBuilder {
 	schematic: any;
+	sourceSchematic: any;
 	blockMeshBuilder: any;
 	ressourceLoader: any;
 	renderer: any;
 	worldMeshes: any[] = [];
+	chunkMeshes: Map<bigint, THREE.Mesh[]> = new Map();
+	meshTranslations: Map<bigint, THREE.Vector3> = new Map();
+	options: any;
+	mergeReport: MergeReport = createMergeReport();
+	lastChunkTimes: any;
+	insetComponentCache: WeakMap<any, any> = new WeakMap();
+	faceIdMaterials: WeakMap<THREE.Material, THREE.Material> = new WeakMap();
+	vertexColorMaterials: WeakMap<THREE.Material, THREE.Material> =
+		new WeakMap();
+	voxelAoMaterials: WeakMap<THREE.Material, THREE.Material> = new WeakMap();
+	chunks: Map<bigint, SchematicChunk> = new Map();
+	chunkHashes: Map<bigint, string> = new Map();
+	chunkDimensions = { chunkWidth: 64, chunkHeight: 64, chunkLength: 64 };
+	chunkPartition: ChunkPartition | undefined;
+	chunkScales: Map<bigint, number> = new Map();
+	meshVersions: Map<bigint, number> = new Map();
+	dirtyChunks: Set<bigint> = new Set();
+	buildTokens: Map<bigint, unknown> = new Map();
+	paletteBakes: Set<PaletteBakeJob> = new Set();
+	dirtyAttributes: Map<bigint, Map<string, Set<string>>> = new Map();
+	meshVersionCounter = 0;
+	prebuildQueue: bigint[] = [];
+	chunkLodLevels: Map<bigint, number> = new Map();
+	lightmapPacker = createLightmapPacker();
+	xrayFilter: XrayFilter | null = null;
+	xrayShellComponents = createXrayShellComponents();
+	prebuiltChunks: Map<
+		bigint,
+		{ meshes: THREE.Mesh[]; translation: THREE.Vector3 }
+	> = new Map();
 	constructor(
 		ressourceLoader: any,
 		materialMap: Map<string, THREE.Material>,
-		renderer: any
+		renderer: any,
+		options: any = {}
 	) {
 		this.ressourceLoader = ressourceLoader;
 		this.renderer = renderer;
+		this.options = options ?? {};
 
 		this.blockMeshBuilder = new BlockMeshBuilder(
 			ressourceLoader,
 			materialMap,
-			this.renderer
+			this.renderer,
+			this.options
 		);
 	}
 
 	public setSchematic(schematic: any) {
-		this.schematic = schematic;
-		this.blockMeshBuilder.setSchematic(schematic);
+		this.sourceSchematic = schematic;
+		const volumes = this.getCutVolumes();
+		this.schematic =
+			volumes.length > 0
+				? excludeSchematicBoxes(schematic, volumes)
+				: schematic;
+		this.blockMeshBuilder.setSchematic(this.schematic);
+		this.chunks = new Map();
+	}
+
+	// swaps in a new version of the same schematic, e.g. an autosave, and only rebuilds the
+	// chunks whose content hash changed plus their neighbours since border faces depend on them
+	public async reloadSchematic(schematic: any) {
+		const previous = this.schematic;
+		const sameSize =
+			previous &&
+			previous.width === schematic.width &&
+			previous.height === schematic.height &&
+			previous.length === schematic.length;
+		const previousHashes = this.chunkHashes;
+		const previousChunkKeys = [...this.chunkMeshes.keys()];
+		this.setSchematic(schematic);
+		if (!sameSize) {
+			for (const chunkKey of previousChunkKeys) {
+				this.removeChunkMeshes(chunkKey);
+			}
+			await this.getSchematicMeshes(this.chunkDimensions);
+			return [...this.chunks.keys()];
+		}
+		this.chunks = this.splitSchemaIntoChunks(this.chunkDimensions);
+		this.chunkHashes = hashSchematicChunks(
+			this.schematic,
+			this.chunkDimensions,
+			this.chunkScales
+		);
+		const changed = new Set<bigint>();
+		for (const key of new Set([
+			...previousHashes.keys(),
+			...this.chunkHashes.keys(),
+		])) {
+			if (previousHashes.get(key) !== this.chunkHashes.get(key)) {
+				changed.add(key);
+			}
+		}
+		const rebuild = new Set(changed);
+		for (const key of changed) {
+			for (const neighbor of this.getNeighborChunkKeys(key)) {
+				rebuild.add(neighbor);
+			}
+		}
+		for (const key of rebuild) {
+			this.discardPrebuiltChunk(key);
+			if (this.chunks.has(key)) {
+				await this.buildChunk(this.chunks.get(key)!);
+			} else {
+				this.removeChunkMeshes(key);
+			}
+		}
+		return [...rebuild];
+	}
+
+	// hides the boxes and rebuilds everything, an empty list shows the whole schematic again
+	public async setExclusionBoxes(boxes: ExclusionBox[]) {
+		this.options.exclusionBoxes = boxes;
+		for (const chunkKey of [...this.chunkMeshes.keys()]) {
+			this.removeChunkMeshes(chunkKey);
+		}
+		this.setSchematic(this.sourceSchematic);
+		return this.getSchematicMeshes(this.chunkDimensions);
+	}
+
+	// unlike exclusion boxes these are meant to change often, e.g. a doll-house view
+	// that opens one quadrant at a time
+	public async setCutVolumes(volumes: CutVolume[]) {
+		this.options.cutVolumes = volumes;
+		return this.setExclusionBoxes(this.options.exclusionBoxes ?? []);
+	}
+
+	// only chunks that can see the neighbours are rebuilt: the ones on the schematic
+	// boundary, plus those within reach of the sun and sky rays when those are baked
+	public async setNeighborOccupancy(
+		neighborOccupancy: ((x: number, y: number, z: number) => boolean) | null
+	) {
+		this.options.neighborOccupancy = neighborOccupancy ?? undefined;
+		const { width, height, length } = this.schematic;
+		let reach = 0;
+		if (this.options.sunShadows) {
+			reach = Math.max(reach, this.options.sunShadows.maxDistance ?? 64);
+		}
+		if (this.options.rayTracedAo) {
+			reach = Math.max(reach, this.options.rayTracedAo.maxDistance ?? 4);
+		}
+		const rebuilt: bigint[] = [];
+		for (const chunk of this.chunks.values()) {
+			const { min, size } = this.getChunkBounds(chunk);
+			const edgeDistance = Math.min(
+				min[0],
+				min[1],
+				min[2],
+				width - min[0] - size[0],
+				height - min[1] - size[1],
+				length - min[2] - size[2]
+			);
+			if (edgeDistance <= reach && this.chunkMeshes.has(chunk.key)) {
+				await this.rebuildChunk(chunk.key);
+				rebuilt.push(chunk.key);
+			}
+		}
+		return rebuilt;
+	}
+
+	// null turns the heatmap off again, every built chunk is rebuilt either way
+	public async setHeatmap(heatmap: HeatmapOptions | null) {
+		this.options.heatmap = heatmap;
+		for (const chunkKey of [...this.chunkMeshes.keys()]) {
+			await this.rebuildChunk(chunkKey);
+		}
+		return this.worldMeshes;
+	}
+
+	public getCutVolumes(): CutVolume[] {
+		return [
+			...(this.options.exclusionBoxes ?? []),
+			...(this.options.cutVolumes ?? []),
+		];
 	}
 
 	public splitSchemaIntoChunks(
 		dimensions = { chunkWidth: 64, chunkHeight: 64, chunkLength: 64 }
 	) {
-		const chunks: any[] = [];
+		const chunks: Map<bigint, SchematicChunk> = new Map();
 		const { chunkWidth, chunkHeight, chunkLength } = dimensions;
-		const { width, height } = this.schematic;
-		const chunkCountX = Math.ceil(width / chunkWidth);
-		const chunkCountY = Math.ceil(height / chunkHeight);
 		for (const pos of this.schematic) {
-			const { x, y, z } = pos;
-			const chunkX = Math.floor(x / chunkWidth);
-			const chunkY = Math.floor(y / chunkHeight);
-			const chunkZ = Math.floor(z / chunkLength);
-			const chunkIndex =
-				chunkX + chunkY * chunkCountX + chunkZ * chunkCountX * chunkCountY;
-			if (!chunks[chunkIndex]) {
-				chunks[chunkIndex] = [];
+			if (this.options.hollowShell && isBuriedBlock(this.schematic, pos)) {
+				continue;
+			}
+			const key = findChunkRegionKey(
+				this.chunkScales,
+				Math.floor(pos.x / chunkWidth),
+				Math.floor(pos.y / chunkHeight),
+				Math.floor(pos.z / chunkLength)
+			);
+			let chunk = chunks.get(key);
+			if (!chunk) {
+				chunk = { key, ...unpackChunkKey(key), positions: [] };
+				const scale = this.chunkScales.get(key);
+				if (scale) {
+					chunk.scale = scale;
+				}
+				chunks.set(key, chunk);
 			}
-			chunks[chunkIndex].push(pos);
+			chunk.positions.push(pos);
 		}
 		return chunks;
 	}
 
 	public async getChunkMesh(
 		chunk: any,
-		offset: { x: number; y: number; z: number }
+		offset: { x: number; y: number; z: number },
+		lodLevel: number = 0,
+		materialFilter?: (materialId: string) => boolean
 	) {
 		const maxBlocksAllowed = 1000000;
 
@@ -81,7 +332,12 @@ export class WorldMeshBuilder {
 		let start;
 		let startTotal = performance.now();
 		const offsetValue = offset ?? { x: 0, y: 0, z: 0 };
-		const components = {} as any;
+		// categories with distances configured get their own meshes, the rest share ""
+		const componentsByCategory: { [category: string]: any } = { "": {} };
+		// every category a group's blocks belong to, so rebuildCategory can find them later
+		const groupCategories = new Map<string, Set<string>>();
+		// debug only, how many blocks of each palette entry went into each group
+		const groupProvenance = new Map<string, Map<string, number>>();
 		for (let i = 0; i < chunk.length; i++) {
 			if (count > maxBlocksAllowed) {
 				break;
@@ -93,34 +349,114 @@ export class WorldMeshBuilder {
 			y += offsetValue.y;
 			z += offsetValue.z;
 
-			const block = this.schematic.getBlock(pos);
+			let block = this.schematic.getBlock(pos);
 			if (INVISIBLE_BLOCKS.has(block.type)) {
 				continue;
 			}
+			const category = getRenderCategory(
+				block.type,
+				this.options.renderCategories
+			);
+			const categoryDistance =
+				category && this.options.categoryDistances?.[category];
+			if (lodLevel > (categoryDistance?.maxLodLevel ?? Infinity)) {
+				continue;
+			}
+			const components = categoryDistance
+				? (componentsByCategory[category!] ??= {})
+				: componentsByCategory[""];
+			block = this.blockMeshBuilder.resolveNeighborDependentState(block, pos);
 			start = performance.now();
-			const occludedFaces = occludedFacesIntToList(
+			let occludedFaces = occludedFacesIntToList(
 				this.blockMeshBuilder.getOccludedFacesForBlock(block, pos)
 			);
 			chunkTimes.occlusion += performance.now() - start;
 
 			start = performance.now();
-			const blockComponents = await this.blockMeshBuilder.getBlockMeshFromCache(
-				block,
-				pos
-			);
+			let blockComponents;
+			if (
+				this.xrayFilter &&
+				!matchesHighlightPredicate(
+					block,
+					this.xrayFilter.predicate,
+					this.xrayFilter.paletteKeys
+				)
+			) {
+				blockComponents = this.xrayShellComponents;
+			} else {
+				blockComponents = await this.blockMeshBuilder.getBlockMeshFromCache(
+					block,
+					pos
+				);
+				// whitelisted blocks sit behind a see-through shell, so nothing is culled
+				if (this.xrayFilter) {
+					occludedFaces = {};
+				}
+			}
+			if (this.options.coplanarOffset > 0) {
+				blockComponents =
+					this.blockMeshBuilder.getCoplanarOffsetMesh(blockComponents);
+			}
+			const rotation = this.options.blockRotation?.(block, pos);
+			if (rotation) {
+				blockComponents = this.blockMeshBuilder.getRotatedBlockMesh(
+					blockComponents,
+					rotation
+				);
+			}
 			chunkTimes.blockMeshRetrieval += performance.now() - start;
 
+			const blockGroups = new Set<string>();
 			for (const key in blockComponents) {
 				const materialId = blockComponents[key].materialId;
+				if (materialFilter && !materialFilter(materialId)) {
+					continue;
+				}
 
 				const blockComponent = blockComponents[key];
-				if (occludedFaces[blockComponent.face]) {
+				const quadCount = blockComponent.positions.length / 12;
+				recordMergeStats(
+					this.mergeReport,
+					blockComponent.face,
+					materialId,
+					"facesConsidered",
+					quadCount
+				);
+				if (
+					occludedFaces[blockComponent.face] &&
+					blockComponent.cullable !== false
+				) {
+					recordMergeStats(
+						this.mergeReport,
+						blockComponent.face,
+						materialId,
+						"facesCulled",
+						quadCount
+					);
 					continue;
 				}
-				if (!components[materialId]) {
-					components[materialId] = [];
+				const groupId = this.getComponentGroupId(materialId, block, pos);
+				if (!components[groupId]) {
+					components[groupId] = [];
+				}
+				if (!groupCategories.has(groupId)) {
+					groupCategories.set(groupId, new Set());
+				}
+				for (const blockCategory of this.getBlockCategories(block)) {
+					groupCategories.get(groupId)!.add(blockCategory);
+				}
+				components[groupId].push([blockComponents[key], [x, y, z]]);
+				blockGroups.add(groupId);
+			}
+			if (this.options.debugProvenance) {
+				const paletteKey = hashBlockForMap(block);
+				for (const groupId of blockGroups) {
+					if (!groupProvenance.has(groupId)) {
+						groupProvenance.set(groupId, new Map());
+					}
+					const counts = groupProvenance.get(groupId)!;
+					counts.set(paletteKey, (counts.get(paletteKey) ?? 0) + 1);
 				}
-				components[materialId].push([blockComponents[key], [x, y, z]]);
 			}
 
 			chunkTimes.blockMeshCreation += performance.now() - start;
@@ -128,18 +464,301 @@ export class WorldMeshBuilder {
 		}
 
 		start = performance.now();
-		const meshes = this.ressourceLoader.createMeshesFromBlocks(
+		const meshes: THREE.Mesh[] = [];
+		for (const [category, components] of Object.entries(
+			componentsByCategory
+		)) {
+			for (const mesh of this.createComponentMeshes(
+				components,
+				chunk,
+				offsetValue,
+				chunkTimes,
+				lodLevel
+			)) {
+				mesh.userData.blockCategories = [
+					...(groupCategories.get(mesh.name) ?? []),
+				];
+				if (this.options.debugProvenance) {
+					mesh.userData.provenance = this.getGroupProvenance(
+						groupProvenance.get(mesh.name)
+					);
+				}
+				if (category) {
+					mesh.userData.category = category;
+					mesh.userData.categoryDistance =
+						this.options.categoryDistances[category];
+				}
+				meshes.push(mesh);
+			}
+		}
+		chunkTimes.chunkMeshCreation.total_time = performance.now() - start;
+		logger.debug("total time", performance.now() - startTotal);
+
+		logger.debug("Chunk times", chunkTimes);
+		this.lastChunkTimes = chunkTimes;
+		return meshes;
+	}
+
+	public createComponentMeshes(
+		components: any,
+		chunk: any,
+		offsetValue: { x: number; y: number; z: number },
+		chunkTimes: any,
+		lodLevel: number = 0
+	): THREE.Mesh[] {
+		if (this.options.voxelAo) {
+			components = applyVoxelAo(components, offsetValue, (x, y, z) =>
+				this.isSolid(x, y, z)
+			);
+		}
+		// quads only merge across cells whose corners darken the same way
+		const mergeKeys = this.options.greedyMergeKeys ?? [];
+		if (this.options.greedyMeshing) {
+			components = greedyMergeComponents(
+				components,
+				this.options.greedyMaxQuadSize,
+				this.options.voxelAo ? [...mergeKeys, "ao"] : mergeKeys
+			);
+		}
+		if (this.options.simplify) {
+			components = simplifyComponents(
+				components,
+				this.options.simplify === true ? {} : this.options.simplify,
+				this.options.greedyMaxQuadSize,
+				mergeKeys
+			);
+		}
+		if (this.options.uvInset > 0) {
+			this.applyUvInset(components);
+		}
+		if (this.options.chunkSkirtDepth > 0 && chunk.length > 0) {
+			const chunkKey = this.getChunkKeyForPosition(chunk[0]);
+			const bounds = this.getChunkBounds(
+				this.chunks.get(chunkKey) ?? {
+					key: chunkKey,
+					...unpackChunkKey(chunkKey),
+					positions: [],
+				}
+			);
+			const offset = [offsetValue.x, offsetValue.y, offsetValue.z];
+			const min = bounds.min.map((value, i) => value + offset[i]);
+			const max = min.map((value, i) => value + bounds.size[i]);
+			// only borders shared with a chunk shown at another detail level can crack
+			const sides = [
+				[-1, 0, 0],
+				[1, 0, 0],
+				[0, 0, -1],
+				[0, 0, 1],
+			].filter((direction) =>
+				this.getChunkKeysAcross(chunkKey, direction).some(
+					(key) => this.getChunkLodLevel(key) !== lodLevel
+				)
+			);
+			if (sides.length > 0) {
+				generateChunkSkirts(
+					components,
+					min,
+					max,
+					this.options.chunkSkirtDepth,
+					sides
+				);
+			}
+		}
+		if (this.options.colorOnly) {
+			components = collapseVertexColorGroups(components);
+			this.ensureVertexColorMaterials();
+		}
+		for (const materialId in components) {
+			for (const [blockComponent] of components[materialId]) {
+				recordMergeStats(
+					this.mergeReport,
+					blockComponent.face,
+					materialId,
+					"quadsEmitted",
+					blockComponent.positions.length / 12
+				);
+			}
+		}
+
+		if (this.options.materialSortPriority) {
+			components = this.sortComponentGroups(components);
+		}
+		const meshes: THREE.Mesh[] = this.ressourceLoader.createMeshesFromBlocks(
 			components,
 			chunkTimes
 		);
-		chunkTimes.chunkMeshCreation.total_time = performance.now() - start;
-		console.log("total time", performance.now() - startTotal);
-
-		console.log("Chunk times", chunkTimes);
+		if (this.options.quadProvenance) {
+			for (const mesh of meshes) {
+				mesh.userData.quadRanges = createQuadRanges(
+					components[mesh.name] ?? [],
+					offsetValue
+				);
+			}
+		}
 		return meshes;
 	}
 
+	// lower priorities are emitted first, ties keep their palette order
+	public sortComponentGroups(components: { [materialId: string]: any }) {
+		const priority = this.options.materialSortPriority;
+		const getPriority =
+			typeof priority === "function"
+				? priority
+				: MATERIAL_SORT_PRESETS[priority as MaterialSortPreset];
+		if (!getPriority) {
+			return components;
+		}
+		const materialMap = this.ressourceLoader.materialMap;
+		const sorted: { [materialId: string]: any } = {};
+		for (const materialId of Object.keys(components)
+			.map((materialId, index) => ({
+				materialId,
+				index,
+				priority: getPriority(materialId, materialMap.get(materialId)),
+			}))
+			.sort((a, b) => a.priority - b.priority || a.index - b.index)
+			.map(({ materialId }) => materialId)) {
+			sorted[materialId] = components[materialId];
+		}
+		return sorted;
+	}
+
+	// hides category meshes past their cull distance, fading is left to the host's materials
+	// since they are shared between chunks
+	public updateCategoryVisibility(cameraPosition: THREE.Vector3) {
+		const center = new THREE.Vector3();
+		for (const mesh of this.worldMeshes.flat() as THREE.Mesh[]) {
+			const cullDistance = mesh.userData.categoryDistance?.cullDistance;
+			if (cullDistance === undefined || !mesh.geometry.boundingSphere) {
+				continue;
+			}
+			center
+				.copy(mesh.geometry.boundingSphere.center)
+				.applyMatrix4(mesh.matrixWorld);
+			const distance =
+				center.distanceTo(cameraPosition) -
+				mesh.geometry.boundingSphere.radius;
+			mesh.visible = distance <= cullDistance;
+		}
+	}
+
+	// baked meshes get a copy of their material, the shared one stays as it is since
+	// meshes without a color attribute would render black with vertex colors on
+	public enableVertexColors(mesh: THREE.Mesh) {
+		const material = mesh.material as THREE.Material;
+		if (material.vertexColors) {
+			return;
+		}
+		let vertexColorMaterial = this.vertexColorMaterials.get(material);
+		if (!vertexColorMaterial) {
+			vertexColorMaterial = material.clone();
+			vertexColorMaterial.vertexColors = true;
+			this.vertexColorMaterials.set(material, vertexColorMaterial);
+		}
+		mesh.material = vertexColorMaterial;
+	}
+
+	// color only and biome tinted builds group faces by color rather than by material
+	public getComponentGroupId(materialId: string, block: any, pos: any) {
+		if (this.options.colorOnly) {
+			return getVertexColorGroupId(
+				getBlockFaceColor(
+					block,
+					this.ressourceLoader.materialMap.get(materialId),
+					this.options.blockColors
+				)
+			);
+		}
+		return this.options.biomeTint
+			? this.getBiomeTintedMaterialId(materialId, block, pos)
+			: materialId;
+	}
+
+	// grass and foliage faces get a material per biome color, which keeps greedy merging
+	// from stretching one tint across a biome border
+	public getBiomeTintedMaterialId(materialId: string, block: any, pos: any) {
+		const descriptor =
+			this.blockMeshBuilder.materialDescriptors.get(materialId);
+		const defaultTint = this.ressourceLoader.TINT_COLOR.toArray();
+		if (
+			!descriptor?.color ||
+			descriptor.color.some(
+				(channel: number, i: number) => channel !== defaultTint[i]
+			)
+		) {
+			return materialId;
+		}
+		const tint = this.options.biomeTint(block, pos);
+		if (tint === undefined) {
+			return materialId;
+		}
+		const color = Array.isArray(tint)
+			? new THREE.Color(tint[0], tint[1], tint[2])
+			: new THREE.Color(tint);
+		const tintedId = `${materialId}@biome-${color.getHexString()}`;
+		const materialMap = this.ressourceLoader.materialMap;
+		if (!materialMap.has(tintedId)) {
+			const base = materialMap.get(materialId) as THREE.MeshStandardMaterial;
+			if (!base) {
+				return materialId;
+			}
+			const tinted = base.clone();
+			tinted.color = color;
+			materialMap.set(tintedId, tinted);
+		}
+		return tintedId;
+	}
+
+	public ensureVertexColorMaterials() {
+		const materialMap = this.ressourceLoader.materialMap;
+		for (const [materialId, material] of Object.entries(
+			createVertexColorMaterials()
+		)) {
+			if (!materialMap.has(materialId)) {
+				materialMap.set(materialId, material);
+			}
+		}
+	}
+
+	// merged quads tile the texture so they are left untouched
+	public applyUvInset(components: { [materialId: string]: [any, number[]][] }) {
+		const inset = this.options.uvInset / (this.options.textureResolution ?? 16);
+		for (const materialId in components) {
+			for (const entry of components[materialId]) {
+				const blockComponent = entry[0];
+				if (blockComponent.merged) {
+					continue;
+				}
+				if (!this.insetComponentCache.has(blockComponent)) {
+					this.insetComponentCache.set(
+						blockComponent,
+						insetComponentUvs(blockComponent, inset)
+					);
+				}
+				entry[0] = this.insetComponentCache.get(blockComponent);
+			}
+		}
+	}
+
+	public isFarFromOrigin(offset: { x: number; y: number; z: number }) {
+		const { width, height, length } = this.schematic;
+		return [
+			offset.x,
+			offset.y,
+			offset.z,
+			offset.x + width,
+			offset.y + height,
+			offset.z + length,
+		].some((value) => Math.abs(value) > RECENTER_THRESHOLD);
+	}
+
+	// cells past the schematic edge come from options.neighborOccupancy, so baked
+	// occlusion continues into the neighbouring schematic instead of ending in a seam
 	public isSolid(x: number, y: number, z: number) {
+		const { width, height, length } = this.schematic;
+		if (x < 0 || y < 0 || z < 0 || x >= width || y >= height || z >= length) {
+			return this.options.neighborOccupancy?.(x, y, z) ?? false;
+		}
 		const block = this.schematic.getBlock(new THREE.Vector3(x, y, z));
 		return block && !TRANSPARENT_BLOCKS.has(block.type);
 	}
@@ -151,7 +770,7 @@ export class WorldMeshBuilder {
 		const worldWidth = this.schematic.width;
 		const worldHeight = this.schematic.height;
 		const worldLength = this.schematic.length;
-		const offset = {
+		const offset = this.options.worldOffset ?? {
 			x: -worldWidth / 2,
 			y: 0,
 			z: -worldLength / 2,
@@ -159,27 +778,866 @@ export class WorldMeshBuilder {
 		return { worldWidth, worldHeight, worldLength, offset };
 	}
 
+	public getMeshOffset() {
+		return (
+			this.initializeMeshCreation().offset ?? {
+				x: 0,
+				y: 0,
+				z: 0,
+			}
+		);
+	}
+
+	// whitelisted blocks keep their full meshes, everything else collapses into a
+	// translucent shell, both come out of the same build
+	public async setXrayFilter(xrayFilter: XrayFilter | null) {
+		this.xrayFilter = xrayFilter;
+		const materialMap = this.ressourceLoader.materialMap;
+		if (xrayFilter && !materialMap.has(XRAY_SHELL_MATERIAL_ID)) {
+			materialMap.set(XRAY_SHELL_MATERIAL_ID, createXrayShellMaterial());
+		}
+		for (const chunkKey of this.chunks.keys()) {
+			await this.rebuildChunk(chunkKey);
+		}
+	}
+
+	// the smallest candidate size, sparse areas are merged into larger regions picked
+	// from the density, the partition stays in chunkPartition for the host
+	public getDefaultChunkDimensions() {
+		if (!this.options.autoChunking) {
+			return { chunkWidth: 64, chunkHeight: 64, chunkLength: 64 };
+		}
+		this.chunkPartition = chooseChunkDimensions(
+			this.schematic,
+			this.options.autoChunking === true ? {} : this.options.autoChunking
+		);
+		return this.chunkPartition.chunkDimensions;
+	}
+
 	public async getSchematicMeshes(
-		chunkDimensions = { chunkWidth: 64, chunkHeight: 64, chunkLength: 64 }
+		chunkDimensions = this.getDefaultChunkDimensions()
 	) {
-		const { offset } = this.initializeMeshCreation();
-		const chunks = await this.splitSchemaIntoChunks(chunkDimensions);
-		const totalChunks = chunks.length;
-		let currentChunk = 0;
+		return this.buildAllChunks(chunkDimensions);
+	}
+
+	// everything a build from scratch resets before the first chunk is meshed
+	public startFullBuild(chunkDimensions = this.chunkDimensions) {
+		this.mergeReport = createMergeReport();
+		// speculative builds were made against the previous chunk layout
+		this.discardPrebuiltChunks();
+		// rebuilt chunks keep packing after the regions already handed out
+		this.lightmapPacker = createLightmapPacker();
+		this.chunkDimensions = chunkDimensions;
+		// regions only apply to the grid auto chunking picked them for
+		const partition = this.chunkPartition;
+		this.chunkScales = new Map();
+		if (partition && partition.chunkDimensions === chunkDimensions) {
+			for (const region of partition.regions) {
+				if (region.size > chunkDimensions.chunkWidth) {
+					this.chunkScales.set(
+						region.key,
+						region.size / chunkDimensions.chunkWidth
+					);
+				}
+			}
+		}
+		this.chunks = this.splitSchemaIntoChunks(chunkDimensions);
+		this.chunkHashes = hashSchematicChunks(
+			this.schematic,
+			chunkDimensions,
+			this.chunkScales
+		);
+	}
 
-		for (const chunk of chunks) {
+	// with a frame budget the loop yields back to the main thread whenever a chunk
+	// pushes the current slice past it
+	public async buildAllChunks(
+		chunkDimensions = this.chunkDimensions,
+		frameBudget?: number,
+		token?: unknown
+	) {
+		this.startFullBuild(chunkDimensions);
+		if (frameBudget !== undefined) {
+			for (const chunkKey of this.chunks.keys()) {
+				this.buildTokens.set(chunkKey, token);
+			}
+		}
+		const totalChunks = this.chunks.size;
+		let currentChunk = 0;
+		let sliceStart = performance.now();
+		for (const chunk of this.chunks.values()) {
 			currentChunk++;
-			const chunkMesh = await this.getChunkMesh(
-				chunk,
-				offset ?? { x: 0, y: 0, z: 0 }
+			await this.buildChunk(chunk);
+			logger.info("Chunk", currentChunk, "of", totalChunks, "processed");
+			if (
+				frameBudget !== undefined &&
+				performance.now() - sliceStart > frameBudget
+			) {
+				await yieldToMain();
+				sliceStart = performance.now();
+			}
+		}
+		return this.worldMeshes;
+	}
+
+	public getGroupProvenance(counts?: Map<string, number>) {
+		const paletteIndices = new Map<string, number>();
+		let index = 0;
+		for (const key of this.blockMeshBuilder.blockMeshCache.keys()) {
+			paletteIndices.set(key, index++);
+		}
+		const palette = [...(counts ?? [])].map(([key, blockCount]) => ({
+			key,
+			index: paletteIndices.get(key) ?? -1,
+			blockCount,
+		}));
+		return {
+			blockCount: palette.reduce((sum, entry) => sum + entry.blockCount, 0),
+			palette,
+		};
+	}
+
+	// a block belongs to its type, its occlusion category and its render category
+	public getBlockCategories(block: any) {
+		const categories = [block.type, getBlockCategory(block.type)];
+		const renderCategory = getRenderCategory(
+			block.type,
+			this.options.renderCategories
+		);
+		if (renderCategory) {
+			categories.push(renderCategory);
+		}
+		if (this.blockMeshBuilder.unknownBlocks.has(hashBlockForMap(block))) {
+			categories.push("unknown");
+		}
+		if (this.options.inferCategories) {
+			categories.push(
+				inferPaletteCategory(
+					block,
+					this.options.inferCategories === true
+						? {}
+						: this.options.inferCategories
+				)
+			);
+		}
+		return categories;
+	}
+
+	// rebuilds only the material groups that hold blocks of the category, before or after
+	// the edit, and keeps every other mesh of the chunk as it is
+	public async rebuildCategory(chunkKey: bigint, category: string) {
+		const chunk = this.chunks.get(chunkKey);
+		if (!chunk) {
+			throw new Error(`Unknown chunk ${chunkKey}`);
+		}
+		const existing = this.chunkMeshes.get(chunkKey);
+		// color only groups are keyed by color rather than material
+		if (!existing || this.options.colorOnly) {
+			return this.rebuildChunk(chunkKey);
+		}
+		this.mergeReport = createMergeReport();
+		const materialIds = new Set<string>();
+		for (const mesh of existing) {
+			if (mesh.userData.blockCategories?.includes(category)) {
+				materialIds.add(mesh.name);
+			}
+		}
+		for (const pos of chunk.positions) {
+			const block = this.schematic.getBlock(pos);
+			if (!block || !this.getBlockCategories(block).includes(category)) {
+				continue;
+			}
+			const blockComponents =
+				await this.blockMeshBuilder.getBlockMeshFromCache(block, pos);
+			for (const component of Object.values(blockComponents ?? {}) as any[]) {
+				materialIds.add(component.materialId);
+			}
+		}
+		this.discardPrebuiltChunk(chunkKey);
+		await this.waitForPaletteBakes();
+		const { meshes, translation } = await this.createChunkMeshes(
+			chunk,
+			(materialId) => materialIds.has(materialId)
+		);
+		const replaced = existing.filter((mesh) => materialIds.has(mesh.name));
+		this.renderer.scene.remove(...replaced);
+		for (const mesh of replaced) {
+			mesh.geometry.dispose();
+		}
+		this.meshVersions.set(chunk.key, ++this.meshVersionCounter);
+		this.dirtyChunks.add(chunk.key);
+		this.tagChunkMeshes(chunk, meshes, translation);
+		const chunkMesh = [
+			...existing.filter((mesh) => !materialIds.has(mesh.name)),
+			...meshes,
+		];
+		if (meshes.length > 0) {
+			this.renderer.scene.add(...meshes);
+		}
+		this.worldMeshes = this.worldMeshes.map((worldMesh) =>
+			worldMesh === existing ? chunkMesh : worldMesh
+		);
+		this.chunkMeshes.set(chunk.key, chunkMesh);
+		return chunkMesh;
+	}
+
+	// in schematic coordinates, chunks merged into a region span several chunks per axis
+	public getChunkBounds(chunk: SchematicChunk) {
+		const { chunkWidth, chunkHeight, chunkLength } = this.chunkDimensions;
+		const scale = chunk.scale ?? 1;
+		return {
+			min: [chunk.x * chunkWidth, chunk.y * chunkHeight, chunk.z * chunkLength],
+			size: [chunkWidth * scale, chunkHeight * scale, chunkLength * scale],
+		};
+	}
+
+	public getChunkBox(chunk: SchematicChunk) {
+		const offset = this.getMeshOffset();
+		const { min, size } = this.getChunkBounds(chunk);
+		const boxMin = new THREE.Vector3(
+			min[0] + offset.x,
+			min[1] + offset.y,
+			min[2] + offset.z
+		);
+		return new THREE.Box3(
+			boxMin,
+			boxMin.clone().add(new THREE.Vector3(size[0], size[1], size[2]))
+		);
+	}
+
+	// meshes only the chunks inside the frustum that aren't built yet, nearest first, so
+	// huge schematics can show what's on screen before the rest is built
+	public async buildVisible(
+		frustum: THREE.Frustum | THREE.Plane[],
+		cameraPosition?: THREE.Vector3,
+		chunkDimensions = this.chunkDimensions
+	) {
+		if (this.chunks.size === 0) {
+			this.startFullBuild(chunkDimensions);
+		} else {
+			// the report only covers the chunks meshed by this call
+			this.mergeReport = createMergeReport();
+		}
+		const planes = Array.isArray(frustum)
+			? new THREE.Frustum(...frustum)
+			: frustum;
+		const visible = [...this.chunks.values()]
+			.filter((chunk) => !this.chunkMeshes.has(chunk.key))
+			.map((chunk) => ({ chunk, box: this.getChunkBox(chunk) }))
+			.filter(({ box }) => planes.intersectsBox(box));
+		if (cameraPosition) {
+			visible.sort(
+				(a, b) =>
+					a.box.distanceToPoint(cameraPosition) -
+					b.box.distanceToPoint(cameraPosition)
+			);
+		}
+		for (const { chunk } of visible) {
+			await this.buildChunk(chunk);
+		}
+		return visible.map(({ chunk }) => chunk.key);
+	}
+
+	// same as getSchematicMeshes but yields back to the main thread whenever a chunk
+	// pushes the current slice past frameBudget milliseconds
+	public async getSchematicMeshesAsync(
+		chunkDimensions = this.getDefaultChunkDimensions(),
+		frameBudget: number = 8,
+		token?: unknown
+	) {
+		return this.buildAllChunks(chunkDimensions, frameBudget, token);
+	}
+
+	// the token ends up in the meshes' userData, a build superseded by a later call with
+	// a different token keeps whatever that later build produced
+	public async buildChunkAsync(chunkKey: bigint, token?: unknown) {
+		this.buildTokens.set(chunkKey, token);
+		await yieldToMain();
+		if (this.buildTokens.get(chunkKey) !== token) {
+			return this.chunkMeshes.get(chunkKey) ?? [];
+		}
+		return this.rebuildChunk(chunkKey);
+	}
+
+	public getBuildToken(chunkKey: bigint) {
+		return this.buildTokens.get(chunkKey);
+	}
+
+	public async createChunkMeshes(
+		chunk: SchematicChunk,
+		materialFilter?: (materialId: string) => boolean
+	) {
+		const { translation, meshOffset } = this.getChunkTranslation(chunk);
+		const meshes = await this.getChunkMesh(
+			chunk.positions,
+			meshOffset,
+			0,
+			materialFilter
+		);
+		this.bakeChunkMeshes(chunk, meshes, meshOffset);
+		return { meshes, translation };
+	}
+
+	public getChunkTranslation(chunk: SchematicChunk) {
+		const offset = this.getMeshOffset();
+		const { min } = this.getChunkBounds(chunk);
+		const recenter =
+			this.options.recenterChunks ?? this.isFarFromOrigin(offset);
+		// positions are made relative to the chunk corner and the remainder goes into the
+		// mesh transform, which three.js keeps in double precision
+		const translation = recenter
+			? new THREE.Vector3(
+					min[0] + offset.x,
+					min[1] + offset.y,
+					min[2] + offset.z
+			  )
+			: new THREE.Vector3();
+		const meshOffset = {
+			x: offset.x - translation.x,
+			y: offset.y - translation.y,
+			z: offset.z - translation.z,
+		};
+		return { translation, meshOffset };
+	}
+
+	// per vertex bakes and extra attributes, shared by full detail and lod meshes
+	public bakeChunkMeshes(
+		chunk: SchematicChunk,
+		meshes: THREE.Mesh[],
+		meshOffset: { x: number; y: number; z: number }
+	) {
+		if (this.options.rayTracedAo) {
+			const aoOptions =
+				this.options.rayTracedAo === true ? {} : this.options.rayTracedAo;
+			for (const mesh of meshes) {
+				bakeRayTracedAo(
+					mesh.geometry,
+					(x, y, z) => this.isSolid(x, y, z),
+					[meshOffset.x, meshOffset.y, meshOffset.z],
+					aoOptions
+				);
+				this.enableVertexColors(mesh);
+			}
+		}
+		if (this.options.heightTint) {
+			for (const mesh of meshes) {
+				bakeHeightTint(
+					mesh.geometry,
+					[meshOffset.x, meshOffset.y, meshOffset.z],
+					this.options.heightTint
+				);
+				this.enableVertexColors(mesh);
+			}
+		}
+		if (this.options.heatmap) {
+			for (const mesh of meshes) {
+				bakeHeatmap(
+					mesh.geometry,
+					[meshOffset.x, meshOffset.y, meshOffset.z],
+					this.options.heatmap
+				);
+				this.enableVertexColors(mesh);
+			}
+		}
+		if (this.options.sunShadows) {
+			const sunOptions =
+				this.options.sunShadows === true ? {} : this.options.sunShadows;
+			for (const mesh of meshes) {
+				bakeSunShadows(
+					mesh.geometry,
+					(x, y, z) => this.isSolid(x, y, z),
+					[meshOffset.x, meshOffset.y, meshOffset.z],
+					sunOptions
+				);
+				this.enableVertexColors(mesh);
+			}
+		}
+		if (this.options.lightmapLayout) {
+			for (const mesh of meshes) {
+				const lightmap = generateLightmapUvs(
+					mesh.geometry,
+					this.options.lightmapLayout,
+					this.lightmapPacker
+				);
+				mesh.userData.lightmap = lightmap;
+				if (lightmap.overflow) {
+					emitWarning(this.options, {
+						type: "lightmap_overflow",
+						message: `Lightmap regions of ${mesh.name} need ${
+							lightmap.usedHeight
+						} texel rows`,
+						details: { chunkKey: chunk.key, materialId: mesh.name },
+					});
+				}
+			}
+		}
+		if (this.options.voxelAo) {
+			const { minLight } =
+				this.options.voxelAo === true ? {} : this.options.voxelAo;
+			for (const mesh of meshes) {
+				// without any ao face the attribute is missing and would read as fully dark
+				if (mesh.geometry.getAttribute("ao")) {
+					mesh.material = this.getVoxelAoMaterial(
+						mesh.material as THREE.Material,
+						minLight
+					);
+				}
+			}
+		}
+		if (this.options.faceIds) {
+			for (const mesh of meshes) {
+				if (packFaceIds(mesh.geometry)) {
+					mesh.material = this.getFaceIdMaterial(
+						mesh.material as THREE.Material
+					);
+				}
+			}
+		}
+	}
+
+	// a low poly stand in for distant chunks, lod level n merges 2^n blocks per axis into
+	// one cube, the host swaps it with the full chunk meshes as the camera moves
+	public async buildChunkLod(chunkKey: bigint, lodLevel: number) {
+		const chunk = this.chunks.get(chunkKey);
+		if (!chunk || lodLevel <= 0) {
+			return [];
+		}
+		this.mergeReport = createMergeReport();
+		const factor = 2 ** Math.min(lodLevel, 3);
+		const positions = chunk.positions.filter((pos) => {
+			const block = this.schematic.getBlock(pos);
+			if (!block || INVISIBLE_BLOCKS.has(block.type)) {
+				return false;
+			}
+			const category = getRenderCategory(
+				block.type,
+				this.options.renderCategories
 			);
-			if (chunkMesh.length === 0) {
+			const maxLodLevel =
+				category && this.options.categoryDistances?.[category]?.maxLodLevel;
+			return lodLevel <= (maxLodLevel ?? Infinity);
+		});
+		const cells = downsampleBlocks(
+			this.schematic,
+			positions,
+			factor,
+			this.options.lodFillRatio !== undefined
+				? this.options.lodFillRatio * factor ** 3
+				: undefined
+		);
+		await this.waitForPaletteBakes();
+		const { translation, meshOffset } = this.getChunkTranslation(chunk);
+		let components = await buildLodComponents(
+			cells,
+			factor,
+			meshOffset,
+			(block) => this.blockMeshBuilder.getBlockMeshFromCache(block),
+			(materialId, block, pos) =>
+				this.getComponentGroupId(materialId, block, pos)
+		);
+		if (this.options.colorOnly) {
+			components = collapseVertexColorGroups(components);
+			this.ensureVertexColorMaterials();
+		}
+		const meshes: THREE.Mesh[] = this.ressourceLoader.createMeshesFromBlocks(
+			components,
+			{
+				chunkMeshCreation: {
+					total_time: 0,
+					arrayCreation: 0,
+					arrayAllocation: 0,
+					indexCalculation: 0,
+					vertexTranslation: 0,
+					geometryCreation: 0,
+					materialRetrieval: 0,
+					slicing: 0,
+				},
+			}
+		);
+		this.bakeChunkMeshes(chunk, meshes, meshOffset);
+		this.tagChunkMeshes(chunk, meshes, translation);
+		for (const mesh of meshes) {
+			mesh.userData.lodLevel = lodLevel;
+		}
+		return meshes;
+	}
+
+	public getVoxelAoMaterial(material: THREE.Material, minLight?: number) {
+		let aoMaterial = this.voxelAoMaterials.get(material);
+		if (!aoMaterial) {
+			aoMaterial = createVoxelAoMaterial(material, minLight);
+			this.voxelAoMaterials.set(material, aoMaterial);
+		}
+		return aoMaterial;
+	}
+
+	public getFaceIdMaterial(material: THREE.Material) {
+		let faceIdMaterial = this.faceIdMaterials.get(material);
+		if (!faceIdMaterial) {
+			faceIdMaterial = createFaceIdMaterial(material);
+			this.faceIdMaterials.set(material, faceIdMaterial);
+		}
+		return faceIdMaterial;
+	}
+
+	// builds requested while a palette bake runs wait for it rather than baking the
+	// same blocks a second time, blocks a failed bake missed are meshed by the build
+	public async waitForPaletteBakes() {
+		while (this.paletteBakes.size > 0) {
+			await Promise.all(
+				[...this.paletteBakes].map((job) => job.promise.catch(() => 0))
+			);
+		}
+	}
+
+	public bakePalette(
+		blocks: any[],
+		options: { frameBudget?: number; rotations?: BlockRotation[] } = {}
+	) {
+		const job = startPaletteBake(this.blockMeshBuilder, blocks, options);
+		this.paletteBakes.add(job);
+		job.onComplete(() => this.paletteBakes.delete(job));
+		return job;
+	}
+
+	public async buildChunk(chunk: SchematicChunk) {
+		const token = this.buildTokens.get(chunk.key);
+		await this.waitForPaletteBakes();
+		const prebuilt = this.prebuiltChunks.get(chunk.key);
+		this.prebuiltChunks.delete(chunk.key);
+		const { meshes: chunkMesh, translation } =
+			prebuilt ?? (await this.createChunkMeshes(chunk));
+		// a newer build of the chunk was requested while this one was meshing
+		if (this.buildTokens.get(chunk.key) !== token) {
+			for (const mesh of chunkMesh) {
+				mesh.geometry.dispose();
+			}
+			return this.chunkMeshes.get(chunk.key) ?? [];
+		}
+		if (this.options.attributeUpdates) {
+			const updated = this.updateChunkAttributes(chunk.key, chunkMesh);
+			if (updated) {
+				return updated;
+			}
+		}
+		this.removeChunkMeshes(chunk.key);
+		this.meshVersions.set(chunk.key, ++this.meshVersionCounter);
+		this.dirtyChunks.add(chunk.key);
+		if (chunkMesh.length === 0) {
+			return chunkMesh;
+		}
+		this.tagChunkMeshes(chunk, chunkMesh, translation);
+		this.renderer.scene.add(...chunkMesh);
+		this.worldMeshes.push(chunkMesh);
+		this.chunkMeshes.set(chunk.key, chunkMesh);
+		this.meshTranslations.set(chunk.key, translation);
+		return chunkMesh;
+	}
+
+	// when a rebuild only changed colors or light the existing buffers are patched in place
+	// and the changed attribute names recorded, so hosts can upload just those
+	public updateChunkAttributes(chunkKey: bigint, meshes: THREE.Mesh[]) {
+		const existing = this.chunkMeshes.get(chunkKey);
+		if (!existing || existing.length !== meshes.length) {
+			return undefined;
+		}
+		const changes: [THREE.Mesh, THREE.Mesh, string[]][] = [];
+		for (const mesh of meshes) {
+			const previous = existing.find(
+				(candidate) => candidate.name === mesh.name
+			);
+			if (!previous) {
+				return undefined;
+			}
+			const changed = diffGeometryAttributes(previous.geometry, mesh.geometry);
+			if (!changed) {
+				return undefined;
+			}
+			changes.push([previous, mesh, changed]);
+		}
+		const version = this.meshVersionCounter + 1;
+		const dirty = this.dirtyAttributes.get(chunkKey) ?? new Map();
+		for (const [previous, mesh, changed] of changes) {
+			copyGeometryAttributes(previous.geometry, mesh.geometry, changed);
+			mesh.geometry.dispose();
+			if (changed.length === 0) {
 				continue;
 			}
-			this.renderer.scene.add(...chunkMesh);
-			this.worldMeshes.push(chunkMesh);
-			console.log("Chunk", currentChunk, "of", totalChunks, "processed");
+			previous.userData.version = version;
+			const names = dirty.get(previous.name) ?? new Set<string>();
+			changed.forEach((name) => names.add(name));
+			dirty.set(previous.name, names);
 		}
-		return this.worldMeshes;
+		if (dirty.size > 0) {
+			this.meshVersions.set(chunkKey, ++this.meshVersionCounter);
+			this.dirtyAttributes.set(chunkKey, dirty);
+			this.dirtyChunks.add(chunkKey);
+		}
+		return existing;
+	}
+
+	public tagChunkMeshes(
+		chunk: SchematicChunk,
+		meshes: THREE.Mesh[],
+		translation: THREE.Vector3
+	) {
+		for (const mesh of meshes) {
+			mesh.position.copy(translation);
+			mesh.userData.translation = translation.toArray();
+			mesh.userData.chunkKey = chunk.key;
+			mesh.userData.version = this.meshVersionCounter;
+			mesh.userData.buildToken = this.buildTokens.get(chunk.key);
+			mesh.userData.boundingBox = mesh.geometry.boundingBox
+				?.clone()
+				.translate(translation);
+			mesh.userData.render = getRenderMetadata(
+				mesh,
+				this.options.inferCategories
+					? {
+							...INFERRED_CATEGORY_SETTINGS,
+							...this.options.categoryRenderSettings,
+					  }
+					: this.options.categoryRenderSettings
+			);
+			mesh.renderOrder = mesh.userData.render.renderOrder;
+		}
+	}
+
+	public removeChunkMeshes(chunkKey: bigint) {
+		const chunkMesh = this.chunkMeshes.get(chunkKey);
+		if (!chunkMesh) {
+			return;
+		}
+		this.renderer.scene.remove(...chunkMesh);
+		for (const mesh of chunkMesh) {
+			mesh.geometry.dispose();
+		}
+		this.worldMeshes = this.worldMeshes.filter(
+			(meshes) => meshes !== chunkMesh
+		);
+		this.chunkMeshes.delete(chunkKey);
+		this.meshTranslations.delete(chunkKey);
+	}
+
+	public getChunkVisibilityGraph() {
+		const graph: Map<bigint, ChunkVisibility> = new Map();
+		for (const chunk of this.chunks.values()) {
+			graph.set(
+				chunk.key,
+				computeChunkVisibility(this.schematic, chunk, this.chunkDimensions)
+			);
+		}
+		return graph;
+	}
+
+	public getChunkKeyForPosition(pos: { x: number; y: number; z: number }) {
+		const { chunkWidth, chunkHeight, chunkLength } = this.chunkDimensions;
+		return findChunkRegionKey(
+			this.chunkScales,
+			Math.floor(pos.x / chunkWidth),
+			Math.floor(pos.y / chunkHeight),
+			Math.floor(pos.z / chunkLength)
+		);
+	}
+
+	// call after editing blocks of the schematic, the chunk must be part of the last build
+	public async rebuildChunk(chunkKey: bigint) {
+		const chunk = this.chunks.get(chunkKey);
+		if (!chunk) {
+			throw new Error(`Unknown chunk ${chunkKey}`);
+		}
+		this.discardPrebuiltChunk(chunkKey);
+		this.mergeReport = createMergeReport();
+		return this.buildChunk(chunk);
+	}
+
+	public getNeighborChunkKeys(chunkKey: bigint) {
+		const neighbors = new Set<bigint>();
+		for (const direction of [
+			[1, 0, 0],
+			[-1, 0, 0],
+			[0, 1, 0],
+			[0, -1, 0],
+			[0, 0, 1],
+			[0, 0, -1],
+		]) {
+			for (const key of this.getChunkKeysAcross(chunkKey, direction)) {
+				neighbors.add(key);
+			}
+		}
+		return [...neighbors];
+	}
+
+	// the chunks touching one face, a region can border several smaller ones on each
+	public getChunkKeysAcross(chunkKey: bigint, direction: number[]) {
+		const { x, y, z } = unpackChunkKey(chunkKey);
+		const start = [x, y, z];
+		const scale = this.chunks.get(chunkKey)?.scale ?? 1;
+		const axis = direction.findIndex((step) => step !== 0);
+		const [u, v] = [0, 1, 2].filter((i) => i !== axis);
+		const cell = start.slice();
+		cell[axis] = direction[axis] < 0 ? start[axis] - 1 : start[axis] + scale;
+		const keys = new Set<bigint>();
+		for (let a = 0; a < scale; a++) {
+			for (let b = 0; b < scale; b++) {
+				cell[u] = start[u] + a;
+				cell[v] = start[v] + b;
+				const key = findChunkRegionKey(
+					this.chunkScales,
+					cell[0],
+					cell[1],
+					cell[2]
+				);
+				if (this.chunks.has(key)) {
+					keys.add(key);
+				}
+			}
+		}
+		return [...keys];
+	}
+
+	public getChunkLodLevel(chunkKey: bigint) {
+		return this.chunkLodLevels.get(chunkKey) ?? 0;
+	}
+
+	// the host reports which detail level it shows for a chunk, full detail neighbours
+	// are rebuilt so their skirts follow, returns the rebuilt chunk keys
+	public async setChunkLodLevel(chunkKey: bigint, lodLevel: number) {
+		if (this.getChunkLodLevel(chunkKey) === lodLevel) {
+			return [];
+		}
+		this.chunkLodLevels.set(chunkKey, lodLevel);
+		if (!(this.options.chunkSkirtDepth > 0)) {
+			return [];
+		}
+		const rebuilt: bigint[] = [];
+		for (const neighbor of this.getNeighborChunkKeys(chunkKey)) {
+			if (
+				this.chunkMeshes.has(neighbor) &&
+				this.getChunkLodLevel(neighbor) === 0
+			) {
+				await this.rebuildChunk(neighbor);
+				rebuilt.push(neighbor);
+			}
+		}
+		return rebuilt;
+	}
+
+	// speculative builds are kept out of the scene until buildChunk asks for them
+	public enqueuePrebuild(chunkKeys: bigint[]) {
+		for (const key of chunkKeys) {
+			if (
+				this.chunks.has(key) &&
+				!this.chunkMeshes.has(key) &&
+				!this.prebuiltChunks.has(key) &&
+				!this.prebuildQueue.includes(key)
+			) {
+				this.prebuildQueue.push(key);
+			}
+		}
+	}
+
+	// meant to be called once per frame with whatever time is left in the frame
+	public async processPrebuildQueue(timeBudget: number) {
+		const start = performance.now();
+		let built = 0;
+		if (this.prebuildQueue.length > 0) {
+			this.mergeReport = createMergeReport();
+		}
+		while (
+			this.prebuildQueue.length > 0 &&
+			performance.now() - start < timeBudget
+		) {
+			const key = this.prebuildQueue.shift()!;
+			const chunk = this.chunks.get(key);
+			if (!chunk || this.chunkMeshes.has(key)) {
+				continue;
+			}
+			await this.waitForPaletteBakes();
+			this.prebuiltChunks.set(key, await this.createChunkMeshes(chunk));
+			built++;
+		}
+		return built;
+	}
+
+	public discardPrebuiltChunk(chunkKey: bigint) {
+		const prebuilt = this.prebuiltChunks.get(chunkKey);
+		if (!prebuilt) {
+			return;
+		}
+		for (const mesh of prebuilt.meshes) {
+			mesh.geometry.dispose();
+		}
+		this.prebuiltChunks.delete(chunkKey);
+	}
+
+	public discardPrebuiltChunks() {
+		for (const chunkKey of [...this.prebuiltChunks.keys()]) {
+			this.discardPrebuiltChunk(chunkKey);
+		}
+		this.prebuildQueue = [];
+	}
+
+	// for long sessions whose peak build was much larger than what is shown now, returns
+	// the number of cache entries released
+	public compact(
+		options: { dropBlockMeshes?: boolean; dropResourceCaches?: boolean } = {}
+	) {
+		let released = this.prebuiltChunks.size + this.prebuildQueue.length;
+		this.discardPrebuiltChunks();
+		this.insetComponentCache = new WeakMap();
+		this.lastChunkTimes = undefined;
+		// tokens of chunks still in the layout may belong to builds in flight
+		for (const chunkKey of [...this.buildTokens.keys()]) {
+			if (!this.chunks.has(chunkKey)) {
+				this.buildTokens.delete(chunkKey);
+				released++;
+			}
+		}
+		released += this.blockMeshBuilder.compact(options);
+		if (options.dropResourceCaches) {
+			released +=
+				this.ressourceLoader.stringCache.size +
+				this.ressourceLoader.blobCache.size;
+			this.ressourceLoader.stringCache.clear();
+			this.ressourceLoader.blobCache.clear();
+		}
+		return released;
+	}
+
+	public getMeshVersion(chunkKey: bigint) {
+		return this.meshVersions.get(chunkKey) ?? 0;
+	}
+
+	// chunks rebuilt since the last getDirtyChunks call, without clearing them
+	public getDirtyChunkBoxes() {
+		return [...this.dirtyChunks]
+			.map((chunkKey) => this.chunks.get(chunkKey))
+			.filter((chunk): chunk is SchematicChunk => chunk !== undefined)
+			.map((chunk) => this.getChunkBox(chunk));
+	}
+
+	// returns the chunks rebuilt since the last call
+	public getDirtyChunks() {
+		const dirtyChunks = [...this.dirtyChunks];
+		this.dirtyChunks.clear();
+		return dirtyChunks;
+	}
+
+	// attribute level changes since the last call, chunks that were replaced outright
+	// only show up in getDirtyChunks
+	public getDirtyAttributes() {
+		const dirtyAttributes: {
+			chunkKey: bigint;
+			materialId: string;
+			attributes: string[];
+		}[] = [];
+		for (const [chunkKey, groups] of this.dirtyAttributes) {
+			for (const [materialId, names] of groups) {
+				dirtyAttributes.push({ chunkKey, materialId, attributes: [...names] });
+			}
+		}
+		this.dirtyAttributes.clear();
+		return dirtyAttributes;
 	}
 }
@@ -0,0 +1,25 @@
+import * as THREE from "three";
+import { createCubeComponents } from "./utils";
+import type { HighlightPredicate } from "./types";
+
+export const XRAY_SHELL_MATERIAL_ID = "xray-shell";
+
+export interface XrayFilter {
+	predicate: HighlightPredicate;
+	paletteKeys?: Set<string>;
+}
+
+export function createXrayShellMaterial() {
+	return new THREE.MeshBasicMaterial({
+		color: 0x9bb4d6,
+		transparent: true,
+		opacity: 0.08,
+		depthWrite: false,
+	});
+}
+
+// every hidden block becomes the same plain unit cube, so the shell shares one material
+// and greedy meshing can collapse it to a handful of quads
+export function createXrayShellComponents() {
+	return createCubeComponents(XRAY_SHELL_MATERIAL_ID);
+}